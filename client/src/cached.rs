@@ -0,0 +1,258 @@
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use shared_types::{ConfigData, ConfigKey};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::ConfigClient;
+
+/// First delay between polls in [`CachedConfigClient::wait_for_version_change`]
+const INITIAL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Upper bound on the delay between polls
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Entries kept by [`CachedConfigClient::new`] before the least recently used is evicted
+pub const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+static INSTANCE: OnceCell<CachedConfigClient> = OnceCell::new();
+
+/// What a cache entry holds: the current version, or a specific (immutable) one
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    Current(ConfigKey),
+    Version(ConfigKey, String),
+}
+
+struct CacheEntry {
+    data: ConfigData,
+    /// Value of the access clock when this entry was last read or written
+    last_used: AtomicU64,
+}
+
+/// Process-wide caching client, shared through [`CachedConfigClient::instance`]
+pub struct CachedConfigClient {
+    client: ConfigClient,
+    cache: RwLock<HashMap<CacheKey, CacheEntry>>,
+    capacity: usize,
+    /// Monotonic counter stamped on entries as they are used
+    clock: AtomicU64,
+    evictions: AtomicU64,
+    /// The task started by [`start_auto_refresh`](Self::start_auto_refresh)
+    auto_refresh: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl CachedConfigClient {
+    pub fn new(base_url: impl Into<String>) -> Result<Self> {
+        Self::with_capacity(base_url, DEFAULT_CACHE_CAPACITY)
+    }
+
+    /// A client that holds at most `capacity` entries (at least one), current
+    /// and versioned alike, evicting the least recently used beyond that
+    pub fn with_capacity(base_url: impl Into<String>, capacity: usize) -> Result<Self> {
+        Ok(Self {
+            client: ConfigClient::new(base_url)?,
+            cache: RwLock::new(HashMap::new()),
+            capacity: capacity.max(1),
+            clock: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            auto_refresh: Mutex::new(None),
+        })
+    }
+
+    /// Create the shared instance holding at most `capacity` entries; fails if
+    /// it was already initialized
+    pub fn initialize(base_url: impl Into<String>, capacity: usize) -> Result<&'static Self> {
+        INSTANCE
+            .set(Self::with_capacity(base_url, capacity)?)
+            .map_err(|_| anyhow::anyhow!("CachedConfigClient is already initialized"))?;
+        Self::instance()
+    }
+
+    /// The shared instance, creating it with `base_url` on first use
+    ///
+    /// Safe to call concurrently and repeatedly: exactly one instance is ever
+    /// created, and later calls return it regardless of the URL they pass.
+    pub fn get_or_init(base_url: impl Into<String>) -> Result<&'static Self> {
+        INSTANCE.get_or_try_init(|| Self::new(base_url))
+    }
+
+    /// The shared instance; fails if `initialize` has not been called
+    pub fn instance() -> Result<&'static Self> {
+        INSTANCE
+            .get()
+            .ok_or_else(|| anyhow::anyhow!("CachedConfigClient is not initialized"))
+    }
+
+    /// Number of entries currently cached
+    pub async fn cache_size(&self) -> usize {
+        self.cache.read().await.len()
+    }
+
+    /// Number of entries evicted to stay within capacity since creation
+    pub fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+
+    /// The current version of `key`, from the cache when present
+    pub async fn get_config(&self, key: &ConfigKey) -> Result<ConfigData> {
+        if let Some(data) = self.cached(&CacheKey::Current(key.clone())).await {
+            return Ok(data);
+        }
+
+        self.refresh(key).await
+    }
+
+    /// A specific version of `key`. Versions never change, so once fetched
+    /// they are always served from the cache.
+    pub async fn get_config_version(&self, key: &ConfigKey, version: &str) -> Result<ConfigData> {
+        let cache_key = CacheKey::Version(key.clone(), version.to_string());
+        if let Some(data) = self.cached(&cache_key).await {
+            return Ok(data);
+        }
+
+        let data = self.client.get_config_version(key, version).await?;
+        self.store(cache_key, data.clone()).await;
+        Ok(data)
+    }
+
+    /// Fetch the current version of `key` and replace the cached copy
+    pub async fn refresh(&self, key: &ConfigKey) -> Result<ConfigData> {
+        let data = self.client.fetch_config(key).await?;
+        self.store(CacheKey::Current(key.clone()), data.clone())
+            .await;
+        Ok(data)
+    }
+
+    pub async fn clear_cache(&self) {
+        self.cache.write().await.clear();
+    }
+
+    /// Poll `key` until its version differs from `current`, backing off
+    /// exponentially between polls. The changed config is cached and returned;
+    /// if nothing changes within `timeout` this fails.
+    pub async fn wait_for_version_change(
+        &self,
+        key: &ConfigKey,
+        current: &str,
+        timeout: Duration,
+    ) -> Result<ConfigData> {
+        let started = Instant::now();
+        let mut interval = INITIAL_POLL_INTERVAL;
+
+        loop {
+            let data = self.client.fetch_config(key).await?;
+            if data.version != current {
+                self.store(CacheKey::Current(key.clone()), data.clone())
+                    .await;
+                return Ok(data);
+            }
+
+            let remaining = timeout.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                anyhow::bail!("{key} still at version {current} after {timeout:?}");
+            }
+
+            tokio::time::sleep(interval.min(remaining)).await;
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+
+    /// Re-fetch every cached current version each `interval` in a background
+    /// task, replacing entries whole so readers see either the old or the new
+    /// config. A failed fetch keeps the last good value. Restarts the task if
+    /// one is already running.
+    pub fn start_auto_refresh(&'static self, interval: Duration) {
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately; the cache is fresh then
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                self.refresh_cached().await;
+            }
+        });
+        if let Ok(mut running) = self.auto_refresh.lock()
+            && let Some(previous) = running.replace(task)
+        {
+            previous.abort();
+        }
+    }
+
+    /// Cancel the task started by [`start_auto_refresh`](Self::start_auto_refresh), if any
+    pub fn stop_auto_refresh(&self) {
+        if let Ok(mut running) = self.auto_refresh.lock()
+            && let Some(task) = running.take()
+        {
+            task.abort();
+        }
+    }
+
+    /// Re-fetch each cached current version once, updating entries in place
+    async fn refresh_cached(&self) {
+        let keys: Vec<ConfigKey> = self
+            .cache
+            .read()
+            .await
+            .keys()
+            .filter_map(|cache_key| match cache_key {
+                CacheKey::Current(key) => Some(key.clone()),
+                CacheKey::Version(..) => None,
+            })
+            .collect();
+
+        for key in keys {
+            match self.client.fetch_config(&key).await {
+                Ok(data) => {
+                    // Only update entries still cached; a refresh is not a use
+                    let mut cache = self.cache.write().await;
+                    if let Some(entry) = cache.get_mut(&CacheKey::Current(key)) {
+                        entry.data = data;
+                    }
+                }
+                Err(e) => warn!("Keeping cached {key} after failed refresh: {e:#}"),
+            }
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// The cached entry for `cache_key`, marking it as recently used
+    async fn cached(&self, cache_key: &CacheKey) -> Option<ConfigData> {
+        let cache = self.cache.read().await;
+        let entry = cache.get(cache_key)?;
+        entry.last_used.store(self.tick(), Ordering::Relaxed);
+        Some(entry.data.clone())
+    }
+
+    /// Cache `data`, evicting the least recently used entry when full
+    async fn store(&self, cache_key: CacheKey, data: ConfigData) {
+        let mut cache = self.cache.write().await;
+
+        if !cache.contains_key(&cache_key) && cache.len() >= self.capacity {
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used.load(Ordering::Relaxed))
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+                debug!(evicted = ?oldest, capacity = self.capacity, "Evicted cached config");
+            }
+        }
+
+        cache.insert(
+            cache_key,
+            CacheEntry {
+                data,
+                last_used: AtomicU64::new(self.tick()),
+            },
+        );
+    }
+}
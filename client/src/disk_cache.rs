@@ -0,0 +1,145 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use shared_types::{ConfigData, ConfigKey};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::{CachedConfig, ConfigClient};
+
+/// Layout of the files written under a disk cache; files of any other format
+/// are ignored rather than parsed
+const DISK_CACHE_FORMAT: u32 = 1;
+
+/// One cached config as stored on disk
+#[derive(Serialize, Deserialize)]
+struct DiskEntry {
+    format: u32,
+    key: ConfigKey,
+    etag: Option<String>,
+    data: ConfigData,
+}
+
+impl ConfigClient {
+    /// Keep a copy of every fetched config under `path`, one JSON file per
+    /// config at its [`ConfigKey::to_path`], and start out with whatever an
+    /// earlier run left there.
+    ///
+    /// Configs loaded from disk are revalidated on first read. If the server
+    /// cannot be reached then, the copy from disk is served instead, so an
+    /// application can start with its last-known config during an outage.
+    pub fn with_disk_cache(mut self, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        std::fs::create_dir_all(&path)?;
+
+        let mut entries = Vec::new();
+        load_dir(&path, &mut entries)?;
+        {
+            let mut cache = self
+                .cache
+                .try_write()
+                .map_err(|_| anyhow::anyhow!("Cache is in use"))?;
+            for entry in entries {
+                let mut cached = CachedConfig::new(entry.data, entry.etag);
+                cached.from_disk = true;
+                cache.entry(entry.key.to_string()).or_insert(cached);
+            }
+        }
+
+        self.disk_cache = Some(path);
+        Ok(self)
+    }
+
+    /// Write `cached` to the disk cache, if there is one. Failing to is only
+    /// logged; the config was fetched fine.
+    pub(crate) async fn persist(&self, key: &ConfigKey, cached: &CachedConfig) {
+        let Some(dir) = &self.disk_cache else {
+            return;
+        };
+        // Key components become path segments, so must not climb out of `dir`
+        if key.validate().is_err() {
+            return;
+        }
+
+        let file = dir.join(format!("{}.json", key.to_path()));
+        let entry = DiskEntry {
+            format: DISK_CACHE_FORMAT,
+            key: key.clone(),
+            etag: cached.etag.clone(),
+            data: cached.data.clone(),
+        };
+        if let Err(e) = write_atomically(&file, &entry).await {
+            warn!("Could not write {key} to the disk cache: {e}");
+        }
+    }
+}
+
+/// Write through a temporary file, so a crash never leaves a half-written
+/// entry behind
+async fn write_atomically(file: &Path, entry: &DiskEntry) -> Result<()> {
+    if let Some(parent) = file.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let temp = file.with_extension("json.tmp");
+    tokio::fs::write(&temp, serde_json::to_vec_pretty(entry)?).await?;
+    tokio::fs::rename(&temp, file).await?;
+    Ok(())
+}
+
+/// Collect every readable entry of the current format under `dir`
+fn load_dir(dir: &Path, entries: &mut Vec<DiskEntry>) -> Result<()> {
+    for item in std::fs::read_dir(dir)? {
+        let path = item?.path();
+        if path.is_dir() {
+            load_dir(&path, entries)?;
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            if let Some(entry) = read_entry(&path) {
+                entries.push(entry);
+            } else {
+                debug!("Ignoring unreadable disk cache entry {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_entry(path: &Path) -> Option<DiskEntry> {
+    let value: serde_json::Value = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+    if value["format"] != DISK_CACHE_FORMAT {
+        return None;
+    }
+    serde_json::from_value(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_other_formats_ignored() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        let key = ConfigKey::new("myapp", "dev", "flags");
+        let client = ConfigClient::new("http://localhost:3000")?.with_disk_cache(dir)?;
+        let data = ConfigData {
+            content: serde_json::json!({"on": true}),
+            schema: serde_json::json!({}),
+            version: "v1".to_string(),
+            content_type: None,
+        };
+        client
+            .persist(&key, &CachedConfig::new(data, Some("\"v1\"".to_string())))
+            .await;
+
+        let file = dir.join("myapp/dev/flags.json");
+        let mut loaded = Vec::new();
+        load_dir(dir, &mut loaded)?;
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].key, key);
+
+        let mut stored: serde_json::Value = serde_json::from_slice(&std::fs::read(&file)?)?;
+        stored["format"] = serde_json::json!(DISK_CACHE_FORMAT + 1);
+        std::fs::write(&file, stored.to_string())?;
+        assert!(read_entry(&file).is_none());
+        Ok(())
+    }
+}
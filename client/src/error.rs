@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+/// Failures callers may want to handle differently from a generic error;
+/// recover them from an [`anyhow::Error`] with `downcast_ref`
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// The configuration changed on the server since the version the request
+    /// was conditional on
+    #[error("Conflict: {0}")]
+    Conflict(String),
+}
@@ -1,118 +1,601 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use futures::stream::{self, Stream, TryStreamExt};
 use reqwest::{Client as ReqwestClient, StatusCode};
-use shared_types::{ConfigData, ConfigKey, VersionInfo};
-use std::collections::HashMap;
+use serde::de::DeserializeOwned;
+use shared_types::{
+    ConfigData, ConfigDiff, ConfigKey, ConfigOrigin, ConfigSummary, TimelineStep, VersionInfo,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+mod cached;
+mod disk_cache;
+mod error;
+mod watch;
+
+pub use cached::{CachedConfigClient, DEFAULT_CACHE_CAPACITY};
+pub use error::ClientError;
+
+/// A cached configuration and the `ETag` the server sent with it, if any
+#[derive(Clone)]
+struct CachedConfig {
+    data: ConfigData,
+    etag: Option<String>,
+    /// When the entry was fetched or last revalidated
+    cached_at: Instant,
+    /// Loaded from the disk cache and not yet revalidated with the server
+    from_disk: bool,
+}
+
+impl CachedConfig {
+    fn new(data: ConfigData, etag: Option<String>) -> Self {
+        Self {
+            data,
+            etag,
+            cached_at: Instant::now(),
+            from_disk: false,
+        }
+    }
+
+    fn is_fresh(&self, ttl: Option<Duration>) -> bool {
+        !self.from_disk && ttl.is_none_or(|ttl| self.cached_at.elapsed() < ttl)
+    }
+}
+
+/// Outcome of a possibly conditional fetch of a config's current version
+enum Fetched {
+    Modified(CachedConfig),
+    NotModified,
+}
 
 pub struct ConfigClient {
     client: ReqwestClient,
     base_url: String,
-    cache: Arc<RwLock<HashMap<String, ConfigData>>>,
+    cache: Arc<RwLock<HashMap<String, CachedConfig>>>,
+    write_through: bool,
+    /// Application and environment the config-name shortcuts use
+    defaults: Option<(String, String)>,
+    /// How long a cached config is served before being revalidated; forever if `None`
+    cache_ttl: Option<Duration>,
+    retry: RetryPolicy,
+    /// Answer with the cached config when revalidating it fails because the
+    /// server is unreachable or erroring
+    serve_stale_on_error: bool,
+    /// Directory fetched configs are mirrored to, set by
+    /// [`with_disk_cache`](Self::with_disk_cache)
+    disk_cache: Option<PathBuf>,
 }
 
-impl ConfigClient {
-    pub fn new(base_url: impl Into<String>) -> Result<Self> {
-        let client = ReqwestClient::builder()
-            .timeout(Duration::from_secs(30))
-            .build()?;
+/// How config reads are retried on transient failures
+#[derive(Debug, Clone, Copy, Default)]
+struct RetryPolicy {
+    /// Retries after the first attempt; none by default
+    max_retries: u32,
+    /// Delay before the first retry, doubled for each one after
+    base_delay: Duration,
+}
+
+/// Builder for a [`ConfigClient`] with non-default settings
+pub struct ConfigClientBuilder {
+    base_url: String,
+    #[cfg(feature = "compression")]
+    compression: bool,
+    write_through: bool,
+    defaults: Option<(String, String)>,
+    api_key: Option<String>,
+    cache_ttl: Option<Duration>,
+    retry: RetryPolicy,
+    serve_stale_on_error: bool,
+}
+
+impl ConfigClientBuilder {
+    /// Send `Accept-Encoding` and transparently decompress gzip/brotli
+    /// responses (enabled by default)
+    #[cfg(feature = "compression")]
+    #[must_use]
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// After a successful `put_config`, cache the written content under the
+    /// version the server reports instead of dropping the cached entry
+    #[must_use]
+    pub fn write_through(mut self, enabled: bool) -> Self {
+        self.write_through = enabled;
+        self
+    }
+
+    /// Application and environment that [`ConfigClient::get`] and
+    /// [`ConfigClient::put`] address configs in by name
+    #[must_use]
+    pub fn defaults(
+        mut self,
+        application: impl Into<String>,
+        environment: impl Into<String>,
+    ) -> Self {
+        self.defaults = Some((application.into(), environment.into()));
+        self
+    }
 
-        Ok(Self {
-            client,
-            base_url: base_url.into().trim_end_matches('/').to_string(),
+    /// Revalidate a cached config with the server once it is older than
+    /// `ttl`; zero keeps entries until refreshed, as by default
+    #[must_use]
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl).filter(|ttl| !ttl.is_zero());
+        self
+    }
+
+    /// Retry config reads up to `max_retries` times on connection errors,
+    /// timeouts and 5xx responses, waiting `base_delay` before the first retry
+    /// and twice as long before each one after. 4xx responses are never retried.
+    #[must_use]
+    pub fn retries(mut self, max_retries: u32, base_delay: Duration) -> Self {
+        self.retry = RetryPolicy {
+            max_retries,
+            base_delay,
+        };
+        self
+    }
+
+    /// When revalidating a cached config fails on a connection error, timeout
+    /// or 5xx response, log a warning and return the cached config instead of
+    /// the error. Configs never fetched still fail, as do 4xx responses.
+    #[must_use]
+    pub fn serve_stale_on_error(mut self, enabled: bool) -> Self {
+        self.serve_stale_on_error = enabled;
+        self
+    }
+
+    /// Send `Authorization: Bearer <key>` with every request
+    #[must_use]
+    pub fn api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    pub fn build(self) -> Result<ConfigClient> {
+        let mut builder = ReqwestClient::builder().timeout(Duration::from_secs(30));
+        if let Some(key) = &self.api_key {
+            let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {key}"))?;
+            value.set_sensitive(true);
+            let headers =
+                reqwest::header::HeaderMap::from_iter([(reqwest::header::AUTHORIZATION, value)]);
+            builder = builder.default_headers(headers);
+        }
+
+        #[cfg(feature = "compression")]
+        let builder = builder.gzip(self.compression).brotli(self.compression);
+
+        Ok(ConfigClient {
+            client: builder.build()?,
+            base_url: self.base_url.trim_end_matches('/').to_string(),
             cache: Arc::new(RwLock::new(HashMap::new())),
+            write_through: self.write_through,
+            defaults: self.defaults,
+            cache_ttl: self.cache_ttl,
+            retry: self.retry,
+            serve_stale_on_error: self.serve_stale_on_error,
+            disk_cache: None,
         })
     }
+}
+
+impl ConfigClient {
+    pub fn new(base_url: impl Into<String>) -> Result<Self> {
+        Self::builder(base_url).build()
+    }
+
+    pub fn builder(base_url: impl Into<String>) -> ConfigClientBuilder {
+        ConfigClientBuilder {
+            base_url: base_url.into(),
+            #[cfg(feature = "compression")]
+            compression: true,
+            write_through: false,
+            defaults: None,
+            api_key: None,
+            cache_ttl: None,
+            retry: RetryPolicy::default(),
+            serve_stale_on_error: false,
+        }
+    }
+
+    /// The key of `config_name` in the default application and environment
+    pub fn key(&self, config_name: impl Into<String>) -> Result<ConfigKey> {
+        let Some((application, environment)) = &self.defaults else {
+            anyhow::bail!("No default application and environment configured");
+        };
+        Ok(ConfigKey::new(
+            application.clone(),
+            environment.clone(),
+            config_name,
+        ))
+    }
+
+    /// [`get_config`](Self::get_config) for a config in the default
+    /// application and environment
+    pub async fn get(&self, config_name: &str) -> Result<ConfigData> {
+        self.get_config(&self.key(config_name)?).await
+    }
+
+    /// [`put_config`](Self::put_config) for a config in the default
+    /// application and environment
+    pub async fn put(
+        &self,
+        config_name: &str,
+        content: serde_json::Value,
+        schema: Option<serde_json::Value>,
+        expected_version: Option<String>,
+    ) -> Result<String> {
+        self.put_config(
+            &self.key(config_name)?,
+            content,
+            schema,
+            expected_version,
+            None,
+        )
+        .await
+    }
 
     pub async fn get_config(&self, key: &ConfigKey) -> Result<ConfigData> {
         let cache_key = key.to_string();
 
-        // Check cache first
+        // Check cache first; an expired entry is revalidated
         {
             let cache = self.cache.read().await;
-            let cached = cache.get(&cache_key);
-            if let Some(cached) = cached {
-                return Ok(cached.clone());
+            match cache.get(&cache_key) {
+                Some(cached) if cached.is_fresh(self.cache_ttl) => {
+                    return Ok(cached.data.clone());
+                }
+                Some(_) => {
+                    drop(cache);
+                    return self.refresh(key).await;
+                }
+                None => {}
             }
         }
 
         // Fetch from remote and cache
-        let data = self.fetch_config(key).await?;
+        let fetched = self.fetch_tagged(key).await?;
+        let data = fetched.data.clone();
+        self.persist(key, &fetched).await;
 
         {
             let mut cache = self.cache.write().await;
-            cache.insert(cache_key, data.clone());
+            cache.insert(cache_key, fetched);
         }
 
         Ok(data)
     }
 
+    /// Fetch the current version, revalidating any cached copy with
+    /// `If-None-Match` so an unchanged config is not downloaded again
     pub async fn refresh(&self, key: &ConfigKey) -> Result<ConfigData> {
         let cache_key = key.to_string();
-        let data = self.fetch_config(key).await?;
+        let cached = self.cache.read().await.get(&cache_key).cloned();
+        let fetched = match (self.revalidate(key, cached.as_ref()).await, cached) {
+            (Ok(fetched), _) => fetched,
+            (Err(e), Some(stale))
+                if (self.serve_stale_on_error || stale.from_disk) && is_unavailable(&e) =>
+            {
+                // Left unrenewed, so the next read tries the server again
+                warn!("Serving stale {key} @ {}: {e:#}", stale.data.version);
+                return Ok(stale.data);
+            }
+            (Err(e), _) => return Err(e),
+        };
+        let data = fetched.data.clone();
+        self.persist(key, &fetched).await;
 
         {
             let mut cache = self.cache.write().await;
-            cache.insert(cache_key, data.clone());
+            cache.insert(cache_key, fetched);
         }
 
         Ok(data)
     }
 
+    async fn revalidate(
+        &self,
+        key: &ConfigKey,
+        cached: Option<&CachedConfig>,
+    ) -> Result<CachedConfig> {
+        let fetched = self
+            .fetch_if_modified(key, cached.and_then(|c| c.etag.as_deref()))
+            .await?;
+        match (fetched, cached) {
+            (Fetched::Modified(fetched), _) => Ok(fetched),
+            (Fetched::NotModified, Some(cached)) => {
+                Ok(CachedConfig::new(cached.data.clone(), cached.etag.clone()))
+            }
+            (Fetched::NotModified, None) => self.fetch_tagged(key).await,
+        }
+    }
+
     async fn fetch_config(&self, key: &ConfigKey) -> Result<ConfigData> {
+        Ok(self.fetch_tagged(key).await?.data)
+    }
+
+    /// Send `request`, retrying connection failures, timeouts and 5xx
+    /// responses with exponential backoff as configured by
+    /// [`ConfigClientBuilder::retries`]. 4xx responses are returned as is.
+    async fn send_with_retry(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let Some(this_try) = request.try_clone() else {
+                return Ok(request.send().await?);
+            };
+            attempt += 1;
+            let retry = attempt <= self.retry.max_retries;
+
+            let result = match this_try.send().await {
+                Ok(response) if response.status().is_server_error() => {
+                    if retry {
+                        debug!(attempt, status = %response.status(), "Retrying after server error");
+                        None
+                    } else {
+                        Some(response.error_for_status())
+                    }
+                }
+                Err(e) if retry && (e.is_connect() || e.is_timeout()) => {
+                    debug!(attempt, "Retrying after {e}");
+                    None
+                }
+                result => Some(result),
+            };
+
+            match result {
+                Some(Err(e)) if attempt > 1 => {
+                    return Err(anyhow::Error::from(e))
+                        .with_context(|| format!("Giving up after {attempt} attempts"));
+                }
+                Some(result) => return Ok(result?),
+                None => {}
+            }
+
+            let backoff = self
+                .retry
+                .base_delay
+                .saturating_mul(1 << (attempt - 1).min(16));
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
+    async fn fetch_tagged(&self, key: &ConfigKey) -> Result<CachedConfig> {
+        match self.fetch_if_modified(key, None).await? {
+            Fetched::Modified(fetched) => Ok(fetched),
+            Fetched::NotModified => anyhow::bail!("Unexpected 304 for {key}"),
+        }
+    }
+
+    /// Fetch the current version with its `ETag`. With `if_none_match`, the
+    /// server may instead answer that the version with that tag is current.
+    async fn fetch_if_modified(
+        &self,
+        key: &ConfigKey,
+        if_none_match: Option<&str>,
+    ) -> Result<Fetched> {
         let url = format!(
             "{}/configs/{}/{}/{}",
             self.base_url, key.application, key.environment, key.config_name
         );
 
-        let response = self.client.get(&url).send().await?;
+        let mut request = self.client.get(&url);
+        if let Some(etag) = if_none_match {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        let response = self.send_with_retry(request).await?;
 
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Fetched::NotModified);
+        }
         if response.status() == StatusCode::NOT_FOUND {
             anyhow::bail!("Configuration not found: {key}");
         }
 
         response.error_for_status_ref()?;
 
-        let data: serde_json::Value = response.json().await?;
-
-        Ok(ConfigData {
-            content: data["content"].clone(),
-            schema: data["schema"].clone(),
-            version: data["version"].as_str().unwrap_or("").to_string(),
-        })
+        let etag = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok(Fetched::Modified(CachedConfig::new(
+            parse_config_response(response).await?,
+            etag,
+        )))
     }
 
+    /// Write `content` as a new version of `key`, with `change_message`
+    /// recorded against it to say why
     pub async fn put_config(
         &self,
         key: &ConfigKey,
         content: serde_json::Value,
         schema: Option<serde_json::Value>,
         expected_version: Option<String>,
+        change_message: Option<&str>,
     ) -> Result<String> {
+        let result = self
+            .send_put(
+                key,
+                &content,
+                schema.as_ref(),
+                expected_version,
+                change_message,
+                false,
+            )
+            .await?;
+        let version = result["version"].as_str();
+
+        {
+            let mut cache = self.cache.write().await;
+            match (self.write_through, version, schema) {
+                // Without the schema or a reported version we can't know what
+                // the server now holds, so the next read has to fetch it
+                (true, Some(version), Some(schema)) => {
+                    let written = ConfigData {
+                        content,
+                        schema,
+                        version: version.to_string(),
+                        content_type: None,
+                    };
+                    cache.insert(key.to_string(), CachedConfig::new(written, None));
+                }
+                _ => {
+                    cache.remove(&key.to_string());
+                }
+            }
+        }
+
+        Ok(version.unwrap_or("unknown").to_string())
+    }
+
+    /// Like [`put_config`](Self::put_config), but returns the config exactly
+    /// as the server stored it, saving a follow-up read
+    pub async fn put_config_returning(
+        &self,
+        key: &ConfigKey,
+        content: serde_json::Value,
+        schema: Option<serde_json::Value>,
+        expected_version: Option<String>,
+    ) -> Result<ConfigData> {
+        let result = self
+            .send_put(key, &content, schema.as_ref(), expected_version, None, true)
+            .await?;
+        let stored = config_from_value(&result);
+
+        {
+            let mut cache = self.cache.write().await;
+            if self.write_through {
+                cache.insert(key.to_string(), CachedConfig::new(stored.clone(), None));
+            } else {
+                cache.remove(&key.to_string());
+            }
+        }
+
+        Ok(stored)
+    }
+
+    async fn send_put(
+        &self,
+        key: &ConfigKey,
+        content: &serde_json::Value,
+        schema: Option<&serde_json::Value>,
+        expected_version: Option<String>,
+        change_message: Option<&str>,
+        representation: bool,
+    ) -> Result<serde_json::Value> {
         let url = format!(
             "{}/configs/{}/{}/{}",
             self.base_url, key.application, key.environment, key.config_name
         );
 
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "content": content,
             "schema": schema,
             "expected_version": expected_version,
         });
+        if let Some(message) = change_message {
+            body["change_message"] = message.into();
+        }
 
-        let response = self.client.put(&url).json(&body).send().await?;
+        let mut request = self.client.put(&url).json(&body);
+        if representation {
+            request = request.query(&[("return", "representation")]);
+        }
+
+        // Updating a config read earlier: make the write conditional on the
+        // server still holding what was read
+        let etag = self
+            .cache
+            .read()
+            .await
+            .get(&key.to_string())
+            .and_then(|cached| cached.etag.clone());
+        if let Some(etag) = etag {
+            request = request.header(reqwest::header::IF_MATCH, etag);
+        }
+
+        let response = request.send().await?;
+        let response =
+            check_write(response, || format!("{key} was changed since it was read")).await?;
+
+        Ok(response.json().await?)
+    }
+
+    /// Create a configuration under a server-generated name in `app`/`env`,
+    /// returning its key and version
+    pub async fn create_config(
+        &self,
+        app: &str,
+        env: &str,
+        content: serde_json::Value,
+        schema: serde_json::Value,
+    ) -> Result<(ConfigKey, String)> {
+        let url = format!("{}/configs/{}/{}", self.base_url, app, env);
+
+        let body = serde_json::json!({
+            "content": content,
+            "schema": schema,
+        });
+
+        let response = self.client.post(&url).json(&body).send().await?;
         response.error_for_status_ref()?;
 
         let result: serde_json::Value = response.json().await?;
+        let config_name = result["config_name"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Response is missing config_name"))?;
+        let key = ConfigKey::new(app, env, config_name);
+
+        Ok((
+            key,
+            result["version"].as_str().unwrap_or("unknown").to_string(),
+        ))
+    }
+
+    /// Put `value` as the content of `key`, with the JSON Schema derived from
+    /// `T` as its schema, so the server enforces the type on later writes
+    #[cfg(feature = "typed")]
+    pub async fn put_typed<T>(
+        &self,
+        key: &ConfigKey,
+        value: &T,
+        expected_version: Option<String>,
+    ) -> Result<String>
+    where
+        T: serde::Serialize + schemars::JsonSchema,
+    {
+        let content = serde_json::to_value(value)?;
+        let schema = serde_json::to_value(schemars::schema_for!(T))?;
+        self.put_config(key, content, Some(schema), expected_version, None)
+            .await
+    }
+
+    /// Make `alias` read through to `target`; `get_config` on the alias then
+    /// returns the target's current content
+    pub async fn set_alias(&self, alias: &ConfigKey, target: &ConfigKey) -> Result<()> {
+        let url = format!(
+            "{}/configs/{}/{}/{}/alias",
+            self.base_url, alias.application, alias.environment, alias.config_name
+        );
+
+        let body = serde_json::json!({ "target": target });
+
+        let response = self.client.put(&url).json(&body).send().await?;
+        response.error_for_status()?;
 
-        // Invalidate cache for this key
         {
             let mut cache = self.cache.write().await;
-            cache.remove(&key.to_string());
+            cache.remove(&alias.to_string());
         }
 
-        Ok(result["version"].as_str().unwrap_or("unknown").to_string())
+        Ok(())
     }
 
     pub async fn delete_environment(&self, app: &str, env: &str) -> Result<()> {
@@ -130,6 +613,35 @@ impl ConfigClient {
         Ok(())
     }
 
+    /// Delete a configuration only if `expected_version` is still its current
+    /// version. Returns whether there was a configuration to delete; fails if
+    /// it has since moved on to another version.
+    pub async fn delete_config_if(&self, key: &ConfigKey, expected_version: &str) -> Result<bool> {
+        let url = format!(
+            "{}/configs/{}/{}/{}",
+            self.base_url, key.application, key.environment, key.config_name
+        );
+
+        let response = self
+            .client
+            .delete(&url)
+            .header(reqwest::header::IF_MATCH, format!("\"{expected_version}\""))
+            .send()
+            .await?;
+        let response = check_write(response, || {
+            format!("Configuration {key} is no longer at version {expected_version}")
+        })
+        .await?;
+
+        {
+            let mut cache = self.cache.write().await;
+            cache.remove(&key.to_string());
+        }
+
+        let result: serde_json::Value = response.json().await?;
+        Ok(result["existed"].as_bool().unwrap_or(true))
+    }
+
     pub async fn list_versions(&self, key: &ConfigKey) -> Result<Vec<VersionInfo>> {
         let url = format!(
             "{}/configs/{}/{}/{}/versions",
@@ -150,13 +662,115 @@ impl ConfigClient {
         Ok(versions)
     }
 
+    /// The current version of `key` with its content flattened server-side into
+    /// a single-level object, keys joined by `delimiter` (`.` by default).
+    /// Not cached.
+    pub async fn get_config_flattened(
+        &self,
+        key: &ConfigKey,
+        delimiter: Option<&str>,
+    ) -> Result<ConfigData> {
+        let url = format!(
+            "{}/configs/{}/{}/{}",
+            self.base_url, key.application, key.environment, key.config_name
+        );
+
+        let mut request = self.client.get(&url).query(&[("flatten", "true")]);
+        if let Some(delimiter) = delimiter {
+            request = request.query(&[("delim", delimiter)]);
+        }
+        let response = request.send().await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            anyhow::bail!("Configuration not found: {key}");
+        }
+
+        response.error_for_status_ref()?;
+
+        parse_config_response(response).await
+    }
+
+    /// Every config, or those under `prefix`: an `app` or `app/env` path
+    pub async fn list_configs(&self, prefix: Option<&str>) -> Result<Vec<ConfigKey>> {
+        let url = format!("{}/configs", self.base_url);
+
+        let mut request = self.client.get(&url);
+        if let Some(prefix) = prefix {
+            request = request.query(&[("prefix", prefix)]);
+        }
+
+        let response = request.send().await?;
+        response.error_for_status_ref()?;
+
+        let result: serde_json::Value = response.json().await?;
+        let configs: Vec<ConfigSummary> = serde_json::from_value(result["configs"].clone())?;
+        Ok(configs.into_iter().map(|summary| summary.key).collect())
+    }
+
+    /// Every application with at least one config, sorted
+    pub async fn list_applications(&self) -> Result<Vec<String>> {
+        let apps: BTreeSet<String> = self
+            .list_configs(None)
+            .await?
+            .into_iter()
+            .map(|key| key.application)
+            .collect();
+        Ok(apps.into_iter().collect())
+    }
+
+    /// Every environment of `app` with at least one config, sorted
+    pub async fn list_environments(&self, app: &str) -> Result<Vec<String>> {
+        let envs: BTreeSet<String> = self
+            .list_configs(Some(app))
+            .await?
+            .into_iter()
+            .map(|key| key.environment)
+            .collect();
+        Ok(envs.into_iter().collect())
+    }
+
+    /// Configurations under `prefix` (all if `None`) with their current
+    /// version and when it was written, in a single request
+    pub async fn list_config_summaries(&self, prefix: Option<&str>) -> Result<Vec<ConfigSummary>> {
+        let url = format!("{}/configs", self.base_url);
+
+        let mut request = self.client.get(&url).query(&[("detailed", "true")]);
+        if let Some(prefix) = prefix {
+            request = request.query(&[("prefix", prefix)]);
+        }
+
+        let response = request.send().await?;
+        response.error_for_status_ref()?;
+
+        let result: serde_json::Value = response.json().await?;
+        let configs = serde_json::from_value(result["configs"].clone())?;
+        Ok(configs)
+    }
+
+    /// The current content of `key` deserialized into `T`, through the same
+    /// cache as [`get_config`](Self::get_config)
+    pub async fn get_config_typed<T: DeserializeOwned>(&self, key: &ConfigKey) -> Result<T> {
+        let data = self.get_config(key).await?;
+        content_as(key, &data)
+    }
+
+    /// The content of one version of `key` deserialized into `T`
+    pub async fn get_config_version_typed<T: DeserializeOwned>(
+        &self,
+        key: &ConfigKey,
+        version: &str,
+    ) -> Result<T> {
+        let data = self.get_config_version(key, version).await?;
+        content_as(key, &data)
+    }
+
     pub async fn get_config_version(&self, key: &ConfigKey, version: &str) -> Result<ConfigData> {
         let url = format!(
             "{}/configs/{}/{}/{}/versions/{}",
             self.base_url, key.application, key.environment, key.config_name, version
         );
 
-        let response = self.client.get(&url).send().await?;
+        let response = self.send_with_retry(self.client.get(&url)).await?;
 
         if response.status() == StatusCode::NOT_FOUND {
             anyhow::bail!("Configuration version not found: {key} @ {version}");
@@ -164,19 +778,269 @@ impl ConfigClient {
 
         response.error_for_status_ref()?;
 
+        parse_config_response(response).await
+    }
+
+    /// The version of a configuration that was current at `time`, i.e. the
+    /// newest one written at or before it. Not cached.
+    pub async fn get_config_at(
+        &self,
+        key: &ConfigKey,
+        time: chrono::DateTime<chrono::Utc>,
+    ) -> Result<ConfigData> {
+        let url = format!(
+            "{}/configs/{}/{}/{}",
+            self.base_url, key.application, key.environment, key.config_name
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .query(&[("at", time.to_rfc3339())])
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            anyhow::bail!("Configuration not found: {key} at {}", time.to_rfc3339());
+        }
+
+        response.error_for_status_ref()?;
+
+        parse_config_response(response).await
+    }
+
+    /// The part of a configuration's current content that the JSON Pointer
+    /// `pointer` (RFC 6901) names, e.g. `/database/host`, fetched without the
+    /// rest of the config. Not cached.
+    pub async fn get_value(&self, key: &ConfigKey, pointer: &str) -> Result<serde_json::Value> {
+        let url = format!(
+            "{}/configs/{}/{}/{}",
+            self.base_url, key.application, key.environment, key.config_name
+        );
+
+        let response = self
+            .send_with_retry(self.client.get(&url).query(&[("pointer", pointer)]))
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            anyhow::bail!("Nothing at {pointer} in {key}");
+        }
+
+        response.error_for_status_ref()?;
+
+        Ok(response.json().await?)
+    }
+
+    /// Compare the content of two configurations already fetched, without a
+    /// round trip to the server
+    pub fn diff_local(a: &ConfigData, b: &ConfigData) -> ConfigDiff {
+        shared_types::diff(&a.content, &b.content)
+    }
+
+    /// The configurations `key` was copied from, nearest origin first
+    pub async fn lineage(&self, key: &ConfigKey) -> Result<Vec<ConfigOrigin>> {
+        let url = format!(
+            "{}/configs/{}/{}/{}/lineage",
+            self.base_url, key.application, key.environment, key.config_name
+        );
+
+        let response = self.client.get(&url).send().await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            anyhow::bail!("Configuration not found: {key}");
+        }
+
+        response.error_for_status_ref()?;
+
+        let data: serde_json::Value = response.json().await?;
+        let lineage: Vec<ConfigOrigin> = serde_json::from_value(data["lineage"].clone())?;
+
+        Ok(lineage)
+    }
+
+    /// Every version's content, oldest first, with the diff from the previous version.
+    /// The server may leave out the newest versions to cap the response size.
+    pub async fn timeline(&self, key: &ConfigKey) -> Result<Vec<TimelineStep>> {
+        let url = format!(
+            "{}/configs/{}/{}/{}/timeline",
+            self.base_url, key.application, key.environment, key.config_name
+        );
+
+        let response = self.client.get(&url).send().await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            anyhow::bail!("Configuration not found: {key}");
+        }
+
+        response.error_for_status_ref()?;
+
         let data: serde_json::Value = response.json().await?;
+        let steps: Vec<TimelineStep> = serde_json::from_value(data["steps"].clone())?;
+
+        Ok(steps)
+    }
+
+    /// Lazily stream every version of a configuration, oldest first.
+    ///
+    /// The version list is fetched up front, but each version's data is only
+    /// requested when the stream is polled for it, one at a time, so callers
+    /// can stop early without downloading the whole history.
+    pub fn version_stream<'a>(
+        &'a self,
+        key: &'a ConfigKey,
+    ) -> impl Stream<Item = Result<ConfigData>> + 'a {
+        stream::once(self.list_versions(key))
+            .map_ok(|versions| stream::iter(versions.into_iter().map(Ok)))
+            .try_flatten()
+            .and_then(move |info| async move { self.get_config_version(key, &info.version).await })
+    }
+
+    /// Read several configs in one request, captured as close together as the
+    /// server can manage. Best-effort: a write landing mid-read isn't detected.
+    /// Not cached.
+    pub async fn read_snapshot(&self, keys: &[ConfigKey]) -> Result<ConfigSnapshot> {
+        let url = format!("{}/configs/snapshot", self.base_url);
+
+        let body = serde_json::json!({ "keys": keys });
+        let response = self.client.post(&url).json(&body).send().await?;
+        response.error_for_status_ref()?;
+
+        let result: serde_json::Value = response.json().await?;
+        let configs = result["configs"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("Snapshot response is missing configs"))?
+            .iter()
+            .map(|entry| {
+                let key: ConfigKey = serde_json::from_value(entry.clone())?;
+                Ok((key, config_from_value(entry)))
+            })
+            .collect::<Result<_>>()?;
 
-        Ok(ConfigData {
-            content: data["content"].clone(),
-            schema: data["schema"].clone(),
-            version: data["version"].as_str().unwrap_or("").to_string(),
+        Ok(ConfigSnapshot {
+            snapshot_id: result["snapshot_id"].as_str().unwrap_or("").to_string(),
+            configs,
         })
     }
 
+    /// Whether the server reports itself healthy. A server that answers with
+    /// an error status is unhealthy rather than an error.
     pub async fn health_check(&self) -> Result<bool> {
+        match self.health_detail().await {
+            Ok(info) => Ok(info.is_healthy()),
+            Err(e)
+                if e.downcast_ref::<reqwest::Error>()
+                    .and_then(reqwest::Error::status)
+                    .is_some() =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The server's health report, including storage details when it gives them
+    pub async fn health_detail(&self) -> Result<HealthInfo> {
         let url = format!("{}/health", self.base_url);
         let response = self.client.get(&url).send().await?;
-        Ok(response.status() == StatusCode::OK)
+        response.error_for_status_ref()?;
+
+        Ok(response.json().await?)
+    }
+}
+
+/// Configs read together by [`ConfigClient::read_snapshot`]
+#[derive(Debug, Clone)]
+pub struct ConfigSnapshot {
+    /// When the server started the reads
+    pub snapshot_id: String,
+    /// Each requested config with the version read, in request order
+    pub configs: Vec<(ConfigKey, ConfigData)>,
+}
+
+/// Body of the server's `/health` endpoint. Fields other than `status` are
+/// only present on servers that report them.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct HealthInfo {
+    pub status: String,
+    /// Storage backend in use, e.g. `local` or `s3`
+    #[serde(default)]
+    pub backend: Option<String>,
+    /// Server version
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Seconds since the server started
+    #[serde(default)]
+    pub uptime: Option<u64>,
+}
+
+impl HealthInfo {
+    pub fn is_healthy(&self) -> bool {
+        self.status == "healthy"
+    }
+}
+
+/// Deserialize a config's content into `T`, naming the config and type on failure
+fn content_as<T: DeserializeOwned>(key: &ConfigKey, data: &ConfigData) -> Result<T> {
+    T::deserialize(&data.content).with_context(|| {
+        format!(
+            "Content of {key} @ {} does not match {}",
+            data.version,
+            std::any::type_name::<T>()
+        )
+    })
+}
+
+/// Whether `error` means the server could not be reached or failed, rather
+/// than that it answered with something the client asked for wrongly
+fn is_unavailable(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<reqwest::Error>().is_some_and(|e| {
+        e.is_connect() || e.is_timeout() || e.status().is_some_and(|s| s.is_server_error())
+    })
+}
+
+/// Error code the server gives a 409 caused by a stale version, as opposed to
+/// one refused for any other reason
+const VERSION_CONFLICT: &str = "VERSION_CONFLICT";
+
+/// Pass a conditional write's response through if it succeeded. Fail with
+/// [`ClientError::Conflict`] if the server refused it because the config moved
+/// on from the version it was based on, and with the server's own message if
+/// it refused it for anything else.
+async fn check_write(
+    response: reqwest::Response,
+    conflict: impl FnOnce() -> String,
+) -> Result<reqwest::Response> {
+    let status = response.status();
+    if !status.is_client_error() {
+        response.error_for_status_ref()?;
+        return Ok(response);
+    }
+
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    let details = body["details"]
+        .as_str()
+        .or_else(|| body["error"].as_str())
+        .unwrap_or_default();
+    let stale = status == StatusCode::PRECONDITION_FAILED
+        || (status == StatusCode::CONFLICT && body["code"] == VERSION_CONFLICT);
+    if stale {
+        return Err(ClientError::Conflict(format!("{}: {details}", conflict())).into());
+    }
+    anyhow::bail!("{status}: {details}")
+}
+
+/// Convert a single-config response body into `ConfigData`
+async fn parse_config_response(response: reqwest::Response) -> Result<ConfigData> {
+    let data: serde_json::Value = response.json().await?;
+    Ok(config_from_value(&data))
+}
+
+fn config_from_value(data: &serde_json::Value) -> ConfigData {
+    ConfigData {
+        content: data["content"].clone(),
+        schema: data["schema"].clone(),
+        version: data["version"].as_str().unwrap_or("").to_string(),
+        content_type: data["content_type"].as_str().map(str::to_string),
     }
 }
 
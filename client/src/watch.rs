@@ -0,0 +1,167 @@
+use anyhow::Result;
+use futures::stream::{self, Stream};
+use reqwest::StatusCode;
+use shared_types::{ConfigData, ConfigKey};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::ConfigClient;
+
+/// First delay before reconnecting a dropped watch
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(250);
+/// Upper bound on the delay between reconnection attempts
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Where a [`ConfigClient::watch`] stream is between polls
+struct WatchState {
+    response: Option<reqwest::Response>,
+    /// Event stream text not yet terminated by a blank line
+    pending: String,
+    last_version: Option<String>,
+    reconnect_delay: Duration,
+    done: bool,
+}
+
+impl ConfigClient {
+    /// Follow a configuration over the server's event stream, yielding its
+    /// data now and again each time its version changes.
+    ///
+    /// Each change is fetched through the cache, so a concurrent
+    /// [`get_config`](Self::get_config) sees it too. A dropped connection is
+    /// re-established with backoff; the stream only ends, after yielding the
+    /// error, when the server refuses the watch outright.
+    pub fn watch<'a>(&'a self, key: &'a ConfigKey) -> impl Stream<Item = Result<ConfigData>> + 'a {
+        let state = WatchState {
+            response: None,
+            pending: String::new(),
+            last_version: None,
+            reconnect_delay: INITIAL_RECONNECT_DELAY,
+            done: false,
+        };
+        stream::unfold(state, move |mut state| async move {
+            while !state.done {
+                let Some(response) = state.response.as_mut() else {
+                    match self.open_watch(key).await {
+                        Ok(response) => {
+                            state.response = Some(response);
+                            state.pending.clear();
+                            state.reconnect_delay = INITIAL_RECONNECT_DELAY;
+                        }
+                        Err(e) if e.status().is_some_and(|s| s.is_client_error()) => {
+                            state.done = true;
+                            return Some((Err(e.into()), state));
+                        }
+                        Err(e) => {
+                            warn!("Watch on {key} failed to connect, retrying: {e}");
+                            state.backoff().await;
+                        }
+                    }
+                    continue;
+                };
+
+                match response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        state.pending.push_str(&String::from_utf8_lossy(&chunk));
+                        let announced = take_versions(&mut state.pending).pop();
+                        let Some(version) = announced else {
+                            continue;
+                        };
+                        if state.last_version.as_ref() == Some(&version) {
+                            continue;
+                        }
+                        // Fetching the current version rather than the one
+                        // announced skips straight past any newer change
+                        let fetched = self.refresh(key).await;
+                        if let Ok(data) = &fetched {
+                            state.last_version = Some(data.version.clone());
+                        }
+                        return Some((fetched, state));
+                    }
+                    Ok(None) => debug!("Watch on {key} closed by the server, reconnecting"),
+                    Err(e) => warn!("Watch on {key} dropped, reconnecting: {e}"),
+                }
+                state.response = None;
+                state.backoff().await;
+            }
+            None
+        })
+    }
+
+    async fn open_watch(&self, key: &ConfigKey) -> reqwest::Result<reqwest::Response> {
+        let url = format!(
+            "{}/configs/{}/{}/{}/watch",
+            self.base_url, key.application, key.environment, key.config_name
+        );
+        let response = self
+            .client
+            .get(&url)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .send()
+            .await?;
+        if response.status() == StatusCode::OK {
+            Ok(response)
+        } else {
+            response.error_for_status()
+        }
+    }
+}
+
+impl WatchState {
+    async fn backoff(&mut self) {
+        tokio::time::sleep(self.reconnect_delay).await;
+        self.reconnect_delay = (self.reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+/// Remove every complete event from `pending`, returning the versions the
+/// `version` events among them announce, oldest first
+fn take_versions(pending: &mut String) -> Vec<String> {
+    let normalized = pending.replace("\r\n", "\n");
+    let Some(end) = normalized.rfind("\n\n") else {
+        *pending = normalized;
+        return Vec::new();
+    };
+    let (complete, rest) = normalized.split_at(end + 2);
+
+    let versions = complete
+        .split("\n\n")
+        .filter_map(|event| {
+            let mut name = "message";
+            let mut data = None;
+            for line in event.lines() {
+                if let Some(value) = line.strip_prefix("event:") {
+                    name = value.trim();
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data = Some(value.trim());
+                }
+            }
+            let data: serde_json::Value = serde_json::from_str(data?).ok()?;
+            (name == "version").then(|| data["version"].as_str().map(str::to_string))?
+        })
+        .collect();
+    *pending = rest.to_string();
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_versions_keeps_partial_event() {
+        let mut pending = concat!(
+            "event: version\ndata: {\"version\":\"v1\"}\nid: v1\n\n",
+            ": keep-alive\n\n",
+            "event: version\r\ndata: {\"version\":\"v2\"}\r\n\r\n",
+            "event: version\ndata: {\"vers",
+        )
+        .to_string();
+
+        assert_eq!(take_versions(&mut pending), ["v1", "v2"]);
+        assert_eq!(pending, "event: version\ndata: {\"vers");
+
+        pending.push_str("ion\":\"v3\"}\n\n");
+        assert_eq!(take_versions(&mut pending), ["v3"]);
+        assert!(pending.is_empty());
+    }
+}
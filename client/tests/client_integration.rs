@@ -1,7 +1,8 @@
-use client::ConfigClient;
+use client::{CachedConfigClient, ClientError, ConfigClient};
+use futures::{StreamExt, TryStreamExt};
 use mockito::{self, Matcher};
 use serde_json::json;
-use shared_types::ConfigKey;
+use shared_types::{ConfigKey, PatchOperation};
 
 #[tokio::test]
 async fn test_health_check() -> anyhow::Result<()> {
@@ -19,6 +20,44 @@ async fn test_health_check() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_health_detail() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("GET", "/health")
+        .with_status(200)
+        .with_body(
+            r#"{"status": "healthy", "backend": "s3", "version": "0.3.1", "uptime": 3600, "storage": "ok"}"#,
+        )
+        .create();
+
+    let client = ConfigClient::new(server.url())?;
+    let info = client.health_detail().await?;
+
+    assert!(info.is_healthy());
+    assert_eq!(info.backend.as_deref(), Some("s3"));
+    assert_eq!(info.version.as_deref(), Some("0.3.1"));
+    assert_eq!(info.uptime, Some(3600));
+    assert!(client.health_check().await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_health_check_unhealthy_status() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("GET", "/health")
+        .with_status(503)
+        .with_body(r#"{"status": "unhealthy"}"#)
+        .create();
+
+    let client = ConfigClient::new(server.url())?;
+    assert!(!client.health_check().await?);
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_get_config() -> anyhow::Result<()> {
     let mut server = mockito::Server::new_async().await;
@@ -91,11 +130,297 @@ async fn test_put_config() -> anyhow::Result<()> {
     let content = json!({"url": "https://api.example.com"});
     let schema = json!({"type": "object"});
 
-    let version = client.put_config(&key, content, Some(schema), None).await?;
+    let version = client
+        .put_config(&key, content, Some(schema), None, None)
+        .await?;
+    assert_eq!(version, "v1");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_default_scope_shortcuts() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let body = |env: &str| {
+        format!(r#"{{"version": "v1", "content": {{"env": "{env}"}}, "schema": {{}}}}"#)
+    };
+    let prod = server
+        .mock("GET", "/configs/myapp/prod/database")
+        .with_status(200)
+        .with_body(body("prod"))
+        .create_async()
+        .await;
+    let dev = server
+        .mock("GET", "/configs/myapp/dev/database")
+        .with_status(200)
+        .with_body(body("dev"))
+        .create_async()
+        .await;
+    let put = server
+        .mock("PUT", "/configs/myapp/prod/flags")
+        .with_status(200)
+        .with_body(r#"{"message": "Success", "version": "v4"}"#)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::builder(server.url())
+        .defaults("myapp", "prod")
+        .build()?;
+
+    assert_eq!(client.get("database").await?.content["env"], "prod");
+    let version = client.put("flags", json!({"on": true}), None, None).await?;
+    assert_eq!(version, "v4");
+
+    // The full key still reaches other environments
+    let key = ConfigKey::new("myapp", "dev", "database");
+    assert_eq!(client.get_config(&key).await?.content["env"], "dev");
+
+    prod.assert_async().await;
+    dev.assert_async().await;
+    put.assert_async().await;
+
+    // Without defaults there is nothing to resolve a bare name against
+    assert!(
+        ConfigClient::new(server.url())?
+            .get("database")
+            .await
+            .is_err()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_put_config_write_through() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _put = server
+        .mock("PUT", "/configs/myapp/dev/api")
+        .with_status(200)
+        .with_body(r#"{"message": "Success", "version": "v2"}"#)
+        .create_async()
+        .await;
+    let get = server
+        .mock("GET", "/configs/myapp/dev/api")
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"url": "old"}, "schema": {}}"#)
+        .expect(0)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::builder(server.url())
+        .write_through(true)
+        .build()?;
+    let key = ConfigKey::new("myapp", "dev", "api");
+
+    client
+        .put_config(
+            &key,
+            json!({"url": "new"}),
+            Some(json!({"type": "object"})),
+            Some("v1".to_string()),
+            None,
+        )
+        .await?;
+
+    // Served from the cache with what was just written
+    let data = client.get_config(&key).await?;
+    assert_eq!(data.version, "v2");
+    assert_eq!(data.content, json!({"url": "new"}));
+    get.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_put_config_returning() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("PUT", "/configs/myapp/dev/api")
+        .match_query(Matcher::UrlEncoded(
+            "return".into(),
+            "representation".into(),
+        ))
+        .with_status(200)
+        .with_body(
+            r#"{"application": "myapp", "environment": "dev", "config_name": "api", "version": "v2", "content": {"url": "https://api.example.com"}, "schema": {"type": "object"}, "content_type": "application/json"}"#,
+        )
+        .create();
+
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "dev", "api");
+    let stored = client
+        .put_config_returning(
+            &key,
+            json!({"url": "https://api.example.com"}),
+            None,
+            Some("v1".to_string()),
+        )
+        .await?;
+
+    assert_eq!(stored.version, "v2");
+    assert_eq!(stored.content, json!({"url": "https://api.example.com"}));
+    assert_eq!(stored.schema, json!({"type": "object"}));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_config() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("POST", "/configs/myapp/snapshots")
+        .match_body(Matcher::Json(json!({
+            "content": {"taken_by": "ci"},
+            "schema": {"type": "object"}
+        })))
+        .with_status(201)
+        .with_body(
+            r#"{"application": "myapp", "environment": "snapshots", "config_name": "0b7e", "version": "v1"}"#,
+        )
+        .create();
+
+    let client = ConfigClient::new(server.url())?;
+    let (key, version) = client
+        .create_config(
+            "myapp",
+            "snapshots",
+            json!({"taken_by": "ci"}),
+            json!({"type": "object"}),
+        )
+        .await?;
+
+    assert_eq!(key, ConfigKey::new("myapp", "snapshots", "0b7e"));
     assert_eq!(version, "v1");
     Ok(())
 }
 
+#[tokio::test]
+async fn test_set_alias() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("PUT", "/configs/myapp/prod/current/alias")
+        .match_body(Matcher::Json(json!({
+            "target": {"application": "myapp", "environment": "prod", "config_name": "flags"}
+        })))
+        .with_status(200)
+        .with_body(
+            r#"{"message": "Configuration myapp/prod/current now aliases myapp/prod/flags"}"#,
+        )
+        .create();
+
+    let client = ConfigClient::new(server.url())?;
+    client
+        .set_alias(
+            &ConfigKey::new("myapp", "prod", "current"),
+            &ConfigKey::new("myapp", "prod", "flags"),
+        )
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_config_summaries() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("GET", "/configs")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("detailed".into(), "true".into()),
+            Matcher::UrlEncoded("prefix".into(), "myapp".into()),
+        ]))
+        .with_status(200)
+        .with_body(
+            r#"{"configs": [{"application": "myapp", "environment": "dev", "config_name": "flags", "current_version": "v3", "updated_at": "2024-01-01T00:00:00Z"}]}"#,
+        )
+        .create();
+
+    let client = ConfigClient::new(server.url())?;
+    let configs = client.list_config_summaries(Some("myapp")).await?;
+
+    assert_eq!(configs.len(), 1);
+    assert_eq!(configs[0].key, ConfigKey::new("myapp", "dev", "flags"));
+    assert_eq!(configs[0].current_version.as_deref(), Some("v3"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_configs_and_derived_names() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _all = server
+        .mock("GET", "/configs")
+        .match_query(Matcher::Missing)
+        .with_status(200)
+        .with_body(
+            r#"{"configs": [
+                {"application": "search", "environment": "prod", "config_name": "index"},
+                {"application": "billing", "environment": "prod", "config_name": "db"},
+                {"application": "billing", "environment": "dev", "config_name": "db"}
+            ]}"#,
+        )
+        .create();
+    let _billing = server
+        .mock("GET", "/configs")
+        .match_query(Matcher::UrlEncoded("prefix".into(), "billing".into()))
+        .with_status(200)
+        .with_body(
+            r#"{"configs": [
+                {"application": "billing", "environment": "prod", "config_name": "db"},
+                {"application": "billing", "environment": "prod", "config_name": "flags"},
+                {"application": "billing", "environment": "dev", "config_name": "db"}
+            ]}"#,
+        )
+        .create();
+
+    let client = ConfigClient::new(server.url())?;
+    let keys = client.list_configs(Some("billing")).await?;
+    assert_eq!(keys.len(), 3);
+    assert_eq!(keys[1], ConfigKey::new("billing", "prod", "flags"));
+    assert_eq!(client.list_applications().await?, ["billing", "search"]);
+    assert_eq!(client.list_environments("billing").await?, ["dev", "prod"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_snapshot() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("POST", "/configs/snapshot")
+        .match_body(Matcher::Json(json!({
+            "keys": [
+                {"application": "myapp", "environment": "dev", "config_name": "flags"},
+                {"application": "myapp", "environment": "dev", "config_name": "limits"}
+            ]
+        })))
+        .with_status(200)
+        .with_body(
+            r#"{"snapshot_id": "2024-01-01T00:00:00+00:00", "configs": [
+                {"application": "myapp", "environment": "dev", "config_name": "flags", "version": "v4", "content": {"on": true}, "schema": {}},
+                {"application": "myapp", "environment": "dev", "config_name": "limits", "version": "v2", "content": {"max": 5}, "schema": {}}
+            ]}"#,
+        )
+        .create();
+
+    let client = ConfigClient::new(server.url())?;
+    let keys = [
+        ConfigKey::new("myapp", "dev", "flags"),
+        ConfigKey::new("myapp", "dev", "limits"),
+    ];
+    let snapshot = client.read_snapshot(&keys).await?;
+
+    assert_eq!(snapshot.snapshot_id, "2024-01-01T00:00:00+00:00");
+    let versions: Vec<_> = snapshot
+        .configs
+        .iter()
+        .map(|(key, data)| (key.config_name.as_str(), data.version.as_str()))
+        .collect();
+    assert_eq!(versions, vec![("flags", "v4"), ("limits", "v2")]);
+    assert_eq!(snapshot.configs[1].1.content, json!({"max": 5}));
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_delete_environment() -> anyhow::Result<()> {
     let mut server = mockito::Server::new_async().await;
@@ -138,3 +463,985 @@ async fn test_list_versions() -> anyhow::Result<()> {
     assert_eq!(versions[1].version, "v2");
     Ok(())
 }
+
+#[tokio::test]
+async fn test_version_stream_fetches_lazily() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _list = server
+        .mock("GET", "/configs/myapp/dev/config/versions")
+        .with_status(200)
+        .with_body(
+            json!({
+                "versions": (1..=5)
+                    .map(|i| json!({"version": format!("v{i}"), "timestamp": "2024-01-01T00:00:00Z"}))
+                    .collect::<Vec<_>>()
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let mut version_mocks = Vec::new();
+    for i in 1..=5 {
+        let mock = server
+            .mock(
+                "GET",
+                format!("/configs/myapp/dev/config/versions/v{i}").as_str(),
+            )
+            .with_status(200)
+            .with_body(
+                json!({
+                    "version": format!("v{i}"),
+                    "content": {"step": i},
+                    "schema": {"type": "object"}
+                })
+                .to_string(),
+            )
+            .expect(usize::from(i <= 2))
+            .create_async()
+            .await;
+        version_mocks.push(mock);
+    }
+
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "dev", "config");
+
+    let consumed: Vec<_> = client.version_stream(&key).take(2).try_collect().await?;
+
+    assert_eq!(consumed.len(), 2);
+    assert_eq!(consumed[0].version, "v1");
+    assert_eq!(consumed[1].content["step"], 2);
+
+    for mock in &version_mocks {
+        mock.assert_async().await;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn test_get_config_decodes_gzip_response() -> anyhow::Result<()> {
+    use flate2::{Compression, write::GzEncoder};
+    use std::io::Write;
+
+    let mut server = mockito::Server::new_async().await;
+
+    let body = json!({
+        "version": "v3",
+        "content": {"host": "db.internal", "replicas": [1, 2, 3]},
+        "schema": {"type": "object"}
+    });
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body.to_string().as_bytes())?;
+    let compressed = encoder.finish()?;
+
+    let _m = server
+        .mock("GET", "/configs/myapp/prod/database")
+        .match_header("accept-encoding", Matcher::Regex("gzip".to_string()))
+        .with_status(200)
+        .with_header("content-encoding", "gzip")
+        .with_body(compressed)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "prod", "database");
+    let config = client.get_config(&key).await?;
+
+    assert_eq!(config.version, "v3");
+    assert_eq!(config.content, body["content"]);
+    Ok(())
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn test_compression_can_be_disabled() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("GET", "/configs/myapp/dev/plain")
+        .match_header("accept-encoding", Matcher::Missing)
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"a": 1}, "schema": {"type": "object"}}"#)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::builder(server.url())
+        .compression(false)
+        .build()?;
+    let config = client
+        .get_config(&ConfigKey::new("myapp", "dev", "plain"))
+        .await?;
+
+    assert_eq!(config.content["a"], 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lineage() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("GET", "/configs/myapp/prod/api/lineage")
+        .with_status(200)
+        .with_body(
+            json!({
+                "lineage": [
+                    {"application": "myapp", "environment": "staging", "config_name": "api", "version": "v4"},
+                    {"application": "myapp", "environment": "dev", "config_name": "api", "version": "v2"}
+                ]
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let client = ConfigClient::new(server.url())?;
+    let lineage = client
+        .lineage(&ConfigKey::new("myapp", "prod", "api"))
+        .await?;
+
+    assert_eq!(lineage.len(), 2);
+    assert_eq!(lineage[0].key, ConfigKey::new("myapp", "staging", "api"));
+    assert_eq!(lineage[0].version, "v4");
+    assert_eq!(lineage[1].key.environment, "dev");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_timeline() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("GET", "/configs/myapp/dev/config/timeline")
+        .with_status(200)
+        .with_body(
+            json!({
+                "steps": [
+                    {"version": "v1", "timestamp": "2024-01-01T00:00:00Z", "content": {"a": 1}, "diff": null},
+                    {"version": "v2", "timestamp": "2024-01-02T00:00:00Z", "content": {"a": 2},
+                     "diff": [{"op": "replace", "path": "/a", "value": 2}]}
+                ],
+                "truncated": false
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let client = ConfigClient::new(server.url())?;
+    let steps = client
+        .timeline(&ConfigKey::new("myapp", "dev", "config"))
+        .await?;
+
+    assert_eq!(steps.len(), 2);
+    assert!(steps[0].diff.is_none());
+    assert_eq!(
+        steps[1].diff,
+        Some(vec![PatchOperation::Replace {
+            path: "/a".to_string(),
+            value: json!(2)
+        }])
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wait_for_version_change() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    // The first two polls see v1, later ones see v2
+    let unchanged = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"on": false}, "schema": {}}"#)
+        .expect(2)
+        .create_async()
+        .await;
+    let changed = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(200)
+        .with_body(r#"{"version": "v2", "content": {"on": true}, "schema": {}}"#)
+        .create_async()
+        .await;
+
+    let client = CachedConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    let data = client
+        .wait_for_version_change(&key, "v1", std::time::Duration::from_secs(5))
+        .await?;
+
+    assert_eq!(data.version, "v2");
+    assert_eq!(data.content, json!({"on": true}));
+    unchanged.assert_async().await;
+    changed.assert_async().await;
+
+    // The new version is now served from the cache
+    let cached = client.get_config(&key).await?;
+    assert_eq!(cached.version, "v2");
+    changed.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cached_client_evicts_least_recently_used() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let flags = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(200)
+        .with_body(r#"{"version": "v3", "content": {"on": true}, "schema": {}}"#)
+        .expect(2)
+        .create_async()
+        .await;
+    let _v1 = server
+        .mock("GET", "/configs/myapp/dev/flags/versions/v1")
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"on": false}, "schema": {}}"#)
+        .create_async()
+        .await;
+    let limits = server
+        .mock("GET", "/configs/myapp/dev/limits")
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"max": 5}, "schema": {}}"#)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = CachedConfigClient::with_capacity(server.url(), 2)?;
+    let flags_key = ConfigKey::new("myapp", "dev", "flags");
+    let limits_key = ConfigKey::new("myapp", "dev", "limits");
+
+    client.get_config(&flags_key).await?;
+    client.get_config(&limits_key).await?;
+    // Touch limits so flags becomes the least recently used entry
+    client.get_config(&limits_key).await?;
+    client.get_config_version(&flags_key, "v1").await?;
+
+    assert_eq!(client.cache_size().await, 2);
+    assert_eq!(client.evictions(), 1);
+
+    // limits is still cached; flags was evicted and has to be fetched again
+    client.get_config(&limits_key).await?;
+    client.get_config(&flags_key).await?;
+    limits.assert_async().await;
+    flags.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_config_flattened() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("GET", "/configs/myapp/dev/service")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("flatten".into(), "true".into()),
+            Matcher::UrlEncoded("delim".into(), "_".into()),
+        ]))
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"database_host": "localhost"}, "schema": {}}"#)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "dev", "service");
+    let data = client.get_config_flattened(&key, Some("_")).await?;
+
+    assert_eq!(data.content, json!({"database_host": "localhost"}));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_config_at() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _m = server
+        .mock("GET", "/configs/myapp/prod/database")
+        .match_query(Matcher::UrlEncoded(
+            "at".into(),
+            "2024-05-01T14:32:00+00:00".into(),
+        ))
+        .with_status(200)
+        .with_body(r#"{"version": "v3", "content": {"pool": 10}, "schema": {}}"#)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "prod", "database");
+    let data = client
+        .get_config_at(&key, "2024-05-01T14:32:00Z".parse()?)
+        .await?;
+
+    assert_eq!(data.version, "v3");
+    assert_eq!(data.content, json!({"pool": 10}));
+    Ok(())
+}
+
+/// Run the real server in-process on a free port, returning its URL
+async fn spawn_server(storage_dir: &std::path::Path) -> anyhow::Result<String> {
+    use server::storage::{ObjectStoreBackend, StorageConfig};
+    use std::sync::Arc;
+
+    let storage = ObjectStoreBackend::from_config(StorageConfig::local(storage_dir))?;
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+    tokio::spawn(server::http::start_server(
+        Arc::new(storage),
+        server::http::ServerSettings::default(),
+        addr,
+    ));
+
+    let url = format!("http://{addr}");
+    let client = ConfigClient::new(&url)?;
+    for _ in 0..50 {
+        if client.health_check().await.unwrap_or(false) {
+            return Ok(url);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    anyhow::bail!("In-process server did not start")
+}
+
+#[cfg(feature = "typed")]
+#[tokio::test]
+async fn test_put_typed_schema_is_enforced_by_server() -> anyhow::Result<()> {
+    #[derive(serde::Serialize, schemars::JsonSchema)]
+    struct DatabaseConfig {
+        host: String,
+        port: u16,
+    }
+
+    let dir = tempfile::TempDir::new()?;
+    let client = ConfigClient::new(spawn_server(dir.path()).await?)?;
+    let key = ConfigKey::new("myapp", "dev", "database");
+
+    let config = DatabaseConfig {
+        host: "localhost".to_string(),
+        port: 5432,
+    };
+    client.put_typed(&key, &config, None).await?;
+
+    let stored = client.get_config(&key).await?;
+    assert_eq!(stored.version, "v1");
+    assert_eq!(stored.content, json!({"host": "localhost", "port": 5432}));
+    assert_eq!(stored.schema["required"], json!(["host", "port"]));
+
+    // Later untyped writes are held to the derived schema
+    let result = client
+        .put_config(
+            &key,
+            json!({"host": "localhost", "port": "5432"}),
+            None,
+            Some("v1".to_string()),
+            None,
+        )
+        .await;
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[test]
+fn test_get_or_init_shares_one_instance() -> anyhow::Result<()> {
+    // The only test in this binary that touches the process-wide instance
+    let handles: Vec<_> = ["http://first:3000", "http://second:3000"]
+        .into_iter()
+        .map(|url| {
+            std::thread::spawn(move || {
+                CachedConfigClient::get_or_init(url)
+                    .map(|client| std::ptr::from_ref(client) as usize)
+            })
+        })
+        .collect();
+
+    let mut addresses = Vec::new();
+    for handle in handles {
+        addresses.push(
+            handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("get_or_init panicked"))??,
+        );
+    }
+
+    assert_eq!(addresses[0], addresses[1]);
+    let instance = CachedConfigClient::instance()?;
+    assert_eq!(std::ptr::from_ref(instance) as usize, addresses[0]);
+    Ok(())
+}
+
+#[test]
+fn test_diff_local() {
+    let config = |content: serde_json::Value, version: &str| shared_types::ConfigData {
+        content,
+        schema: json!({}),
+        version: version.to_string(),
+        content_type: None,
+    };
+    let a = config(json!({"db": {"host": "a"}, "debug": true}), "v1");
+    let b = config(json!({"db": {"host": "b"}, "replicas": 2}), "v2");
+
+    let diff = ConfigClient::diff_local(&a, &b);
+
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].path, "/db/host");
+    assert_eq!(diff.removed[0].path, "/debug");
+    assert_eq!(diff.added[0].path, "/replicas");
+    assert!(ConfigClient::diff_local(&a, &a).is_empty());
+}
+
+#[tokio::test]
+async fn test_delete_config_if() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let deleted = server
+        .mock("DELETE", "/configs/myapp/dev/flags")
+        .match_header("if-match", "\"v3\"")
+        .with_status(200)
+        .with_body(r#"{"message": "Deleted", "existed": true, "deleted_versions": 3}"#)
+        .create_async()
+        .await;
+    let conflict = server
+        .mock("DELETE", "/configs/myapp/dev/flags")
+        .match_header("if-match", "\"v2\"")
+        .with_status(409)
+        .with_body(
+            r#"{"error": "Conflict", "details": "Version conflict", "code": "VERSION_CONFLICT"}"#,
+        )
+        .create_async()
+        .await;
+
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+
+    assert!(client.delete_config_if(&key, "v3").await?);
+    let err = client
+        .delete_config_if(&key, "v2")
+        .await
+        .err()
+        .map(|e| e.to_string())
+        .unwrap_or_default();
+    assert!(err.contains("no longer at version v2"));
+
+    deleted.assert_async().await;
+    conflict.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_put_sends_if_match_from_cached_etag() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let _get = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(200)
+        .with_header("etag", "\"v3\"")
+        .with_body(r#"{"version": "v3", "content": {"on": false}, "schema": {}}"#)
+        .create_async()
+        .await;
+    let put = server
+        .mock("PUT", "/configs/myapp/dev/flags")
+        .match_header("if-match", "\"v3\"")
+        .with_status(200)
+        .with_body(r#"{"message": "Success", "version": "v4"}"#)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    client.get_config(&key).await?;
+    let version = client
+        .put_config(
+            &key,
+            json!({"on": true}),
+            None,
+            Some("v3".to_string()),
+            None,
+        )
+        .await?;
+    assert_eq!(version, "v4");
+    put.assert_async().await;
+    put.remove_async().await;
+
+    // Someone else wrote in between: the server refuses the stale ETag
+    client.get_config(&key).await?;
+    let _stale = server
+        .mock("PUT", "/configs/myapp/dev/flags")
+        .match_header("if-match", "\"v3\"")
+        .with_status(412)
+        .with_body(r#"{"error": "Precondition Failed"}"#)
+        .create_async()
+        .await;
+    let err = client
+        .put_config(
+            &key,
+            json!({"on": true}),
+            None,
+            Some("v3".to_string()),
+            None,
+        )
+        .await
+        .err()
+        .ok_or_else(|| anyhow::anyhow!("expected a conflict"))?;
+    assert!(matches!(
+        err.downcast_ref::<ClientError>(),
+        Some(ClientError::Conflict(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_put_conflict_only_for_version_conflicts() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    let put = || {
+        client.put_config(
+            &key,
+            json!({"on": true}),
+            None,
+            Some("v3".to_string()),
+            None,
+        )
+    };
+
+    // Refused for a reason other than a stale version: not a conflict, and
+    // the server's explanation comes through
+    let limit = server
+        .mock("PUT", "/configs/myapp/dev/flags")
+        .with_status(409)
+        .with_body(
+            r#"{"error": "Conflict", "details": "Application myapp already has 2 environments (limit 2)"}"#,
+        )
+        .create_async()
+        .await;
+    let err = put()
+        .await
+        .err()
+        .ok_or_else(|| anyhow::anyhow!("expected an error"))?;
+    assert!(err.downcast_ref::<ClientError>().is_none());
+    assert!(err.to_string().contains("already has 2 environments"));
+    limit.remove_async().await;
+
+    let stale = server
+        .mock("PUT", "/configs/myapp/dev/flags")
+        .with_status(409)
+        .with_body(
+            r#"{"error": "Conflict", "details": "Version conflict: expected v3, but found v4", "code": "VERSION_CONFLICT"}"#,
+        )
+        .create_async()
+        .await;
+    let err = put()
+        .await
+        .err()
+        .ok_or_else(|| anyhow::anyhow!("expected a conflict"))?;
+    assert!(matches!(
+        err.downcast_ref::<ClientError>(),
+        Some(ClientError::Conflict(_))
+    ));
+    stale.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_refresh_keeps_cached_config_on_not_modified() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let first = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .match_header("if-none-match", Matcher::Missing)
+        .with_status(200)
+        .with_header("etag", "\"v3\"")
+        .with_body(r#"{"version": "v3", "content": {"on": false}, "schema": {}}"#)
+        .create_async()
+        .await;
+    let revalidate = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .match_header("if-none-match", "\"v3\"")
+        .with_status(304)
+        .with_header("etag", "\"v3\"")
+        .create_async()
+        .await;
+
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    client.get_config(&key).await?;
+    first.assert_async().await;
+
+    let refreshed = client.refresh(&key).await?;
+    revalidate.assert_async().await;
+    assert_eq!(refreshed.version, "v3");
+    assert_eq!(refreshed.content, json!({"on": false}));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_key_sent_as_bearer_token() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let m = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .match_header("authorization", "Bearer secret-key")
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"on": true}, "schema": {}}"#)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::builder(server.url())
+        .api_key("secret-key")
+        .build()?;
+    let config = client
+        .get_config(&ConfigKey::new("myapp", "dev", "flags"))
+        .await?;
+
+    m.assert_async().await;
+    assert_eq!(config.content["on"], true);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_config_typed() -> anyhow::Result<()> {
+    #[derive(serde::Deserialize)]
+    struct Database {
+        host: String,
+        port: u16,
+    }
+
+    let mut server = mockito::Server::new_async().await;
+    let current = server
+        .mock("GET", "/configs/myapp/dev/database")
+        .with_status(200)
+        .with_body(r#"{"version": "v2", "content": {"host": "db", "port": 5432}, "schema": {}}"#)
+        .expect(1)
+        .create_async()
+        .await;
+    let _old = server
+        .mock("GET", "/configs/myapp/dev/database/versions/v1")
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"host": "db"}, "schema": {}}"#)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "dev", "database");
+    let db: Database = client.get_config_typed(&key).await?;
+    assert_eq!((db.host.as_str(), db.port), ("db", 5432));
+
+    // Served from the cache the second time
+    let db: Database = client.get_config_typed(&key).await?;
+    assert_eq!(db.port, 5432);
+    current.assert_async().await;
+
+    let err = client
+        .get_config_version_typed::<Database>(&key, "v1")
+        .await
+        .err()
+        .ok_or_else(|| anyhow::anyhow!("expected a type mismatch"))?;
+    assert!(err.to_string().contains("myapp/dev/database @ v1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cached_client_auto_refresh_keeps_last_good_value() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+
+    let v1 = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"on": false}, "schema": {}}"#)
+        .create_async()
+        .await;
+    let client: &'static CachedConfigClient =
+        Box::leak(Box::new(CachedConfigClient::new(server.url())?));
+    assert_eq!(client.get_config(&key).await?.version, "v1");
+    v1.remove_async().await;
+
+    let v2 = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(200)
+        .with_body(r#"{"version": "v2", "content": {"on": true}, "schema": {}}"#)
+        .create_async()
+        .await;
+    client.start_auto_refresh(std::time::Duration::from_millis(20));
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while client.get_config(&key).await?.version != "v2" {
+        anyhow::ensure!(
+            std::time::Instant::now() < deadline,
+            "cache was never refreshed"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    v2.remove_async().await;
+
+    // The server failing does not evict the cached config
+    let failing = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(500)
+        .expect_at_least(1)
+        .create_async()
+        .await;
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    failing.assert_async().await;
+    assert_eq!(client.get_config(&key).await?.version, "v2");
+
+    client.stop_auto_refresh();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_ttl_refetches_expired_entries() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let m = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"on": true}, "schema": {}}"#)
+        .expect(2)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::builder(server.url())
+        .cache_ttl(std::time::Duration::from_millis(50))
+        .build()?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    client.get_config(&key).await?;
+    // Fresh: served from the cache
+    client.get_config(&key).await?;
+
+    tokio::time::sleep(std::time::Duration::from_millis(80)).await;
+    let config = client.get_config(&key).await?;
+    assert_eq!(config.version, "v1");
+    m.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_serve_stale_on_error_falls_back_to_cache() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+
+    let available = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(200)
+        .with_body(r#"{"version": "v1", "content": {"on": true}, "schema": {}}"#)
+        .create_async()
+        .await;
+    let strict = ConfigClient::new(server.url())?;
+    let lenient = ConfigClient::builder(server.url())
+        .serve_stale_on_error(true)
+        .build()?;
+    strict.get_config(&key).await?;
+    lenient.get_config(&key).await?;
+    available.remove_async().await;
+
+    let outage = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(503)
+        .create_async()
+        .await;
+    assert!(strict.refresh(&key).await.is_err());
+    let stale = lenient.refresh(&key).await?;
+    assert_eq!(stale.version, "v1");
+    assert_eq!(stale.content, json!({"on": true}));
+    outage.remove_async().await;
+
+    // A config the server says is gone is not papered over
+    server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(404)
+        .create_async()
+        .await;
+    assert!(lenient.refresh(&key).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fetch_retries_server_errors_but_not_client_errors() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let client = ConfigClient::builder(server.url())
+        .retries(2, std::time::Duration::from_millis(1))
+        .build()?;
+
+    let unavailable = server
+        .mock("GET", "/configs/myapp/dev/flags")
+        .with_status(503)
+        .expect(3)
+        .create_async()
+        .await;
+    let err = client
+        .get_config(&ConfigKey::new("myapp", "dev", "flags"))
+        .await
+        .err()
+        .ok_or_else(|| anyhow::anyhow!("expected the server error"))?;
+    assert!(err.to_string().contains("3 attempts"));
+    unavailable.assert_async().await;
+
+    let missing = server
+        .mock("GET", "/configs/myapp/dev/missing")
+        .with_status(404)
+        .expect(1)
+        .create_async()
+        .await;
+    assert!(
+        client
+            .get_config(&ConfigKey::new("myapp", "dev", "missing"))
+            .await
+            .is_err()
+    );
+    missing.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_watch_yields_each_new_version() -> anyhow::Result<()> {
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir()?;
+    let url = spawn_server(dir.path()).await?;
+    let client = ConfigClient::new(&url)?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    client
+        .put_config(
+            &key,
+            json!({"enabled": false}),
+            Some(json!({"type": "object"})),
+            None,
+            None,
+        )
+        .await?;
+
+    let updates = client.watch(&key);
+    futures::pin_mut!(updates);
+    let mut next = async || {
+        tokio::time::timeout(Duration::from_secs(5), updates.next())
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("watch ended"))?
+    };
+
+    assert_eq!(next().await?.version, "v1");
+
+    let writer = ConfigClient::new(&url)?;
+    writer
+        .put_config(
+            &key,
+            json!({"enabled": true}),
+            None,
+            Some("v1".to_string()),
+            None,
+        )
+        .await?;
+    let update = next().await?;
+    assert_eq!(update.version, "v2");
+    assert_eq!(update.content, json!({"enabled": true}));
+
+    // The cache was refreshed along the way
+    assert_eq!(client.get_config(&key).await?.version, "v2");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_change_message_listed_with_version() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let client = ConfigClient::new(spawn_server(dir.path()).await?)?;
+    let key = ConfigKey::new("myapp", "dev", "limits");
+
+    client
+        .put_config(
+            &key,
+            json!({"max": 10}),
+            Some(json!({"type": "object"})),
+            None,
+            None,
+        )
+        .await?;
+    client
+        .put_config(
+            &key,
+            json!({"max": 20}),
+            None,
+            Some("v1".to_string()),
+            Some("Raise the limit for the launch"),
+        )
+        .await?;
+
+    let messages: Vec<_> = client
+        .list_versions(&key)
+        .await?
+        .into_iter()
+        .map(|v| v.change_message)
+        .collect();
+    assert_eq!(
+        messages,
+        [None, Some("Raise the limit for the launch".to_string())]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_disk_cache_serves_last_known_config_when_server_down() -> anyhow::Result<()> {
+    let storage_dir = tempfile::tempdir()?;
+    let cache_dir = tempfile::tempdir()?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+
+    let online = ConfigClient::new(spawn_server(storage_dir.path()).await?)?
+        .with_disk_cache(cache_dir.path())?;
+    online
+        .put_config(
+            &key,
+            json!({"enabled": true}),
+            Some(json!({"type": "object"})),
+            None,
+            None,
+        )
+        .await?;
+    online.get_config(&key).await?;
+
+    // Nothing listens on the discard port
+    let offline_url = "http://127.0.0.1:9";
+    let booted = ConfigClient::new(offline_url)?.with_disk_cache(cache_dir.path())?;
+    let config = booted.get_config(&key).await?;
+    assert_eq!(config.version, "v1");
+    assert_eq!(config.content, json!({"enabled": true}));
+
+    assert!(
+        ConfigClient::new(offline_url)?
+            .get_config(&key)
+            .await
+            .is_err()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_value_fetches_pointer() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let host = server
+        .mock("GET", "/configs/myapp/dev/service")
+        .match_query(Matcher::UrlEncoded(
+            "pointer".into(),
+            "/database/host".into(),
+        ))
+        .with_status(200)
+        .with_body(r#""db.internal""#)
+        .create_async()
+        .await;
+    let missing = server
+        .mock("GET", "/configs/myapp/dev/service")
+        .match_query(Matcher::UrlEncoded("pointer".into(), "/nope".into()))
+        .with_status(404)
+        .create_async()
+        .await;
+
+    let client = ConfigClient::new(server.url())?;
+    let key = ConfigKey::new("myapp", "dev", "service");
+    assert_eq!(
+        client.get_value(&key, "/database/host").await?,
+        json!("db.internal")
+    );
+    assert!(client.get_value(&key, "/nope").await.is_err());
+    host.assert_async().await;
+    missing.assert_async().await;
+    Ok(())
+}
@@ -2,89 +2,154 @@ use anyhow::Result;
 use client::ConfigClient;
 use serde_json::json;
 use shared_types::ConfigKey;
-use std::net::TcpListener;
+use std::io::{BufRead, BufReader, Read};
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
-use std::thread;
-use std::time::Duration;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Startup deadline used unless `E2E_STARTUP_DEADLINE_SECS` overrides it
+const DEFAULT_STARTUP_DEADLINE: Duration = Duration::from_secs(30);
+const INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+const MAX_BACKOFF: Duration = Duration::from_secs(2);
+
+/// The server binary, built at most once per test run
+static SERVER_BINARY: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+static NEXT_INSTANCE: AtomicUsize = AtomicUsize::new(0);
+
+struct StartOptions {
+    /// How long to wait for the server to report healthy before giving up
+    deadline: Duration,
+    /// Extra environment variables for the server process
+    env: Vec<(&'static str, &'static str)>,
+    /// Run this executable instead of the server binary
+    program: Option<PathBuf>,
+}
+
+impl Default for StartOptions {
+    fn default() -> Self {
+        let deadline = std::env::var("E2E_STARTUP_DEADLINE_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map_or(DEFAULT_STARTUP_DEADLINE, Duration::from_secs);
+
+        Self {
+            deadline,
+            env: Vec::new(),
+            program: None,
+        }
+    }
+}
 
 struct TestServer {
     process: Child,
     port: u16,
-    #[allow(dead_code)]
     storage_path: String,
 }
 
 impl TestServer {
     async fn start() -> Result<Self> {
-        // Find an available port
-        let port = {
-            let listener = TcpListener::bind("127.0.0.1:0")?;
-            listener.local_addr()?.port()
+        Self::start_with(StartOptions::default()).await
+    }
+
+    async fn start_with(options: StartOptions) -> Result<Self> {
+        let program = match options.program {
+            Some(program) => program,
+            None => tokio::task::spawn_blocking(server_binary).await??,
         };
 
         // Use a unique storage path for this test instance
-        let storage_path = format!("/tmp/open-app-config-test-{port}");
+        let storage_path = format!(
+            "/tmp/open-app-config-test-{}-{}",
+            std::process::id(),
+            NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed)
+        );
         let _ = std::fs::remove_dir_all(&storage_path);
 
-        // Build the server first
-        println!("Building server...");
-        let output = Command::new("cargo")
-            .args(["build", "--bin", "server", "-p", "server"])
-            .output()?;
-
-        if !output.status.success() {
-            anyhow::bail!(
-                "Failed to build server: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
-
-        println!("Starting server on port {port}...");
-        let mut process = Command::new("cargo")
-            .args(["run", "--bin", "server", "-p", "server"])
+        // Port 0 lets the OS pick a free port; the server logs the one it bound
+        let mut process = Command::new(&program)
+            .env_remove("BIND_ADDRESS")
             .env("HOST", "127.0.0.1")
-            .env("PORT", port.to_string())
+            .env("PORT", "0")
             .env("STORAGE_PATH", &storage_path)
             .env("RUST_LOG", "info")
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
+            .env("NO_COLOR", "1")
+            .envs(options.env)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()?;
 
-        // Give the server time to start and verify it's running
-        let server_url = format!("http://localhost:{port}");
-        let client = ConfigClient::new(&server_url)?;
-
-        for i in 0..20 {
-            // Try for up to 10 seconds
-            // Check if process has exited with error
-            match process.try_wait() {
-                Ok(Some(status)) => {
-                    anyhow::bail!("Server exited early with status: {status:?}");
+        let stdout = process.stdout.take();
+        let stderr = process.stderr.take();
+        let (addr_tx, addr_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let Some(stdout) = stdout else { return };
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                println!("{line}");
+                if let Some(addr) = parse_listen_address(&line) {
+                    let _ = addr_tx.send(addr);
                 }
-                Ok(None) => {
-                    // Process is still running, check if it's ready
-                    if client.health_check().await.unwrap_or(false) {
-                        println!("Server is ready after {} ms", i * 500);
-                        return Ok(TestServer {
-                            process,
-                            port,
-                            storage_path,
-                        });
-                    }
-                }
-                Err(e) => anyhow::bail!("Failed to check server status: {e}"),
+            }
+        });
+        let stderr = thread::spawn(move || {
+            let mut captured = String::new();
+            if let Some(mut stderr) = stderr {
+                let _ = stderr.read_to_string(&mut captured);
+            }
+            captured
+        });
+
+        let started = Instant::now();
+        let mut backoff = INITIAL_BACKOFF;
+        let mut client = None;
+        loop {
+            if let Some(status) = process.try_wait()? {
+                return Err(startup_error(
+                    &format!("Server exited early with status: {status}"),
+                    &mut process,
+                    stderr,
+                    &storage_path,
+                ));
             }
 
-            thread::sleep(Duration::from_millis(500));
-        }
+            if client.is_none()
+                && let Ok(addr) = addr_rx.try_recv()
+            {
+                client = Some((addr.port(), ConfigClient::new(local_url(addr.port()))?));
+            }
 
-        // If we get here, server didn't become ready in time
-        let _ = process.kill();
-        anyhow::bail!("Server failed to become ready after 10 seconds")
+            if let Some((port, client)) = &client
+                && client.health_check().await.unwrap_or(false)
+            {
+                println!("Server is ready after {:?}", started.elapsed());
+                return Ok(TestServer {
+                    process,
+                    port: *port,
+                    storage_path,
+                });
+            }
+
+            let remaining = options.deadline.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                return Err(startup_error(
+                    &format!("Server did not become ready within {:?}", options.deadline),
+                    &mut process,
+                    stderr,
+                    &storage_path,
+                ));
+            }
+
+            tokio::time::sleep(backoff.min(remaining)).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
     }
 
     fn url(&self) -> String {
-        format!("http://localhost:{}", self.port)
+        local_url(self.port)
     }
 }
 
@@ -96,6 +161,114 @@ impl Drop for TestServer {
     }
 }
 
+fn local_url(port: u16) -> String {
+    format!("http://localhost:{port}")
+}
+
+fn server_binary() -> Result<PathBuf> {
+    SERVER_BINARY
+        .get_or_init(build_server)
+        .clone()
+        .map_err(anyhow::Error::msg)
+}
+
+fn build_server() -> Result<PathBuf, String> {
+    println!("Building server...");
+    let output = Command::new("cargo")
+        .args([
+            "build",
+            "--bin",
+            "server",
+            "-p",
+            "server",
+            "--message-format=json",
+        ])
+        .output()
+        .map_err(|e| format!("Failed to run cargo: {e}"))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to build server: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|msg| msg["reason"] == "compiler-artifact" && msg["target"]["name"] == "server")
+        .find_map(|msg| msg["executable"].as_str().map(PathBuf::from))
+        .ok_or_else(|| "cargo did not report a server executable".to_string())
+}
+
+fn parse_listen_address(line: &str) -> Option<SocketAddr> {
+    let (_, addr) = line.split_once("Server listening on ")?;
+    addr.trim().parse().ok()
+}
+
+/// Stop a server that failed to start and describe why, including its stderr
+fn startup_error(
+    reason: &str,
+    process: &mut Child,
+    stderr: JoinHandle<String>,
+    storage_path: &str,
+) -> anyhow::Error {
+    let _ = process.kill();
+    let _ = process.wait();
+    let _ = std::fs::remove_dir_all(storage_path);
+    let stderr = stderr.join().unwrap_or_default();
+    anyhow::anyhow!("{reason}\n--- server stderr ---\n{stderr}")
+}
+
+#[tokio::test]
+async fn test_e2e_startup_failure_includes_stderr() -> Result<()> {
+    let options = StartOptions {
+        env: vec![("KEY_CASE", "bogus")],
+        ..StartOptions::default()
+    };
+    let Err(err) = TestServer::start_with(options).await else {
+        anyhow::bail!("server should refuse to start with an invalid KEY_CASE");
+    };
+
+    let message = err.to_string();
+    assert!(message.contains("exited early"), "{message}");
+    assert!(message.contains("Unknown key case: bogus"), "{message}");
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_e2e_startup_timeout_includes_stderr() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // A stand-in server that complains on stderr but never becomes healthy
+    let script = std::env::temp_dir().join(format!(
+        "open-app-config-hung-server-{}.sh",
+        std::process::id()
+    ));
+    std::fs::write(
+        &script,
+        "#!/bin/sh\necho 'storage backend unreachable' >&2\nexec sleep 30\n",
+    )?;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+    let options = StartOptions {
+        deadline: Duration::from_millis(500),
+        program: Some(script.clone()),
+        ..StartOptions::default()
+    };
+    let result = TestServer::start_with(options).await;
+    let _ = std::fs::remove_file(&script);
+    let Err(err) = result else {
+        anyhow::bail!("a server that never reports healthy should time out");
+    };
+
+    let message = err.to_string();
+    assert!(message.contains("did not become ready"), "{message}");
+    assert!(message.contains("storage backend unreachable"), "{message}");
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_e2e_basic_workflow() -> Result<()> {
     let server = TestServer::start().await?;
@@ -118,7 +291,7 @@ async fn test_e2e_basic_workflow() -> Result<()> {
 
     // Create config
     let version = client
-        .put_config(&key, content.clone(), Some(schema.clone()), None)
+        .put_config(&key, content.clone(), Some(schema.clone()), None, None)
         .await?;
     assert!(!version.is_empty());
 
@@ -134,7 +307,13 @@ async fn test_e2e_basic_workflow() -> Result<()> {
         "database": "myapp_prod"
     });
     let version2 = client
-        .put_config(&key, updated_content.clone(), None, Some(retrieved.version))
+        .put_config(
+            &key,
+            updated_content.clone(),
+            None,
+            Some(retrieved.version),
+            None,
+        )
         .await?;
     assert!(!version2.is_empty());
 
@@ -166,7 +345,7 @@ async fn test_caching_behavior() -> Result<()> {
 
     // Create config
     client
-        .put_config(&key, content.clone(), Some(schema.clone()), None)
+        .put_config(&key, content.clone(), Some(schema.clone()), None, None)
         .await?;
 
     // First get - caching is internal
@@ -202,7 +381,7 @@ async fn test_e2e_error_handling() -> Result<()> {
     let content = json!({"valid": true});
     assert!(
         client
-            .put_config(&key, content.clone(), None, None)
+            .put_config(&key, content.clone(), None, None, None)
             .await
             .is_err()
     );
@@ -212,18 +391,18 @@ async fn test_e2e_error_handling() -> Result<()> {
     let schema = json!({"type": "object"});
     assert!(
         client
-            .put_config(&key, invalid_content, Some(schema.clone()), None)
+            .put_config(&key, invalid_content, Some(schema.clone()), None, None)
             .await
             .is_err()
     );
 
     // Create properly then test version conflict
     client
-        .put_config(&key, content.clone(), Some(schema), None)
+        .put_config(&key, content.clone(), Some(schema), None, None)
         .await?;
     assert!(
         client
-            .put_config(&key, content, None, Some("wrong-version".to_string()))
+            .put_config(&key, content, None, Some("wrong-version".to_string()), None)
             .await
             .is_err()
     );
@@ -0,0 +1,173 @@
+use super::dto::DiffSummary;
+use serde_json::Value;
+use shared_types::PatchOperation;
+
+/// Compute the RFC 6902 JSON Patch that turns `from` into `to`
+///
+/// Objects are compared key by key; any other differing value, including
+/// arrays, is replaced as a whole.
+pub fn json_patch(from: &Value, to: &Value) -> Vec<PatchOperation> {
+    let diff = shared_types::diff(from, to);
+    let removes = diff
+        .removed
+        .into_iter()
+        .map(|removed| PatchOperation::Remove { path: removed.path });
+    let replaces = diff
+        .changed
+        .into_iter()
+        .map(|changed| PatchOperation::Replace {
+            path: changed.path,
+            value: changed.to,
+        });
+    let adds = diff.added.into_iter().map(|added| PatchOperation::Add {
+        path: added.path,
+        value: added.value,
+    });
+    removes.chain(replaces).chain(adds).collect()
+}
+
+/// Which top-level keys differ between `from` and `to`
+pub fn summary(from: &Value, to: &Value) -> DiffSummary {
+    let diff = shared_types::diff(from, to);
+    let mut summary = DiffSummary::default();
+
+    // Additions and removals below the top level change their parent key
+    for value in &diff.added {
+        match top_level_key(&value.path) {
+            (key, false) => push_unique(&mut summary.added, key),
+            (key, true) => push_unique(&mut summary.changed, key),
+        }
+    }
+    for value in &diff.removed {
+        match top_level_key(&value.path) {
+            (key, false) => push_unique(&mut summary.removed, key),
+            (key, true) => push_unique(&mut summary.changed, key),
+        }
+    }
+    for change in &diff.changed {
+        push_unique(&mut summary.changed, top_level_key(&change.path).0);
+    }
+    summary
+}
+
+fn push_unique(keys: &mut Vec<String>, key: String) {
+    if !keys.contains(&key) {
+        keys.push(key);
+    }
+}
+
+/// The unescaped first token of a JSON Pointer, and whether the pointer goes
+/// deeper than it. A change at the root counts as a nested change of "".
+fn top_level_key(path: &str) -> (String, bool) {
+    let Some(rest) = path.strip_prefix('/') else {
+        return (String::new(), true);
+    };
+    let (token, nested) = rest
+        .split_once('/')
+        .map_or((rest, false), |(t, _)| (t, true));
+    (token.replace("~1", "/").replace("~0", "~"), nested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_identical_values_produce_no_ops() {
+        let value = json!({"a": 1, "b": {"c": [1, 2]}});
+        assert!(json_patch(&value, &value).is_empty());
+    }
+
+    #[test]
+    fn test_add_remove_replace() {
+        let from = json!({"keep": 1, "drop": true, "change": "old"});
+        let to = json!({"keep": 1, "change": "new", "extra": [1]});
+
+        let ops = json_patch(&from, &to);
+
+        assert_eq!(ops.len(), 3);
+        assert!(ops.contains(&PatchOperation::Remove {
+            path: "/drop".to_string()
+        }));
+        assert!(ops.contains(&PatchOperation::Replace {
+            path: "/change".to_string(),
+            value: json!("new")
+        }));
+        assert!(ops.contains(&PatchOperation::Add {
+            path: "/extra".to_string(),
+            value: json!([1])
+        }));
+    }
+
+    #[test]
+    fn test_nested_objects_and_escaping() {
+        let from = json!({"db": {"host": "a", "a/b": 1}});
+        let to = json!({"db": {"host": "b", "a/b": 1, "m~n": 2}});
+
+        let ops = json_patch(&from, &to);
+
+        assert_eq!(
+            ops,
+            vec![
+                PatchOperation::Replace {
+                    path: "/db/host".to_string(),
+                    value: json!("b")
+                },
+                PatchOperation::Add {
+                    path: "/db/m~0n".to_string(),
+                    value: json!(2)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arrays_and_type_changes_are_replaced() {
+        let ops = json_patch(
+            &json!({"a": [1, 2], "b": {"x": 1}}),
+            &json!({"a": [1], "b": 5}),
+        );
+        assert_eq!(
+            ops,
+            vec![
+                PatchOperation::Replace {
+                    path: "/a".to_string(),
+                    value: json!([1])
+                },
+                PatchOperation::Replace {
+                    path: "/b".to_string(),
+                    value: json!(5)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary_groups_by_top_level_key() {
+        let from = json!({"keep": 1, "drop": 2, "db": {"host": "a", "old": 1}, "a/b": 1});
+        let to = json!({"keep": 1, "new": 3, "db": {"host": "b", "extra": 2}, "a/b": 2});
+
+        assert_eq!(
+            summary(&from, &to),
+            DiffSummary {
+                added: vec!["new".to_string()],
+                removed: vec!["drop".to_string()],
+                changed: vec!["db".to_string(), "a/b".to_string()],
+            }
+        );
+        assert_eq!(summary(&from, &from), DiffSummary::default());
+    }
+
+    #[test]
+    fn test_root_replacement() {
+        let ops = json_patch(&json!([1]), &json!({"a": 1}));
+        assert_eq!(
+            ops,
+            vec![PatchOperation::Replace {
+                path: String::new(),
+                value: json!({"a": 1})
+            }]
+        );
+    }
+}
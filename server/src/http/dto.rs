@@ -1,5 +1,9 @@
+use crate::storage::metadata::Metadata;
 use serde::{Deserialize, Serialize};
-use shared_types::{ConfigData, ConfigKey, VersionInfo};
+use shared_types::{
+    ConfigData, ConfigKey, ConfigOrigin, ConfigSummary, PatchOperation, TimelineStep, VersionInfo,
+};
+use std::collections::BTreeMap;
 
 /// Request body for creating or updating a configuration
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,6 +19,150 @@ pub struct PutConfigRequest {
     /// - None for first creation
     /// - Some("v1") when updating from v1
     pub expected_version: Option<String>,
+
+    /// MIME type of the content, defaults to `application/json`
+    #[serde(default)]
+    pub content_type: Option<String>,
+
+    /// Why this change is being made, kept with the version it writes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_message: Option<String>,
+}
+
+/// Request body for creating a configuration with a server-generated name
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateConfigRequest {
+    /// The configuration content (JSON)
+    pub content: serde_json::Value,
+
+    /// JSON schema for validation, required since this is always a first version
+    pub schema: serde_json::Value,
+
+    /// MIME type of the content, defaults to `application/json`
+    #[serde(default)]
+    pub content_type: Option<String>,
+}
+
+/// Response for a configuration created with a server-generated name
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateConfigResponse {
+    pub application: String,
+    pub environment: String,
+    pub config_name: String,
+    pub version: String,
+}
+
+/// Request body for creating an environment's configs from a template environment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FromTemplateRequest {
+    /// Environment whose current configs are copied
+    pub template_env: String,
+}
+
+/// Query parameters for creating configs from a template
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FromTemplateQuery {
+    /// Write a new version over configs that already exist instead of skipping them
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Response for creating configs from a template
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FromTemplateResponse {
+    /// Config names written to the new environment
+    pub created: Vec<String>,
+    /// Config names left alone because they already existed
+    pub skipped: Vec<String>,
+}
+
+/// Request body for pointing an alias at another configuration
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetAliasRequest {
+    /// Configuration that reads of the alias resolve to
+    pub target: ConfigKey,
+}
+
+/// Request body for pointing a version alias at a version
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetVersionAliasRequest {
+    pub version: String,
+}
+
+/// Request body for promoting a configuration to another environment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromoteRequest {
+    /// Environment that receives the source configuration
+    pub to_environment: String,
+}
+
+/// Query parameters for promotion
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PromoteQuery {
+    /// Report what would change without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Request body for copying a configuration to another key
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CopyConfigRequest {
+    /// Configuration that receives the source's current content and schema
+    pub to: ConfigKey,
+    /// Replace the destination if it already exists instead of failing
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Query parameters for making a version current
+#[derive(Debug, Default, Deserialize)]
+pub struct ActivateQuery {
+    /// Activate the version even if it has been marked bad
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Query parameters for creating or updating a configuration
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PutConfigQuery {
+    /// Reject schemas containing keywords no JSON Schema draft defines
+    #[serde(default)]
+    pub strict_schema: bool,
+    /// Reject a new schema that the currently live content does not satisfy
+    #[serde(default)]
+    pub validate_history: bool,
+    /// With `false`, store the new version without making it current
+    pub activate: Option<bool>,
+    /// Write a new version even when the content matches the current one
+    #[serde(default)]
+    pub touch: bool,
+    /// `representation` responds with the stored config instead of just its version
+    #[serde(rename = "return")]
+    pub return_preference: Option<String>,
+}
+
+/// Query parameters for validating a configuration without storing it
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ValidateConfigQuery {
+    /// Reject schemas containing keywords no JSON Schema draft defines
+    #[serde(default)]
+    pub strict_schema: bool,
+}
+
+/// Query parameters for reading a configuration
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GetConfigQuery {
+    /// Return the content as a single-level object with joined key paths
+    #[serde(default)]
+    pub flatten: bool,
+    /// Separator between key path segments when flattening, `.` by default
+    pub delim: Option<String>,
+    /// Comma-separated top-level response fields to return, all by default
+    pub fields: Option<String>,
+    /// Return the version that was newest at this instant instead of the current one
+    pub at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Return only the part of the content this JSON Pointer (RFC 6901) names
+    pub pointer: Option<String>,
 }
 
 /// Response for a successful configuration retrieval
@@ -26,6 +174,46 @@ pub struct GetConfigResponse {
     pub version: String,
     pub content: serde_json::Value,
     pub schema: serde_json::Value,
+    #[serde(default)]
+    pub content_type: Option<String>,
+}
+
+/// Request body for reading several configurations together
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotRequest {
+    pub keys: Vec<ConfigKey>,
+}
+
+/// Configurations read together. The reads run concurrently, so the set is
+/// consistent only as far as no write lands while they are in flight; pin
+/// versions for a guaranteed-consistent set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotResponse {
+    /// When the reads were started (RFC 3339)
+    pub snapshot_id: String,
+    /// One entry per requested key, in request order
+    pub configs: Vec<GetConfigResponse>,
+}
+
+/// Request body for fetching several configurations at once
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchGetRequest {
+    pub keys: Vec<ConfigKey>,
+}
+
+/// Configurations fetched at once, keyed by [`ConfigKey::to_path`]. A key
+/// that could not be read maps to its error instead of failing the batch.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchGetResponse {
+    pub configs: BTreeMap<String, BatchGetResult>,
+}
+
+/// The outcome of reading one key of a batch
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BatchGetResult {
+    Config(GetConfigResponse),
+    Error(ErrorResponse),
 }
 
 /// Response for listing versions
@@ -34,20 +222,202 @@ pub struct ListVersionsResponse {
     pub versions: Vec<VersionInfo>,
 }
 
+/// Response for a configuration's lineage, nearest origin first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LineageResponse {
+    pub lineage: Vec<ConfigOrigin>,
+}
+
+/// Response for a configuration's timeline, oldest version first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimelineResponse {
+    pub steps: Vec<TimelineStep>,
+    /// True if later versions were left out to cap the response size
+    pub truncated: bool,
+}
+
+/// Query parameters for diffing two versions of a configuration
+#[derive(Debug, Default, Deserialize)]
+pub struct VersionDiffQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// The change between two versions of a configuration's content
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionDiffResponse {
+    pub from: String,
+    pub to: String,
+    /// JSON Patch that turns `from`'s content into `to`'s
+    pub patch: Vec<PatchOperation>,
+    pub summary: DiffSummary,
+}
+
+/// Top-level keys of a configuration's content touched by a change. A key
+/// only nested values changed under counts as changed; an empty key stands
+/// for the whole document when either side is not an object.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiffSummary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+/// Preview of a promotion: the change to the target and whether it would validate
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromotePreviewResponse {
+    /// JSON Patch from the target's current content to the source content
+    pub diff: Vec<PatchOperation>,
+    pub valid: bool,
+    pub errors: Vec<String>,
+}
+
+/// Outcome of a promotion that was carried out
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromoteResponse {
+    /// Version of the source configuration that was promoted
+    pub source_version: String,
+    /// Version the target configuration now serves
+    pub target_version: String,
+}
+
+/// Whether content satisfies its schema, checked without storing anything
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidateConfigResponse {
+    pub valid: bool,
+    pub errors: Vec<ValidationIssue>,
+}
+
+/// One way content fails its schema
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    /// JSON Pointer to the offending value, empty for the content itself
+    pub instance_path: String,
+    pub message: String,
+}
+
+/// Response for a configuration's usage statistics
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigStatsResponse {
+    /// Reads of any version since the server started, or `None` if more
+    /// configs are in use than the server counts individually
+    pub read_count: Option<u64>,
+}
+
+/// Response for an application's resource usage
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppUsageResponse {
+    pub application: String,
+    pub environment_count: usize,
+    pub config_count: usize,
+    /// Configured environment quota, if any
+    pub max_environments: Option<usize>,
+}
+
+/// Query parameters for listing configurations
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ListConfigsQuery {
+    /// Only list configurations whose path starts with this
+    pub prefix: Option<String>,
+    /// Include each configuration's current version and when it was written
+    #[serde(default)]
+    pub detailed: bool,
+    /// Return at most this many configurations, with a token for the rest
+    pub limit: Option<usize>,
+    /// `next_page_token` of the previous page; needs `limit`
+    pub page_token: Option<String>,
+}
+
+/// Response for listing configurations
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListConfigsResponse {
+    pub configs: Vec<ConfigSummary>,
+    /// Pass as `page_token` to continue a limited listing; absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_page_token: Option<String>,
+}
+
+/// One configuration in the admin inventory: its key and version metadata
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InventoryEntry {
+    #[serde(flatten)]
+    pub key: ConfigKey,
+    pub metadata: Metadata,
+}
+
+/// Query parameters for garbage collection
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GcQuery {
+    /// Delete the orphans found; by default they are only reported
+    #[serde(default)]
+    pub apply: bool,
+}
+
+/// Response for garbage collection
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GcResponse {
+    /// Paths of version objects not referenced by any metadata
+    pub orphans: Vec<String>,
+    /// Number of orphans deleted, zero for a dry run
+    pub removed: usize,
+}
+
 /// Response for successful operations that don't return data
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SuccessResponse {
     pub message: String,
     pub version: Option<String>,
+    /// Stable machine-readable outcome for responses that need one, e.g. [`NO_CHANGE`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+/// Query parameters for merge-patching a configuration
+#[derive(Debug, Default, Deserialize)]
+pub struct PatchConfigQuery {
+    /// Only patch if this is still the current version; `If-Match` works too.
+    /// Without either, the patch applies to the version it was merged onto.
+    pub expected_version: Option<String>,
+    /// Why the change is being made, recorded with the new version
+    pub change_message: Option<String>,
+}
+
+/// Query parameters for deleting a single configuration
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteConfigQuery {
+    /// Only delete if this is still the current version; `If-Match` works too
+    pub expected_version: Option<String>,
 }
 
+/// Response for deleting a single configuration
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteConfigResponse {
+    pub message: String,
+    /// Whether there was anything to delete; `false` on a repeated delete
+    pub existed: bool,
+    pub deleted_versions: usize,
+}
+
+/// Code for a write that matched the current version and so created nothing
+pub const NO_CHANGE: &str = "NO_CHANGE";
+
 /// Error response
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
     pub details: Option<String>,
+    /// Stable machine-readable error kind for errors that need one, e.g. [`ROUTE_NOT_FOUND`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
 }
 
+/// Code for a request to a path the server has no route for
+pub const ROUTE_NOT_FOUND: &str = "ROUTE_NOT_FOUND";
+
+/// Code for a 409 caused by writing over a version that is no longer current,
+/// as opposed to one refused for any other reason
+pub const VERSION_CONFLICT: &str = "VERSION_CONFLICT";
+
 // Conversion helpers
 impl GetConfigResponse {
     pub fn from_data_and_key(data: ConfigData, key: &ConfigKey) -> Self {
@@ -55,6 +425,7 @@ impl GetConfigResponse {
             application: key.application.clone(),
             environment: key.environment.clone(),
             config_name: key.config_name.clone(),
+            content_type: Some(data.content_type().to_string()),
             version: data.version,
             content: data.content,
             schema: data.schema,
@@ -73,6 +444,8 @@ mod tests {
             content: json!({"key": "value"}),
             schema: Some(json!({"type": "object"})),
             expected_version: Some("v1".to_string()),
+            content_type: None,
+            change_message: None,
         };
 
         let json = serde_json::to_string(&request)?;
@@ -106,6 +479,7 @@ mod tests {
             content: json!({"setting": "value"}),
             schema: json!({"type": "object"}),
             version: "v1".to_string(),
+            content_type: None,
         };
 
         let response = GetConfigResponse::from_data_and_key(data.clone(), &key);
@@ -123,6 +497,7 @@ mod tests {
         let response = SuccessResponse {
             message: "Operation successful".to_string(),
             version: Some("v5".to_string()),
+            code: None,
         };
 
         let json = serde_json::to_string(&response)?;
@@ -138,6 +513,7 @@ mod tests {
         let response = ErrorResponse {
             error: "Not Found".to_string(),
             details: Some("Configuration not found".to_string()),
+            code: None,
         };
 
         let json = serde_json::to_string(&response)?;
@@ -1,4 +1,4 @@
-use super::dto::ErrorResponse;
+use super::dto::{ErrorResponse, VERSION_CONFLICT};
 use crate::storage::StorageError;
 use axum::{
     Json,
@@ -10,26 +10,73 @@ use axum::{
 pub enum ApiError {
     NotFound(String),
     BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    Conflict(String),
+    /// A write based on a version that is no longer current
+    VersionConflict(String),
+    PreconditionFailed(String),
     InternalError(String),
+    GatewayTimeout(String),
+    NotImplemented(String),
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
-        let (status, error, details) = match self {
+impl ApiError {
+    /// Stable machine-readable kind, for errors a client needs to tell apart
+    /// from others sharing their status
+    fn code(&self) -> Option<&'static str> {
+        match self {
+            ApiError::VersionConflict(_) => Some(VERSION_CONFLICT),
+            _ => None,
+        }
+    }
+
+    fn into_parts(self) -> (StatusCode, &'static str, String) {
+        match self {
             ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, "Not Found", msg),
             ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "Bad Request", msg),
+            ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "Unauthorized", msg),
+            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, "Forbidden", msg),
+            ApiError::Conflict(msg) | ApiError::VersionConflict(msg) => {
+                (StatusCode::CONFLICT, "Conflict", msg)
+            }
+            ApiError::PreconditionFailed(msg) => {
+                (StatusCode::PRECONDITION_FAILED, "Precondition Failed", msg)
+            }
             ApiError::InternalError(msg) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Internal Server Error",
                 msg,
             ),
-        };
+            ApiError::GatewayTimeout(msg) => (StatusCode::GATEWAY_TIMEOUT, "Gateway Timeout", msg),
+            ApiError::NotImplemented(msg) => (StatusCode::NOT_IMPLEMENTED, "Not Implemented", msg),
+        }
+    }
+}
+
+impl From<ApiError> for ErrorResponse {
+    fn from(err: ApiError) -> Self {
+        let code = err.code();
+        let (_, error, details) = err.into_parts();
+        ErrorResponse {
+            error: error.to_string(),
+            details: Some(details),
+            code: code.map(str::to_string),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let code = self.code();
+        let (status, error, details) = self.into_parts();
 
         (
             status,
             Json(ErrorResponse {
                 error: error.to_string(),
                 details: Some(details),
+                code: code.map(str::to_string),
             }),
         )
             .into_response()
@@ -38,11 +85,25 @@ impl IntoResponse for ApiError {
 
 impl From<anyhow::Error> for ApiError {
     fn from(err: anyhow::Error) -> Self {
+        if let Some(object_store::Error::NotFound { .. }) = err.downcast_ref() {
+            return ApiError::NotFound(err.to_string());
+        }
+
         match err.downcast_ref::<StorageError>() {
             Some(storage_err) => match storage_err {
-                StorageError::VersionConflict { .. } => ApiError::BadRequest(err.to_string()),
+                StorageError::AliasCycle(_)
+                | StorageError::InvalidKey(_)
+                | StorageError::OnlyVersion(_) => ApiError::BadRequest(err.to_string()),
                 StorageError::NotFound(_) => ApiError::NotFound(err.to_string()),
-                StorageError::AlreadyExists(_) => ApiError::InternalError(err.to_string()),
+                StorageError::VersionConflict { .. } => ApiError::VersionConflict(err.to_string()),
+                StorageError::AlreadyExists(_) | StorageError::IsAlias { .. } => {
+                    ApiError::Conflict(err.to_string())
+                }
+                StorageError::VersionCorruption(_) | StorageError::IntegrityError(_) => {
+                    ApiError::InternalError(err.to_string())
+                }
+                StorageError::Timeout(_) => ApiError::GatewayTimeout(err.to_string()),
+                StorageError::Unsupported(_) => ApiError::NotImplemented(err.to_string()),
             },
             None => ApiError::InternalError(err.to_string()),
         }
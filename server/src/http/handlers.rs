@@ -1,149 +1,1554 @@
 use axum::{
-    Json,
-    extract::{Path, State},
+    Extension, Json,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode, Uri, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
 };
-use shared_types::ConfigKey;
-use std::sync::Arc;
-use tracing::{info, instrument};
+use bytes::Bytes;
+use futures::{Stream, StreamExt, TryStreamExt, stream};
+use shared_types::{ConfigData, ConfigKey, ConfigOrigin, ConfigSummary, TimelineStep};
+use std::{
+    collections::BTreeSet,
+    convert::Infallible,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, instrument, warn};
+
+use crate::storage::{ChangeInfo, StorageError};
+
+use super::{
+    diff,
+    dto::{
+        ActivateQuery, AppUsageResponse, BatchGetRequest, BatchGetResponse, BatchGetResult,
+        ConfigStatsResponse, CopyConfigRequest, CreateConfigRequest, CreateConfigResponse,
+        DeleteConfigQuery, DeleteConfigResponse, ErrorResponse, FromTemplateQuery,
+        FromTemplateRequest, FromTemplateResponse, GcQuery, GcResponse, GetConfigQuery,
+        GetConfigResponse, InventoryEntry, LineageResponse, ListConfigsQuery, ListConfigsResponse,
+        ListVersionsResponse, NO_CHANGE, PatchConfigQuery, PromotePreviewResponse, PromoteQuery,
+        PromoteRequest, PromoteResponse, PutConfigQuery, PutConfigRequest, ROUTE_NOT_FOUND,
+        SetAliasRequest, SetVersionAliasRequest, SnapshotRequest, SnapshotResponse,
+        SuccessResponse, TimelineResponse, ValidateConfigQuery, ValidateConfigResponse,
+        ValidationIssue, VersionDiffQuery, VersionDiffResponse,
+    },
+    error::ApiResult,
+    merge_patch,
+    middleware::Principal,
+    openapi,
+    settings::ServerSettings,
+    state::AppState,
+    strict_schema,
+};
+
+/// GET /configs/:app/:env/:config
+/// Get the current version of a configuration, optionally with flattened content
+/// or only the part a JSON Pointer names.
+/// Answers an `If-None-Match` naming the current version with 304.
+#[instrument(skip(state, request_headers))]
+pub async fn get_config(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+    Query(query): Query<GetConfigQuery>,
+    request_headers: HeaderMap,
+) -> ApiResult<Response> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Getting config: {}/{}/{}", app, env, config);
+
+    let key = resolve_allowed(&state, &valid_key(app, env, config)?).await?;
+
+    let delimiter = query
+        .delim
+        .as_deref()
+        .unwrap_or(shared_types::DEFAULT_FLATTEN_DELIMITER);
+    if query.flatten && delimiter.is_empty() {
+        return Err(super::error::ApiError::BadRequest(
+            "Flatten delimiter must not be empty".to_string(),
+        ));
+    }
+    if let Some(pointer) = &query.pointer
+        && !pointer.is_empty()
+        && !pointer.starts_with('/')
+    {
+        return Err(super::error::ApiError::BadRequest(format!(
+            "JSON Pointer {pointer:?} must be empty or start with '/'"
+        )));
+    }
+
+    if query.at.is_none()
+        && let Some(if_none_match) = request_headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+        && let Ok(Some(metadata)) = state.storage.metadata(&key).await
+    {
+        let etag = format!("\"{}\"", metadata.current_version);
+        if etag_matches(if_none_match, &etag) {
+            return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+        }
+    }
+
+    let (mut data, degraded) = match query.at {
+        Some(at) => (read_at(&state, &key, at).await?, false),
+        None => read_current(&state, &key).await?,
+    };
+    state.metrics.record_read(&key);
+
+    if let Some(pointer) = &query.pointer {
+        data.content = data
+            .content
+            .pointer_mut(pointer)
+            .map(serde_json::Value::take)
+            .ok_or_else(|| {
+                super::error::ApiError::NotFound(format!(
+                    "JSON Pointer {pointer:?} does not resolve in {key} @ {}",
+                    data.version
+                ))
+            })?;
+    }
+    if query.flatten {
+        data.content =
+            serde_json::Value::Object(shared_types::flatten_json(&data.content, delimiter));
+    }
+
+    let mut headers = HeaderMap::new();
+    if degraded {
+        headers.insert(DEGRADED_HEADER, HeaderValue::from_static("true"));
+    }
+    if let Ok(etag) = HeaderValue::from_str(&format!("\"{}\"", data.version)) {
+        headers.insert(header::ETAG, etag);
+    }
+
+    // A pointer read returns the subtree alone
+    if query.pointer.is_some() {
+        return Ok((headers, Json(data.content)).into_response());
+    }
+
+    let response = GetConfigResponse::from_data_and_key(data, &key);
+    Ok((
+        headers,
+        Json(select_fields(&response, query.fields.as_deref())?),
+    )
+        .into_response())
+}
+
+/// Keep only the comma-separated top-level `fields` of a response, or all of
+/// them when none are named
+fn select_fields(
+    response: &GetConfigResponse,
+    fields: Option<&str>,
+) -> ApiResult<serde_json::Value> {
+    let serde_json::Value::Object(mut all) = serde_json::to_value(response)
+        .map_err(|e| super::error::ApiError::InternalError(e.to_string()))?
+    else {
+        return Err(super::error::ApiError::InternalError(
+            "Config response is not an object".to_string(),
+        ));
+    };
+    let Some(fields) = fields else {
+        return Ok(serde_json::Value::Object(all));
+    };
+
+    let requested: Vec<&str> = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .collect();
+    if let Some(unknown) = requested.iter().find(|field| !all.contains_key(**field)) {
+        return Err(super::error::ApiError::BadRequest(format!(
+            "Unknown response field: {unknown}"
+        )));
+    }
+    all.retain(|name, _| requested.contains(&name.as_str()));
+    Ok(serde_json::Value::Object(all))
+}
+
+/// The version of `key` that was newest at `at`
+async fn read_at(
+    state: &AppState,
+    key: &ConfigKey,
+    at: chrono::DateTime<chrono::Utc>,
+) -> ApiResult<ConfigData> {
+    let metadata = state.storage.metadata(key).await?.ok_or_else(|| {
+        super::error::ApiError::NotFound(format!("Configuration not found: {key}"))
+    })?;
+    let version = metadata.version_at(at).ok_or_else(|| {
+        super::error::ApiError::NotFound(format!(
+            "Configuration {key} did not exist at {}",
+            at.to_rfc3339()
+        ))
+    })?;
+    Ok(state.storage.get_version(key, &version.version).await?)
+}
+
+/// Set on reads served from an earlier version because the current one is unreadable
+pub const DEGRADED_HEADER: &str = "x-config-degraded";
+
+/// Names who made a change when the request carries no authenticated principal
+pub const AUTHOR_HEADER: &str = "x-author";
+
+/// The current version of `key`. With `fallback_on_corrupt`, an unreadable
+/// current version is replaced by the newest readable one before it, and the
+/// returned flag is set.
+async fn read_current(state: &AppState, key: &ConfigKey) -> ApiResult<(ConfigData, bool)> {
+    let err = match state.storage.get(key).await {
+        Ok(data) => return Ok((data, false)),
+        Err(err) => err,
+    };
+
+    // Missing configs, timeouts and the like are not corruption
+    let unreadable = matches!(
+        err.downcast_ref::<StorageError>(),
+        None | Some(StorageError::VersionCorruption(_) | StorageError::IntegrityError(_))
+    );
+    if !state.settings.fallback_on_corrupt || !unreadable {
+        return Err(err.into());
+    }
+
+    let Some(metadata) = state.storage.metadata(key).await? else {
+        return Err(err.into());
+    };
+    let current = metadata
+        .versions
+        .iter()
+        .position(|v| v.version == metadata.current_version)
+        .unwrap_or(metadata.versions.len());
+
+    for earlier in metadata.versions[..current].iter().rev() {
+        if let Ok(data) = state.storage.get_version(key, &earlier.version).await {
+            warn!(
+                "Serving {key} @ {} because {} is unreadable: {err:#}",
+                earlier.version, metadata.current_version
+            );
+            return Ok((data, true));
+        }
+    }
+
+    Err(err.into())
+}
+
+/// Metadata reads in flight at once for a detailed listing
+const LIST_METADATA_CONCURRENCY: usize = 16;
+
+/// GET /configs
+/// List configurations, with their current version when `detailed=true`.
+/// Carries a weak `ETag` and answers a matching `If-None-Match` with 304.
+#[instrument(skip(state, headers))]
+pub async fn list_configs(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListConfigsQuery>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    info!("Listing configs with prefix: {:?}", query.prefix);
+
+    let (keys, next_page_token) = match (query.limit, query.page_token.as_deref()) {
+        (Some(0), _) | (None, Some(_)) => {
+            return Err(super::error::ApiError::BadRequest(
+                "Paging needs a limit of at least 1".to_string(),
+            ));
+        }
+        (Some(limit), token) => {
+            let page = state
+                .storage
+                .list_page(query.prefix.as_deref(), limit, token)
+                .await?;
+            (page.keys, page.next_token)
+        }
+        (None, None) => (state.storage.list(query.prefix.as_deref()).await?, None),
+    };
+    let keys = keys.into_iter().filter(|key| {
+        state
+            .settings
+            .is_app_allowed(&key.application, state.storage.key_case())
+    });
+
+    let mut configs: Vec<ConfigSummary> = if query.detailed {
+        stream::iter(keys)
+            .map(|key| {
+                let storage = state.storage.clone();
+                async move {
+                    let metadata = storage.metadata(&key).await?;
+                    Ok::<_, anyhow::Error>(metadata.map(|metadata| {
+                        let current = metadata.find_version(&metadata.current_version);
+                        ConfigSummary {
+                            current_version: current.map(|v| v.version.clone()),
+                            updated_at: current.map(|v| v.timestamp),
+                            key,
+                        }
+                    }))
+                }
+            })
+            .buffered(LIST_METADATA_CONCURRENCY)
+            // Configs deleted since listing are simply left out
+            .try_filter_map(|summary| std::future::ready(Ok(summary)))
+            .try_collect()
+            .await?
+    } else {
+        keys.map(|key| ConfigSummary {
+            key,
+            current_version: None,
+            updated_at: None,
+        })
+        .collect()
+    };
+    configs.sort_by_key(|summary| summary.key.to_path());
+
+    let etag = list_etag(&configs);
+    let etag_header = [(header::ETAG, etag.clone())];
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| etag_matches(value, &etag))
+    {
+        return Ok((StatusCode::NOT_MODIFIED, etag_header).into_response());
+    }
+
+    Ok((
+        etag_header,
+        Json(ListConfigsResponse {
+            configs,
+            next_page_token,
+        }),
+    )
+        .into_response())
+}
+
+/// Weak validator over the listed keys and, for detailed listings, their versions
+fn list_etag(configs: &[ConfigSummary]) -> String {
+    let mut hasher = DefaultHasher::new();
+    for summary in configs {
+        summary.key.hash(&mut hasher);
+        summary.current_version.hash(&mut hasher);
+    }
+    format!("W/\"{:016x}\"", hasher.finish())
+}
+
+/// Whether an `If-None-Match` value names `etag`, using weak comparison
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    if_none_match
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || opaque(candidate) == opaque(etag))
+}
+
+/// Upper bound on keys in one snapshot request
+const MAX_SNAPSHOT_KEYS: usize = 100;
+
+/// POST /configs/snapshot
+/// Read several configurations as close together as possible (best-effort:
+/// a write that lands mid-read is not detected)
+#[instrument(skip(state, request))]
+pub async fn read_snapshot(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SnapshotRequest>,
+) -> ApiResult<Json<SnapshotResponse>> {
+    info!("Reading snapshot of {} configs", request.keys.len());
+
+    if request.keys.is_empty() || request.keys.len() > MAX_SNAPSHOT_KEYS {
+        return Err(super::error::ApiError::BadRequest(format!(
+            "A snapshot needs between 1 and {MAX_SNAPSHOT_KEYS} keys"
+        )));
+    }
+    for key in &request.keys {
+        ensure_app_allowed(&state, &key.application)?;
+    }
+
+    let snapshot_id = chrono::Utc::now().to_rfc3339();
+    let reads = request.keys.into_iter().map(|key| {
+        let state = state.clone();
+        async move {
+            let key = resolve_allowed(&state, &key).await?;
+            let data = state.storage.get(&key).await?;
+            state.metrics.record_read(&key);
+            ApiResult::Ok(GetConfigResponse::from_data_and_key(data, &key))
+        }
+    });
+    let configs = futures::future::try_join_all(reads).await?;
+
+    Ok(Json(SnapshotResponse {
+        snapshot_id,
+        configs,
+    }))
+}
+
+/// Most keys a single batch get may ask for
+const MAX_BATCH_KEYS: usize = 100;
+
+/// POST /configs/batch
+/// Fetch many configurations in one request; each key succeeds or fails on its own
+#[instrument(skip(state, request))]
+pub async fn batch_get(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BatchGetRequest>,
+) -> ApiResult<Json<BatchGetResponse>> {
+    info!("Batch getting {} configs", request.keys.len());
+
+    if request.keys.len() > MAX_BATCH_KEYS {
+        return Err(super::error::ApiError::BadRequest(format!(
+            "A batch get accepts at most {MAX_BATCH_KEYS} keys"
+        )));
+    }
+
+    let reads = request.keys.into_iter().map(|key| {
+        let state = state.clone();
+        async move {
+            let read = async {
+                ensure_app_allowed(&state, &key.application)?;
+                let resolved = resolve_allowed(&state, &key).await?;
+                let data = state.storage.get(&resolved).await?;
+                state.metrics.record_read(&resolved);
+                ApiResult::Ok(GetConfigResponse::from_data_and_key(data, &resolved))
+            };
+            let result = match read.await {
+                Ok(config) => BatchGetResult::Config(config),
+                Err(e) => BatchGetResult::Error(e.into()),
+            };
+            (key.to_path(), result)
+        }
+    });
+    let configs = futures::future::join_all(reads).await.into_iter().collect();
+
+    Ok(Json(BatchGetResponse { configs }))
+}
+
+/// GET /configs/:app/:env/:config/versions
+/// List all versions of a configuration
+#[instrument(skip(state))]
+pub async fn list_versions(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+) -> ApiResult<Json<ListVersionsResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Listing versions for: {}/{}/{}", app, env, config);
+
+    let key = resolve_allowed(&state, &valid_key(app, env, config)?).await?;
+
+    let versions = state.storage.list_versions(&key).await?;
+
+    Ok(Json(ListVersionsResponse { versions }))
+}
+
+/// GET /configs/:app/:env/:config/versions/:version
+/// Get a specific version of a configuration
+#[instrument(skip(state))]
+pub async fn get_config_version(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config, version)): Path<(String, String, String, String)>,
+) -> ApiResult<Json<GetConfigResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!(
+        "Getting config version: {}/{}/{} @ {}",
+        app, env, config, version
+    );
+
+    let key = resolve_allowed(&state, &valid_key(app, env, config)?).await?;
+
+    let data = state.storage.get_version(&key, &version).await?;
+    state.metrics.record_read(&key);
+
+    Ok(Json(GetConfigResponse::from_data_and_key(data, &key)))
+}
+
+/// GET /configs/:app/:env/:config/lineage
+/// List the configurations this one was derived from, nearest first
+#[instrument(skip(state))]
+pub async fn get_lineage(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+) -> ApiResult<Json<LineageResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Getting lineage for: {}/{}/{}", app, env, config);
+
+    let key = valid_key(app, env, config)?;
+    let lineage = state.storage.lineage(&key).await?;
+
+    Ok(Json(LineageResponse { lineage }))
+}
+
+/// GET /configs/:app/:env/:config/openapi
+/// The current schema as an `OpenAPI` 3.0 fragment, under `components.schemas`
+#[instrument(skip(state))]
+pub async fn get_openapi(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+) -> ApiResult<Json<serde_json::Value>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Getting OpenAPI schema for: {}/{}/{}", app, env, config);
+
+    let key = resolve_allowed(&state, &valid_key(app, env, config)?).await?;
+    let data = state.storage.get(&key).await?;
+
+    Ok(Json(openapi::fragment(&key, &data.version, &data.schema)))
+}
+
+/// GET /configs/:app/:env/:config/stats
+/// Usage statistics for a configuration, counted in memory since startup
+#[instrument(skip(state))]
+pub async fn get_config_stats(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+) -> ApiResult<Json<ConfigStatsResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    let key = valid_key(app, env, config)?;
+
+    Ok(Json(ConfigStatsResponse {
+        read_count: state.metrics.read_count(&key),
+    }))
+}
+
+/// Upper bound on the total content size included in a timeline response
+const MAX_TIMELINE_BYTES: usize = 1024 * 1024;
+
+/// GET /configs/:app/:env/:config/timeline
+/// Every version's content in order, with the diff from the version before it
+#[instrument(skip(state))]
+pub async fn get_timeline(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+) -> ApiResult<Json<TimelineResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Getting timeline for: {}/{}/{}", app, env, config);
+
+    let key = valid_key(app, env, config)?;
+    let versions = state.storage.list_versions(&key).await?;
+
+    let mut steps: Vec<TimelineStep> = Vec::with_capacity(versions.len());
+    let mut total_bytes = 0;
+    for version in versions {
+        let data = state.storage.get_version(&key, &version.version).await?;
+
+        total_bytes += data.content.to_string().len();
+        if total_bytes > MAX_TIMELINE_BYTES && !steps.is_empty() {
+            return Ok(Json(TimelineResponse {
+                steps,
+                truncated: true,
+            }));
+        }
+
+        let diff = steps
+            .last()
+            .map(|previous| diff::json_patch(&previous.content, &data.content));
+        steps.push(TimelineStep {
+            version: version.version,
+            timestamp: version.timestamp,
+            content: data.content,
+            diff,
+        });
+    }
+
+    Ok(Json(TimelineResponse {
+        steps,
+        truncated: false,
+    }))
+}
+
+/// GET /configs/:app/:env/:config/watch
+/// Server-sent events naming each version the config switches to, starting
+/// with the one it serves now
+#[instrument(skip(state))]
+pub async fn watch_config(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+) -> ApiResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Watching config: {}/{}/{}", app, env, config);
+
+    let key = resolve_allowed(&state, &valid_key(app, env, config)?).await?;
+    // Subscribe before reading so a write landing in between is not missed
+    let receiver = state.subscribe(&key);
+    let current = state
+        .storage
+        .metadata(&key)
+        .await?
+        .map(|metadata| metadata.current_version);
+
+    let changes = stream::unfold(
+        (receiver, current.clone()),
+        |(mut receiver, mut last)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(version) if last.as_deref() == Some(version.as_str()) => {}
+                    Ok(version) => {
+                        last = Some(version.clone());
+                        return Some((version, (receiver, last)));
+                    }
+                    // Later messages still name the newest version
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    );
+    let events = stream::iter(current).chain(changes).map(|version| {
+        Ok(Event::default()
+            .event("version")
+            .id(version.clone())
+            .data(serde_json::json!({ "version": version }).to_string()))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// GET /configs/:app/:env/:config/diff?from=v1&to=v3
+/// The change between two versions' content, as a JSON Patch and by top-level key
+#[instrument(skip(state))]
+pub async fn diff_versions(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+    Query(query): Query<VersionDiffQuery>,
+) -> ApiResult<Json<VersionDiffResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    let (Some(from), Some(to)) = (query.from, query.to) else {
+        return Err(super::error::ApiError::BadRequest(
+            "Both from and to versions are required".to_string(),
+        ));
+    };
+
+    info!(
+        "Diffing config: {}/{}/{} {} -> {}",
+        app, env, config, from, to
+    );
+
+    let key = valid_key(app, env, config)?;
+    let from_data = state.storage.get_version(&key, &from).await?;
+    let to_data = state.storage.get_version(&key, &to).await?;
+
+    Ok(Json(VersionDiffResponse {
+        patch: diff::json_patch(&from_data.content, &to_data.content),
+        summary: diff::summary(&from_data.content, &to_data.content),
+        from,
+        to,
+    }))
+}
+
+/// POST /configs/:app/:env/:config/promote
+/// Write a configuration's current content to the same config in another
+/// environment, or with `dry_run=true` only preview doing so
+#[instrument(skip(state))]
+pub async fn promote_config(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+    Query(query): Query<PromoteQuery>,
+    Json(request): Json<PromoteRequest>,
+) -> ApiResult<Response> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!(
+        "Promoting config: {}/{}/{} -> {}",
+        app, env, config, request.to_environment
+    );
+
+    let source_key = valid_key(app.clone(), env, config.clone())?;
+    let target_key = valid_key(app, request.to_environment, config)?;
+    if source_key == target_key {
+        return Err(super::error::ApiError::BadRequest(
+            "A configuration cannot be promoted to its own environment".to_string(),
+        ));
+    }
+
+    let source = state.storage.get(&source_key).await?;
+    let target = if state.storage.exists(&target_key).await? {
+        Some(state.storage.get(&target_key).await?)
+    } else {
+        None
+    };
+
+    // Promotion must satisfy the target's schema when it already has one
+    let (current, schema) = match &target {
+        Some(target) => (&target.content, &target.schema),
+        None => (&serde_json::Value::Null, &source.schema),
+    };
+    let errors = validation_errors(&target_key, &source.content, schema)?;
+
+    if query.dry_run {
+        return Ok(Json(PromotePreviewResponse {
+            diff: diff::json_patch(current, &source.content),
+            valid: errors.is_empty(),
+            errors,
+        })
+        .into_response());
+    }
+
+    if !errors.is_empty() {
+        return Err(super::error::ApiError::BadRequest(format!(
+            "Content of {source_key} does not satisfy the schema of {target_key}: {}",
+            errors.join("; ")
+        )));
+    }
+    if target.is_none() {
+        enforce_new_env_policy(&state, &target_key).await?;
+    }
+
+    let config_data = shared_types::ConfigData {
+        content: source.content,
+        schema: schema.clone(),
+        version: String::new(),
+        content_type: source.content_type,
+    };
+    let change = ChangeInfo {
+        derived_from: Some(ConfigOrigin {
+            key: source_key.clone(),
+            version: source.version.clone(),
+        }),
+        ..ChangeInfo::default()
+    };
+    let target_version = state
+        .storage
+        .put_with_change(
+            &target_key,
+            &config_data,
+            target.as_ref().map(|t| t.version.as_str()),
+            &change,
+        )
+        .await
+        .map_err(|e| write_error(&state, &target_key, false, e))?;
+
+    state.metrics.record_write(&target_key);
+    state.metrics.record_version(&target_key, &target_version);
+    state.notify_watchers(&target_key, &target_version);
+
+    Ok(Json(PromoteResponse {
+        source_version: source.version,
+        target_version,
+    })
+    .into_response())
+}
+
+/// POST /configs/:app/:env/:config/copy
+/// Create another configuration from this one's current content and schema
+#[instrument(skip(state, request))]
+pub async fn copy_config(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+    Json(request): Json<CopyConfigRequest>,
+) -> ApiResult<Json<SuccessResponse>> {
+    ensure_app_allowed(&state, &app)?;
+    ensure_app_allowed(&state, &request.to.application)?;
+
+    let source_key = valid_key(app, env, config)?;
+    let target_key = valid_key(
+        request.to.application,
+        request.to.environment,
+        request.to.config_name,
+    )?;
+    info!("Copying config: {} -> {}", source_key, target_key);
+
+    if source_key == target_key {
+        return Err(super::error::ApiError::BadRequest(
+            "A configuration cannot be copied onto itself".to_string(),
+        ));
+    }
+
+    let source = state.storage.get(&source_key).await?;
+    let expected_version = if state.storage.exists(&target_key).await? {
+        if !request.overwrite {
+            return Err(super::error::ApiError::Conflict(format!(
+                "Configuration {target_key} already exists; set overwrite to replace it"
+            )));
+        }
+        Some(state.storage.get(&target_key).await?.version)
+    } else {
+        enforce_new_env_policy(&state, &target_key).await?;
+        None
+    };
+
+    let config_data = source;
+    let change = ChangeInfo {
+        derived_from: Some(ConfigOrigin {
+            key: source_key.clone(),
+            version: config_data.version.clone(),
+        }),
+        ..ChangeInfo::default()
+    };
+    let version = state
+        .storage
+        .put_with_change(
+            &target_key,
+            &config_data,
+            expected_version.as_deref(),
+            &change,
+        )
+        .await
+        .map_err(|e| write_error(&state, &target_key, false, e))?;
+
+    state.metrics.record_write(&target_key);
+    state.metrics.record_version(&target_key, &version);
+    state.notify_watchers(&target_key, &version);
+
+    Ok(Json(SuccessResponse {
+        message: format!("Configuration {source_key} copied to {target_key}"),
+        version: Some(version),
+        code: None,
+    }))
+}
+
+/// POST /configs/:app/:env/:config/validate
+/// Check content against its schema as a PUT would, without storing anything
+#[instrument(skip(state, request))]
+pub async fn validate_config(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+    Query(query): Query<ValidateConfigQuery>,
+    Json(request): Json<PutConfigRequest>,
+) -> ApiResult<Json<ValidateConfigResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Validating config: {}/{}/{}", app, env, config);
+    let key = valid_key(app, env, config)?;
+
+    let schema = resolve_schema(&state, &key, &request, query.strict_schema).await?;
+    ensure_content_shape(&request.content, &schema)?;
+    let errors = schema_violations(&key, &request.content, &schema)?;
+
+    Ok(Json(ValidateConfigResponse {
+        valid: errors.is_empty(),
+        errors,
+    }))
+}
+
+/// PUT /configs/:app/:env/:config
+#[instrument(skip(state, request))]
+pub async fn put_config(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+    Query(query): Query<PutConfigQuery>,
+    principal: Option<Extension<Principal>>,
+    headers: HeaderMap,
+    Json(request): Json<PutConfigRequest>,
+) -> ApiResult<Response> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Putting config: {}/{}/{}", app, env, config);
+    let key = valid_key(app, env, config)?;
+    let representation = wants_representation(&query, &headers);
+
+    let (expected_version, precondition) =
+        expected_version(&state, &key, &headers, request.expected_version).await?;
+    let request = PutConfigRequest {
+        expected_version,
+        ..request
+    };
+
+    let schema = resolve_schema(&state, &key, &request, query.strict_schema).await?;
+    validate_request(&key, &request, &schema)?;
+    if query.validate_history && request.schema.is_some() {
+        ensure_live_content_valid(&state, &key, &schema).await?;
+    }
+    if request.expected_version.is_none() {
+        enforce_new_env_policy(&state, &key).await?;
+    }
+
+    let change = ChangeInfo {
+        message: request.change_message,
+        author: author(principal, &headers),
+        derived_from: None,
+    };
+    let config_data = shared_types::ConfigData {
+        content: request.content,
+        schema,
+        version: String::new(),
+        content_type: request.content_type,
+    };
+
+    // Rewriting what is already current would only add a duplicate version
+    if !query.touch
+        && query.activate != Some(false)
+        && let Some(version) = unchanged_version(
+            &state,
+            &key,
+            &config_data,
+            request.expected_version.as_deref(),
+        )
+        .await
+    {
+        let success = SuccessResponse {
+            message: "no change".to_string(),
+            version: Some(version),
+            code: Some(NO_CHANGE.to_string()),
+        };
+        return write_response(&state, &key, representation, success).await;
+    }
+
+    if query.activate == Some(false) {
+        let version = state
+            .storage
+            .stage_with_change(
+                &key,
+                &config_data,
+                request.expected_version.as_deref(),
+                &change,
+            )
+            .await
+            .map_err(|e| write_error(&state, &key, precondition, e))?;
+        state.metrics.record_write(&key);
+
+        let success = SuccessResponse {
+            message: format!("Configuration {key} staged as {version}"),
+            version: Some(version),
+            code: None,
+        };
+        return write_response(&state, &key, representation, success).await;
+    }
+
+    let version = commit_write(
+        &state,
+        &key,
+        &config_data,
+        request.expected_version.as_deref(),
+        &change,
+        precondition,
+    )
+    .await?;
+
+    let success = SuccessResponse {
+        message: format!("Configuration {key} updated successfully"),
+        version: Some(version),
+        code: None,
+    };
+    write_response(&state, &key, representation, success).await
+}
+
+/// PATCH /configs/:app/:env/:config
+/// Apply an RFC 7386 merge patch to the current content and store the result
+/// as a new version
+#[instrument(skip(state, patch))]
+pub async fn patch_config(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+    Query(query): Query<PatchConfigQuery>,
+    principal: Option<Extension<Principal>>,
+    headers: HeaderMap,
+    Json(patch): Json<serde_json::Value>,
+) -> ApiResult<Json<SuccessResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Patching config: {}/{}/{}", app, env, config);
+    let key = valid_key(app, env, config)?;
+
+    let (expected_version, precondition) =
+        expected_version(&state, &key, &headers, query.expected_version).await?;
+    let current = state.storage.get(&key).await?;
+    let expected_version = expected_version.unwrap_or_else(|| current.version.clone());
+
+    let mut content = current.content;
+    merge_patch::apply(&mut content, &patch);
+    let request = PutConfigRequest {
+        content,
+        schema: None,
+        expected_version: Some(expected_version),
+        content_type: current.content_type,
+        change_message: query.change_message,
+    };
+    validate_request(&key, &request, &current.schema)?;
+
+    let config_data = shared_types::ConfigData {
+        content: request.content,
+        schema: current.schema,
+        version: String::new(),
+        content_type: request.content_type,
+    };
+    if let Some(version) = unchanged_version(
+        &state,
+        &key,
+        &config_data,
+        request.expected_version.as_deref(),
+    )
+    .await
+    {
+        return Ok(Json(SuccessResponse {
+            message: "no change".to_string(),
+            version: Some(version),
+            code: Some(NO_CHANGE.to_string()),
+        }));
+    }
+
+    let change = ChangeInfo {
+        message: request.change_message,
+        author: author(principal, &headers),
+        derived_from: None,
+    };
+    let version = commit_write(
+        &state,
+        &key,
+        &config_data,
+        request.expected_version.as_deref(),
+        &change,
+        precondition,
+    )
+    .await?;
+
+    Ok(Json(SuccessResponse {
+        message: format!("Configuration {key} patched successfully"),
+        version: Some(version),
+        code: None,
+    }))
+}
+
+/// The version a write expects to replace: `explicit` if given, else the one
+/// `If-Match` names, which must be current or the write fails with 412. The
+/// flag tells whether it came from `If-Match`, so a later conflict is a failed
+/// precondition too.
+async fn expected_version(
+    state: &AppState,
+    key: &ConfigKey,
+    headers: &HeaderMap,
+    explicit: Option<String>,
+) -> ApiResult<(Option<String>, bool)> {
+    if explicit.is_some() {
+        return Ok((explicit, false));
+    }
+    let Some(if_match) = if_match_version(headers) else {
+        return Ok((None, false));
+    };
+    ensure_current_version(state, key, &if_match).await?;
+    Ok((Some(if_match), true))
+}
+
+/// Write `data` as the new current version of `key` and announce it
+async fn commit_write(
+    state: &AppState,
+    key: &ConfigKey,
+    data: &ConfigData,
+    expected_version: Option<&str>,
+    change: &ChangeInfo,
+    precondition: bool,
+) -> ApiResult<String> {
+    let version = state
+        .storage
+        .put_with_change(key, data, expected_version, change)
+        .await
+        .map_err(|e| write_error(state, key, precondition, e))?;
+    state.metrics.record_write(key);
+    state.metrics.record_version(key, &version);
+    state.notify_watchers(key, &version);
+    Ok(version)
+}
+
+/// The response to a failed write of `key`. Losing a version race is counted
+/// and answered with 409, or 412 when the expected version was an `If-Match`
+/// precondition.
+fn write_error(
+    state: &AppState,
+    key: &ConfigKey,
+    precondition: bool,
+    error: anyhow::Error,
+) -> super::error::ApiError {
+    if let Some(conflict @ StorageError::VersionConflict { .. }) = error.downcast_ref() {
+        state.metrics.record_conflict(key);
+        let message = conflict.to_string();
+        return if precondition {
+            super::error::ApiError::PreconditionFailed(message)
+        } else {
+            super::error::ApiError::VersionConflict(message)
+        };
+    }
+    super::error::ApiError::from(error)
+}
+
+/// Fail with 412 unless `expected` is the current version of `key`
+async fn ensure_current_version(
+    state: &AppState,
+    key: &ConfigKey,
+    expected: &str,
+) -> ApiResult<()> {
+    let current = state.storage.get(key).await.ok().map(|data| data.version);
+    if current.as_deref() != Some(expected) {
+        state.metrics.record_conflict(key);
+        return Err(super::error::ApiError::PreconditionFailed(format!(
+            "{key} is not at {expected}"
+        )));
+    }
+    Ok(())
+}
+
+/// Reject `schema` with 400 if the content `key` currently serves violates it
+async fn ensure_live_content_valid(
+    state: &AppState,
+    key: &ConfigKey,
+    schema: &serde_json::Value,
+) -> ApiResult<()> {
+    let live = match state.storage.get(key).await {
+        Ok(live) => live,
+        Err(e) if matches!(e.downcast_ref(), Some(StorageError::NotFound(_))) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let errors = validation_errors(key, &live.content, schema)?;
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(super::error::ApiError::BadRequest(format!(
+        "Current content of {key} @ {} does not satisfy the new schema: {}",
+        live.version,
+        errors.join("; ")
+    )))
+}
+
+/// The version an `If-Match` header names, without quotes or a weak prefix
+fn if_match_version(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::IF_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .trim()
+                .trim_start_matches("W/")
+                .trim_matches('"')
+                .to_string()
+        })
+        .filter(|version| version != "*")
+}
+
+/// Whether a write asked for the stored config back, via `?return=representation`
+/// or a `Prefer: return=representation` header
+fn wants_representation(query: &PutConfigQuery, headers: &HeaderMap) -> bool {
+    query.return_preference.as_deref() == Some("representation")
+        || headers
+            .get_all("prefer")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split([',', ';']))
+            .any(|preference| preference.trim() == "return=representation")
+}
+
+/// The response to a successful write: the version it produced, or with
+/// `representation` the whole config as stored under that version
+async fn write_response(
+    state: &AppState,
+    key: &ConfigKey,
+    representation: bool,
+    success: SuccessResponse,
+) -> ApiResult<Response> {
+    let Some(version) = success.version.as_deref().filter(|_| representation) else {
+        return Ok(Json(success).into_response());
+    };
+
+    let data = state.storage.get_version(key, version).await?;
+    Ok(Json(GetConfigResponse::from_data_and_key(data, key)).into_response())
+}
+
+/// POST /configs/:app/:env/:config/activate/:version
+/// Make a previously written (e.g. staged) version the current one
+#[instrument(skip(state))]
+pub async fn activate_version(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config, version)): Path<(String, String, String, String)>,
+    Query(query): Query<ActivateQuery>,
+) -> ApiResult<Json<SuccessResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!(
+        "Activating config version: {}/{}/{} @ {}",
+        app, env, config, version
+    );
 
-use super::{
-    dto::{GetConfigResponse, ListVersionsResponse, PutConfigRequest, SuccessResponse},
-    error::ApiResult,
-    state::AppState,
-};
+    let key = valid_key(app, env, config)?;
+    if !query.force {
+        ensure_not_bad(&state, &key, &version).await?;
+    }
+    state.storage.activate(&key, &version).await?;
+    state.metrics.record_version(&key, &version);
+    state.notify_watchers(&key, &version);
 
-/// GET /configs/:app/:env/:config
-/// Get the current version of a configuration
+    Ok(Json(SuccessResponse {
+        message: format!("Configuration {key} now serves {version}"),
+        version: Some(version),
+        code: None,
+    }))
+}
+
+/// POST /configs/:app/:env/:config/rollback/:version
+/// Write an old version's content and schema again as a new current version,
+/// so the rollback itself shows up in the history
 #[instrument(skip(state))]
-pub async fn get_config(
+pub async fn rollback_version(
     State(state): State<Arc<AppState>>,
-    Path((app, env, config)): Path<(String, String, String)>,
-) -> ApiResult<Json<GetConfigResponse>> {
-    info!("Getting config: {}/{}/{}", app, env, config);
+    Path((app, env, config, version)): Path<(String, String, String, String)>,
+    Query(query): Query<ActivateQuery>,
+) -> ApiResult<Json<SuccessResponse>> {
+    ensure_app_allowed(&state, &app)?;
 
-    let key = ConfigKey::new(app, env, config);
+    info!(
+        "Rolling back config: {}/{}/{} to {}",
+        app, env, config, version
+    );
 
-    let data = state
+    let key = valid_key(app, env, config)?;
+    let source = state.storage.get_version(&key, &version).await?;
+    if !query.force {
+        ensure_not_bad(&state, &key, &version).await?;
+    }
+    let current = state.storage.get(&key).await?.version;
+
+    let data = shared_types::ConfigData {
+        version: String::new(),
+        ..source
+    };
+    let new_version = state
         .storage
-        .get(&key)
+        .put(&key, &data, Some(&current))
         .await
-        .map_err(|e| super::error::ApiError::NotFound(format!("Config not found: {e}")))?;
+        .map_err(|e| write_error(&state, &key, false, e))?;
+    state.metrics.record_write(&key);
+    state.metrics.record_version(&key, &new_version);
+    state.notify_watchers(&key, &new_version);
 
-    Ok(Json(GetConfigResponse::from_data_and_key(data, &key)))
+    Ok(Json(SuccessResponse {
+        message: format!("Configuration {key} rolled back to {version} as {new_version}"),
+        version: Some(new_version),
+        code: None,
+    }))
 }
 
-/// GET /configs/:app/:env/:config/versions
-/// List all versions of a configuration
+/// Refuse to make a version marked bad current again
+async fn ensure_not_bad(state: &AppState, key: &ConfigKey, version: &str) -> ApiResult<()> {
+    let bad = state
+        .storage
+        .metadata(key)
+        .await?
+        .and_then(|metadata| metadata.find_version(version).map(|v| v.bad))
+        .unwrap_or(false);
+    if bad {
+        return Err(super::error::ApiError::Conflict(format!(
+            "{key} @ {version} is marked bad; pass force=true to use it anyway"
+        )));
+    }
+    Ok(())
+}
+
+/// POST /configs/:app/:env/:config/versions/:version/mark-bad
+/// Flag a version as bad so it is not made current again without `force`
 #[instrument(skip(state))]
-pub async fn list_versions(
+pub async fn mark_version_bad(
     State(state): State<Arc<AppState>>,
-    Path((app, env, config)): Path<(String, String, String)>,
-) -> ApiResult<Json<ListVersionsResponse>> {
-    info!("Listing versions for: {}/{}/{}", app, env, config);
+    Path((app, env, config, version)): Path<(String, String, String, String)>,
+) -> ApiResult<Json<SuccessResponse>> {
+    ensure_app_allowed(&state, &app)?;
 
-    let key = ConfigKey::new(app, env, config);
+    info!(
+        "Marking config version bad: {}/{}/{} @ {}",
+        app, env, config, version
+    );
 
-    let versions = state
-        .storage
-        .list_versions(&key)
-        .await
-        .map_err(|e| super::error::ApiError::NotFound(format!("Config not found: {e}")))?;
+    let key = valid_key(app, env, config)?;
+    state.storage.mark_bad(&key, &version).await?;
 
-    Ok(Json(ListVersionsResponse { versions }))
+    Ok(Json(SuccessResponse {
+        message: format!("Marked {key} @ {version} as bad"),
+        version: Some(version),
+        code: None,
+    }))
 }
 
-/// GET /configs/:app/:env/:config/versions/:version
-/// Get a specific version of a configuration
+/// DELETE /configs/:app/:env/:config/versions/:version
+/// Remove one version; if it was current, the newest remaining version takes over
 #[instrument(skip(state))]
-pub async fn get_config_version(
+pub async fn delete_version(
     State(state): State<Arc<AppState>>,
     Path((app, env, config, version)): Path<(String, String, String, String)>,
-) -> ApiResult<Json<GetConfigResponse>> {
+) -> ApiResult<Json<SuccessResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
     info!(
-        "Getting config version: {}/{}/{} @ {}",
+        "Deleting config version: {}/{}/{} @ {}",
         app, env, config, version
     );
 
-    let key = ConfigKey::new(app, env, config);
+    let key = valid_key(app, env, config)?;
+    let previous = state
+        .storage
+        .metadata(&key)
+        .await?
+        .map(|metadata| metadata.current_version);
+    let current = state.storage.delete_version(&key, &version).await?;
+    if previous.as_deref() != Some(current.as_str()) {
+        state.metrics.record_version(&key, &current);
+        state.notify_watchers(&key, &current);
+    }
+
+    Ok(Json(SuccessResponse {
+        message: format!("Deleted {key} @ {version}; {current} is current"),
+        version: Some(current),
+        code: None,
+    }))
+}
 
-    let data = state
+/// PUT /configs/:app/:env/:config/alias
+/// Make a configuration an alias that reads through to another key
+#[instrument(skip(state))]
+pub async fn set_alias(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+    Json(request): Json<SetAliasRequest>,
+) -> ApiResult<Json<SuccessResponse>> {
+    ensure_app_allowed(&state, &app)?;
+    ensure_app_allowed(&state, &request.target.application)?;
+
+    info!(
+        "Aliasing config: {}/{}/{} -> {}",
+        app, env, config, request.target
+    );
+
+    let key = valid_key(app, env, config)?;
+    state.storage.set_alias(&key, &request.target).await?;
+
+    Ok(Json(SuccessResponse {
+        message: format!("Configuration {key} now aliases {}", request.target),
+        version: None,
+        code: None,
+    }))
+}
+
+/// PUT /configs/:app/:env/:config/aliases/:alias
+/// Tag a version with a name like `stable`, or move the name to another version
+#[instrument(skip(state))]
+pub async fn set_version_alias(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config, alias)): Path<(String, String, String, String)>,
+    Json(request): Json<SetVersionAliasRequest>,
+) -> ApiResult<Json<SuccessResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!(
+        "Aliasing config version: {}/{}/{} @ {} as {}",
+        app, env, config, request.version, alias
+    );
+
+    let key = valid_key(app, env, config)?;
+    state
         .storage
-        .get_version(&key, &version)
-        .await
-        .map_err(|e| super::error::ApiError::NotFound(format!("Config version not found: {e}")))?;
+        .set_version_alias(&key, &alias, &request.version)
+        .await?;
+
+    Ok(Json(SuccessResponse {
+        message: format!("Alias {alias} of {key} now names {}", request.version),
+        version: Some(request.version),
+        code: None,
+    }))
+}
+
+/// GET /configs/:app/:env/:config/aliases/:alias
+/// Read the version a version alias names
+#[instrument(skip(state))]
+pub async fn get_version_alias(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config, alias)): Path<(String, String, String, String)>,
+) -> ApiResult<Json<GetConfigResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!(
+        "Getting config version alias: {}/{}/{} @ {}",
+        app, env, config, alias
+    );
+
+    let key = resolve_allowed(&state, &valid_key(app, env, config)?).await?;
+    let metadata = state
+        .storage
+        .metadata(&key)
+        .await?
+        .ok_or_else(|| super::error::ApiError::NotFound(format!("Config not found: {key}")))?;
+    let version = metadata.aliases.get(&alias).ok_or_else(|| {
+        super::error::ApiError::NotFound(format!("Version alias not found: {key} @ {alias}"))
+    })?;
+
+    let data = state.storage.get_version(&key, version).await?;
+    state.metrics.record_read(&key);
 
     Ok(Json(GetConfigResponse::from_data_and_key(data, &key)))
 }
 
-/// PUT /configs/:app/:env/:config
+/// POST /configs/:app/:env
+/// Create a configuration under a server-generated unique name
 #[instrument(skip(state, request))]
-pub async fn put_config(
+pub async fn create_config(
     State(state): State<Arc<AppState>>,
-    Path((app, env, config)): Path<(String, String, String)>,
-    Json(request): Json<PutConfigRequest>,
-) -> ApiResult<Json<SuccessResponse>> {
-    info!("Putting config: {}/{}/{}", app, env, config);
-    let key = ConfigKey::new(app, env, config);
+    Path((app, env)): Path<(String, String)>,
+    Query(query): Query<PutConfigQuery>,
+    Json(request): Json<CreateConfigRequest>,
+) -> ApiResult<(StatusCode, Json<CreateConfigResponse>)> {
+    ensure_app_allowed(&state, &app)?;
+
+    let key = valid_key(app, env, uuid::Uuid::new_v4().to_string())?;
+    info!("Creating config: {}", key);
 
-    let schema = resolve_schema(&state, &key, &request).await?;
-    validate_request(&request, &schema)?;
+    let request = PutConfigRequest {
+        content: request.content,
+        schema: Some(request.schema),
+        expected_version: None,
+        content_type: request.content_type,
+        change_message: None,
+    };
+    let schema = resolve_schema(&state, &key, &request, query.strict_schema).await?;
+    validate_request(&key, &request, &schema)?;
+    enforce_new_env_policy(&state, &key).await?;
 
     let config_data = shared_types::ConfigData {
         content: request.content,
         schema,
         version: String::new(),
+        content_type: request.content_type,
     };
+    let version = state.storage.put(&key, &config_data, None).await?;
+    state.metrics.record_write(&key);
+    state.metrics.record_version(&key, &version);
+    state.notify_watchers(&key, &version);
 
-    state
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateConfigResponse {
+            application: key.application,
+            environment: key.environment,
+            config_name: key.config_name,
+            version,
+        }),
+    ))
+}
+
+/// POST /configs/:app/:env/from-template
+/// Copy every current config of a template environment into this one, with
+/// `${env}` and `${app}` placeholders in string values filled in
+#[instrument(skip(state))]
+pub async fn create_from_template(
+    State(state): State<Arc<AppState>>,
+    Path((app, env)): Path<(String, String)>,
+    Query(query): Query<FromTemplateQuery>,
+    Json(request): Json<FromTemplateRequest>,
+) -> ApiResult<Json<FromTemplateResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!(
+        "Creating {}/{} from template environment {}",
+        app, env, request.template_env
+    );
+
+    if request.template_env == env {
+        return Err(super::error::ApiError::BadRequest(
+            "An environment cannot be its own template".to_string(),
+        ));
+    }
+
+    let sources: Vec<ConfigKey> = state
         .storage
-        .put(&key, &config_data, request.expected_version.as_deref())
-        .await
-        .map_err(|e| super::error::ApiError::InternalError(e.to_string()))?;
+        .list(Some(&format!("{app}/{}", request.template_env)))
+        .await?
+        .into_iter()
+        .filter(|key| key.environment == request.template_env)
+        .collect();
+    if sources.is_empty() {
+        return Err(super::error::ApiError::NotFound(format!(
+            "Template environment {app}/{} has no configs",
+            request.template_env
+        )));
+    }
+    enforce_new_env_policy(&state, &ConfigKey::new(&app, &env, "")).await?;
 
-    Ok(Json(SuccessResponse {
-        message: format!("Configuration {key} updated successfully"),
-        version: Some(format!(
-            "v{}",
-            state
-                .storage
-                .get(&key)
-                .await
-                .map_or_else(|_| "unknown".to_string(), |d| d.version)
-        )),
-    }))
+    let mut created = Vec::new();
+    let mut skipped = Vec::new();
+    for source in sources {
+        let mut data = match state.storage.get(&source).await {
+            Ok(data) => data,
+            // Aliases and configs without versions have nothing to copy
+            Err(e) if matches!(e.downcast_ref(), Some(StorageError::NotFound(_))) => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        let target = valid_key(app.clone(), env.clone(), source.config_name.clone())?;
+        let existing = state.storage.metadata(&target).await?;
+        let expected_version = match existing {
+            Some(_) if !query.overwrite => {
+                skipped.push(target.config_name);
+                continue;
+            }
+            Some(metadata) => Some(metadata.current_version),
+            None => None,
+        };
+
+        fill_placeholders(&mut data.content, &app, &env);
+        let errors = validation_errors(&target, &data.content, &data.schema)?;
+        if !errors.is_empty() {
+            return Err(super::error::ApiError::BadRequest(format!(
+                "{target} from template {source} is invalid: {}",
+                errors.join("; ")
+            )));
+        }
+
+        let change = ChangeInfo {
+            derived_from: Some(ConfigOrigin {
+                key: source,
+                version: data.version.clone(),
+            }),
+            ..ChangeInfo::default()
+        };
+        let version = state
+            .storage
+            .put_with_change(&target, &data, expected_version.as_deref(), &change)
+            .await?;
+        state.metrics.record_write(&target);
+        state.metrics.record_version(&target, &version);
+        state.notify_watchers(&target, &version);
+        created.push(target.config_name);
+    }
+
+    Ok(Json(FromTemplateResponse { created, skipped }))
+}
+
+/// Replace `${env}` and `${app}` in every string within `value`
+fn fill_placeholders(value: &mut serde_json::Value, app: &str, env: &str) {
+    match value {
+        serde_json::Value::String(s) if s.contains("${") => {
+            *s = s.replace("${env}", env).replace("${app}", app);
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                fill_placeholders(item, app, env);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for item in map.values_mut() {
+                fill_placeholders(item, app, env);
+            }
+        }
+        _ => {}
+    }
 }
 
-fn validate_request(request: &PutConfigRequest, schema: &serde_json::Value) -> ApiResult<()> {
-    if !request.content.is_object() {
+/// Content may be any JSON value its schema accepts; only a schema declaring
+/// an object at the root gets the blunt shape error in place of a violation
+/// list
+fn ensure_content_shape(content: &serde_json::Value, schema: &serde_json::Value) -> ApiResult<()> {
+    if schema.get("type").and_then(serde_json::Value::as_str) == Some("object")
+        && !content.is_object()
+    {
         return Err(super::error::ApiError::BadRequest(
             "Content must be a JSON object".to_string(),
         ));
     }
+    Ok(())
+}
 
-    // The jsonschema crate automatically validates that the schema is valid when compiling
-    // It will return an error if the schema itself is invalid
-    let compiled_schema = jsonschema::Validator::new(schema)
-        .map_err(|e| super::error::ApiError::BadRequest(format!("Invalid JSON Schema: {e}")))?;
-
-    // Validate content against schema
-    let validation_result = compiled_schema.validate(&request.content);
-    if let Err(errors) = validation_result {
-        let error_messages: Vec<String> = errors
-            .take(10) // Limit to first 10 errors to avoid huge error messages
-            .map(|e| {
-                let path = e.instance_path.to_string();
-                let path_str = if path.is_empty() || path == "/" {
-                    "root".to_string()
-                } else {
-                    path
-                };
-                format!("{path_str}: {e}")
-            })
-            .collect();
+fn validate_request(
+    key: &ConfigKey,
+    request: &PutConfigRequest,
+    schema: &serde_json::Value,
+) -> ApiResult<()> {
+    ensure_content_shape(&request.content, schema)?;
 
+    let error_messages = validation_errors(key, &request.content, schema)?;
+    if !error_messages.is_empty() {
         let error_count = error_messages.len();
         let mut message = error_messages.join("; ");
-        if error_count == 10 {
+        if error_count == MAX_VALIDATION_ERRORS {
             message.push_str("; ... and more errors");
         }
 
@@ -155,10 +1560,206 @@ fn validate_request(request: &PutConfigRequest, schema: &serde_json::Value) -> A
     Ok(())
 }
 
+/// Cap on reported validation errors to avoid huge error messages
+const MAX_VALIDATION_ERRORS: usize = 10;
+
+/// Validate `content` against `schema`, returning one message per error
+///
+/// Fails only if the schema itself does not compile.
+fn validation_errors(
+    key: &ConfigKey,
+    content: &serde_json::Value,
+    schema: &serde_json::Value,
+) -> ApiResult<Vec<String>> {
+    Ok(schema_violations(key, content, schema)?
+        .into_iter()
+        .map(|issue| {
+            let path = if issue.instance_path.is_empty() || issue.instance_path == "/" {
+                "root"
+            } else {
+                &issue.instance_path
+            };
+            format!("{path}: {}", issue.message)
+        })
+        .collect())
+}
+
+/// Validate `content` against `schema`, returning where and how it fails
+fn schema_violations(
+    key: &ConfigKey,
+    content: &serde_json::Value,
+    schema: &serde_json::Value,
+) -> ApiResult<Vec<ValidationIssue>> {
+    // The jsonschema crate automatically validates that the schema is valid when compiling
+    // It will return an error if the schema itself is invalid
+    let compiled_schema = jsonschema::Validator::new(schema)
+        .map_err(|e| super::error::ApiError::BadRequest(format!("Invalid JSON Schema: {e}")))?;
+
+    let Err(errors) = compiled_schema.validate(content) else {
+        return Ok(Vec::new());
+    };
+
+    Ok(errors
+        .take(MAX_VALIDATION_ERRORS)
+        .map(|e| {
+            let instance_path = e.instance_path.to_string();
+            // Only paths are logged: the offending values may be secrets
+            warn!(
+                config = %key,
+                schema_path = %e.schema_path,
+                instance_path = %instance_path,
+                "Content validation failed"
+            );
+            ValidationIssue {
+                instance_path,
+                message: e.to_string(),
+            }
+        })
+        .collect())
+}
+
+/// The key addressed by a request's path, rejected with 400 unless each part
+/// is an ordinary path segment
+fn valid_key(app: String, env: String, config: String) -> ApiResult<ConfigKey> {
+    let key = ConfigKey::new(app, env, config);
+    key.validate()
+        .map_err(|e| super::error::ApiError::BadRequest(e.to_string()))?;
+    Ok(key)
+}
+
+/// The current version of `key` if it already holds exactly `data`, provided
+/// it is the version the writer expected when they named one
+async fn unchanged_version(
+    state: &AppState,
+    key: &ConfigKey,
+    data: &ConfigData,
+    expected_version: Option<&str>,
+) -> Option<String> {
+    let current = state.storage.get(key).await.ok()?;
+    (current.content == data.content
+        && current.schema == data.schema
+        && current.content_type() == data.content_type()
+        && expected_version.is_none_or(|expected| expected == current.version))
+    .then_some(current.version)
+}
+
+/// Who is making a change: the authenticated principal, or else whoever the
+/// `X-Author` header names
+fn author(principal: Option<Extension<Principal>>, headers: &HeaderMap) -> Option<String> {
+    principal
+        .map(|Extension(principal)| principal.0)
+        .or_else(|| {
+            headers
+                .get(AUTHOR_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|author| !author.is_empty())
+                .map(str::to_string)
+        })
+}
+
+/// Follow aliases from `key`, refusing with 403 if they lead into an
+/// application this server does not serve
+async fn resolve_allowed(state: &AppState, key: &ConfigKey) -> ApiResult<ConfigKey> {
+    let resolved = state.storage.resolve_alias(key).await?;
+    ensure_app_allowed(state, &resolved.application)?;
+    Ok(resolved)
+}
+
+/// Reject requests for applications this server is not configured to serve
+fn ensure_app_allowed(state: &AppState, app: &str) -> ApiResult<()> {
+    if state.settings.is_app_allowed(app, state.storage.key_case()) {
+        Ok(())
+    } else {
+        Err(super::error::ApiError::Forbidden(format!(
+            "Application {app} is not served by this server"
+        )))
+    }
+}
+
+/// Checks for writes that may create `key`'s environment: its name must be
+/// allowed and the application must be below its environment limit.
+///
+/// The environments are listed before the write rather than locked, so writes
+/// racing to create different new environments can each pass and overshoot
+/// the limit; it bounds environment sprawl rather than guaranteeing a count.
+async fn enforce_new_env_policy(state: &AppState, key: &ConfigKey) -> ApiResult<()> {
+    let settings = &state.settings;
+    if settings.max_envs_per_app.is_none() && !settings.restricts_env_names() {
+        return Ok(());
+    }
+
+    // Listed names are stored names, so compare the environment as stored
+    let env = state.storage.key_case().apply(&key.environment);
+    let environments = app_environments(state, &key.application).await?;
+    if environments.contains(env.as_ref()) {
+        return Ok(());
+    }
+
+    if let Err(reason) = settings.check_env_name(&env) {
+        return Err(super::error::ApiError::BadRequest(reason));
+    }
+
+    if let Some(max_envs) = settings
+        .max_envs_per_app
+        .filter(|&max_envs| environments.len() >= max_envs)
+    {
+        return Err(super::error::ApiError::Conflict(format!(
+            "Application {} already has {} environments (limit {max_envs})",
+            key.application,
+            environments.len()
+        )));
+    }
+
+    Ok(())
+}
+
+async fn app_environments(state: &AppState, app: &str) -> ApiResult<BTreeSet<String>> {
+    Ok(state
+        .storage
+        .list_environments(app)
+        .await?
+        .into_iter()
+        .collect())
+}
+
+/// Reject schemas too large or deeply nested to compile cheaply, before the
+/// validator ever sees them
+fn check_schema_limits(settings: &ServerSettings, schema: &serde_json::Value) -> ApiResult<()> {
+    let bytes = serde_json::to_vec(schema)
+        .map_err(|e| super::error::ApiError::InternalError(e.to_string()))?
+        .len();
+    if bytes > settings.max_schema_bytes {
+        return Err(super::error::ApiError::BadRequest(format!(
+            "Schema is {bytes} bytes, more than the limit of {}",
+            settings.max_schema_bytes
+        )));
+    }
+
+    let depth = nesting_depth(schema);
+    if depth > settings.max_schema_depth {
+        return Err(super::error::ApiError::BadRequest(format!(
+            "Schema is nested {depth} levels deep, more than the limit of {}",
+            settings.max_schema_depth
+        )));
+    }
+    Ok(())
+}
+
+/// How many objects and arrays deep a value nests; scalars are 0
+fn nesting_depth(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Object(map) => 1 + map.values().map(nesting_depth).max().unwrap_or(0),
+        serde_json::Value::Array(items) => 1 + items.iter().map(nesting_depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
 async fn resolve_schema(
     state: &Arc<AppState>,
     key: &ConfigKey,
     request: &PutConfigRequest,
+    strict: bool,
 ) -> ApiResult<serde_json::Value> {
     if let Some(schema) = &request.schema {
         if !schema.is_object() {
@@ -166,6 +1767,16 @@ async fn resolve_schema(
                 "Schema must be a valid JSON Schema object".to_string(),
             ));
         }
+        check_schema_limits(&state.settings, schema)?;
+        if strict {
+            let unknown = strict_schema::unknown_keywords(schema);
+            if !unknown.is_empty() {
+                return Err(super::error::ApiError::BadRequest(format!(
+                    "Invalid JSON Schema: {}",
+                    unknown.join("; ")
+                )));
+            }
+        }
         return Ok(schema.clone());
     }
 
@@ -207,22 +1818,148 @@ pub async fn delete_environment(
     State(state): State<Arc<AppState>>,
     Path((app, env)): Path<(String, String)>,
 ) -> ApiResult<Json<SuccessResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
     info!("Deleting all configs for: {}/{}", app, env);
 
-    let deleted_count = state
-        .storage
-        .delete_environment(&app, &env)
-        .await
-        .map_err(|e| {
-            super::error::ApiError::InternalError(format!("Failed to delete environment: {e}"))
-        })?;
+    let deleted_count = state.storage.delete_environment(&app, &env).await?;
 
     Ok(Json(SuccessResponse {
         message: format!("Deleted {deleted_count} configurations for {app}/{env}"),
         version: None,
+        code: None,
+    }))
+}
+
+/// DELETE /configs/:app/:env/:config
+/// Delete a configuration with all of its versions. Deleting a config that
+/// does not exist succeeds too, so a retried delete is safe. With an expected
+/// version (`If-Match` or `expected_version`), a config whose current version
+/// differs is left alone and 409 returned.
+#[instrument(skip(state, headers))]
+pub async fn delete_config(
+    State(state): State<Arc<AppState>>,
+    Path((app, env, config)): Path<(String, String, String)>,
+    Query(query): Query<DeleteConfigQuery>,
+    headers: HeaderMap,
+) -> ApiResult<Json<DeleteConfigResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Deleting config: {}/{}/{}", app, env, config);
+
+    let expected_version = query
+        .expected_version
+        .or_else(|| if_match_version(&headers));
+
+    let key = valid_key(app, env, config)?;
+    let deleted = state
+        .storage
+        .delete(&key, expected_version.as_deref())
+        .await
+        .map_err(|e| write_error(&state, &key, false, e))?;
+
+    Ok(Json(DeleteConfigResponse {
+        message: match deleted {
+            Some(count) => format!("Deleted {key} and its {count} versions"),
+            None => format!("{key} does not exist"),
+        },
+        existed: deleted.is_some(),
+        deleted_versions: deleted.unwrap_or(0),
+    }))
+}
+
+/// GET /apps/:app/usage
+/// Report how many environments and configurations an application uses
+#[instrument(skip(state))]
+pub async fn get_app_usage(
+    State(state): State<Arc<AppState>>,
+    Path(app): Path<String>,
+) -> ApiResult<Json<AppUsageResponse>> {
+    ensure_app_allowed(&state, &app)?;
+
+    info!("Getting usage for: {}", app);
+
+    let keys = state.storage.list(Some(&app)).await?;
+    let environments: BTreeSet<&str> = keys.iter().map(|k| k.environment.as_str()).collect();
+
+    Ok(Json(AppUsageResponse {
+        environment_count: environments.len(),
+        config_count: keys.len(),
+        max_environments: state.settings.max_envs_per_app,
+        application: app,
     }))
 }
 
+/// GET /admin/inventory
+/// Stream every served configuration's key and version metadata as a JSON array
+#[instrument(skip(state))]
+pub async fn get_inventory(State(state): State<Arc<AppState>>) -> ApiResult<Response> {
+    info!("Exporting inventory");
+
+    let keys: Vec<ConfigKey> = state
+        .storage
+        .list(None)
+        .await?
+        .into_iter()
+        .filter(|key| {
+            state
+                .settings
+                .is_app_allowed(&key.application, state.storage.key_case())
+        })
+        .collect();
+
+    // Metadata is read one config at a time as the body is written, so the
+    // response never holds the whole inventory in memory
+    let storage = state.storage.clone();
+    let entries = stream::iter(keys)
+        .then(move |key| {
+            let storage = storage.clone();
+            async move {
+                let metadata = storage.metadata(&key).await?;
+                Ok::<_, anyhow::Error>(metadata.map(|metadata| InventoryEntry { key, metadata }))
+            }
+        })
+        // Configs deleted since listing are simply left out
+        .try_filter_map(|entry| std::future::ready(Ok(entry)))
+        .enumerate()
+        .map(|(i, entry)| {
+            let mut chunk = if i == 0 { Vec::new() } else { b",".to_vec() };
+            serde_json::to_writer(&mut chunk, &entry?)?;
+            Ok::<_, anyhow::Error>(Bytes::from(chunk))
+        });
+
+    let body = stream::once(async { Ok(Bytes::from_static(b"[")) })
+        .chain(entries)
+        .chain(stream::once(async { Ok(Bytes::from_static(b"]")) }));
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// POST /admin/gc
+/// Report version objects no metadata references, deleting them with `apply=true`
+#[instrument(skip(state))]
+pub async fn collect_garbage(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<GcQuery>,
+) -> ApiResult<Json<GcResponse>> {
+    info!("Collecting garbage (apply: {})", query.apply);
+
+    let orphans = state.storage.collect_garbage(query.apply).await?;
+    let removed = if query.apply { orphans.len() } else { 0 };
+    if !orphans.is_empty() {
+        warn!(
+            orphans = orphans.len(),
+            removed, "Found orphaned version objects"
+        );
+    }
+
+    Ok(Json(GcResponse { orphans, removed }))
+}
+
 /// GET /health
 /// Health check endpoint
 pub async fn health_check() -> Json<serde_json::Value> {
@@ -232,3 +1969,27 @@ pub async fn health_check() -> Json<serde_json::Value> {
         "timestamp": chrono::Utc::now().to_rfc3339(),
     }))
 }
+
+/// GET /metrics
+/// Per-configuration metrics in the Prometheus text format
+pub async fn get_metrics(State(state): State<Arc<AppState>>) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+        .into_response()
+}
+
+/// Fallback for paths no route matches, answered in the same JSON shape as
+/// every other error
+pub async fn route_not_found(uri: Uri) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: "Not Found".to_string(),
+            details: Some(format!("No route for {}", uri.path())),
+            code: Some(ROUTE_NOT_FOUND.to_string()),
+        }),
+    )
+        .into_response()
+}
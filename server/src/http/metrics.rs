@@ -0,0 +1,326 @@
+use shared_types::ConfigKey;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Default number of configs that get their own labeled series
+pub const DEFAULT_MAX_METRIC_CONFIGS: usize = 1000;
+
+/// Label value shared by every config beyond the cap
+const OVERFLOW_LABEL: &str = "_other";
+
+/// Upper bounds, in seconds, of the request latency histogram buckets
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Default)]
+struct ConfigSeries {
+    reads: u64,
+    writes: u64,
+    conflicts: u64,
+    version: Option<u64>,
+}
+
+/// Requests answered by one route, keyed by method and matched path
+#[derive(Debug, Default)]
+struct RouteSeries {
+    statuses: BTreeMap<u16, u64>,
+    /// Cumulative count per entry of [`LATENCY_BUCKETS`]
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    seconds: f64,
+    count: u64,
+}
+
+/// One metric name and how to read its value from a config's series
+struct Family {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    value: fn(&ConfigSeries) -> Option<u64>,
+}
+
+const FAMILIES: [Family; 4] = [
+    Family {
+        name: "config_reads_total",
+        kind: "counter",
+        help: "Reads served per configuration",
+        value: |series| Some(series.reads),
+    },
+    Family {
+        name: "config_writes_total",
+        kind: "counter",
+        help: "Versions written per configuration",
+        value: |series| Some(series.writes),
+    },
+    Family {
+        name: "config_version_conflicts_total",
+        kind: "counter",
+        help: "Writes rejected because the expected version was stale",
+        value: |series| Some(series.conflicts),
+    },
+    Family {
+        name: "config_version",
+        kind: "gauge",
+        help: "Version number each configuration currently serves",
+        value: |series| series.version,
+    },
+];
+
+/// Per-configuration read, write and version metrics, plus request counts
+/// and latencies per route, rendered in the Prometheus text format
+///
+/// Only the first `max_configs` configs seen get their own series; activity
+/// on any others is added to a single series labeled `_other`, so the number
+/// of series stays bounded however many configs exist. Routes are labeled by
+/// their pattern rather than the requested path, so they are bounded too.
+pub struct ConfigMetrics {
+    max_configs: usize,
+    series: Mutex<HashMap<ConfigKey, ConfigSeries>>,
+    routes: Mutex<BTreeMap<(String, String), RouteSeries>>,
+}
+
+impl Default for ConfigMetrics {
+    fn default() -> Self {
+        Self::with_max_configs(DEFAULT_MAX_METRIC_CONFIGS)
+    }
+}
+
+impl ConfigMetrics {
+    pub fn with_max_configs(max_configs: usize) -> Self {
+        Self {
+            max_configs,
+            series: Mutex::default(),
+            routes: Mutex::default(),
+        }
+    }
+
+    pub fn record_read(&self, key: &ConfigKey) {
+        self.update(key, |series| series.reads += 1);
+    }
+
+    /// Reads of `key` recorded so far, or `None` if it is beyond the cap and
+    /// its reads are only counted toward `_other`
+    pub fn read_count(&self, key: &ConfigKey) -> Option<u64> {
+        let all = self.series.lock().ok()?;
+        match all.get(key) {
+            Some(series) => Some(series.reads),
+            None => (all.len() < self.max_configs).then_some(0),
+        }
+    }
+
+    pub fn record_write(&self, key: &ConfigKey) {
+        self.update(key, |series| series.writes += 1);
+    }
+
+    /// Record a write to `key` refused for naming a stale expected version
+    pub fn record_conflict(&self, key: &ConfigKey) {
+        self.update(key, |series| series.conflicts += 1);
+    }
+
+    /// Record a request answered by the route matching `route`
+    pub fn record_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let Ok(mut routes) = self.routes.lock() else {
+            return;
+        };
+        let series = routes
+            .entry((method.to_string(), route.to_string()))
+            .or_default();
+        *series.statuses.entry(status).or_default() += 1;
+        let seconds = elapsed.as_secs_f64();
+        for (count, bound) in series.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *count += 1;
+            }
+        }
+        series.seconds += seconds;
+        series.count += 1;
+    }
+
+    /// Record the version `key` now serves, for versions of the form `v<N>`
+    pub fn record_version(&self, key: &ConfigKey, version: &str) {
+        let Some(number) = version.strip_prefix('v').and_then(|n| n.parse().ok()) else {
+            return;
+        };
+        self.update(key, |series| series.version = Some(number));
+    }
+
+    fn update(&self, key: &ConfigKey, apply: impl FnOnce(&mut ConfigSeries)) {
+        let Ok(mut all) = self.series.lock() else {
+            return;
+        };
+        let tracked = all.contains_key(key) || all.len() < self.max_configs;
+        if tracked {
+            apply(all.entry(key.clone()).or_default());
+        } else {
+            let overflow = all
+                .entry(ConfigKey::new(
+                    OVERFLOW_LABEL,
+                    OVERFLOW_LABEL,
+                    OVERFLOW_LABEL,
+                ))
+                .or_default();
+            // A version number summed across configs means nothing
+            let version = overflow.version;
+            apply(overflow);
+            overflow.version = version;
+        }
+    }
+
+    /// All series in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let Ok(all) = self.series.lock() else {
+            return String::new();
+        };
+        let mut entries: Vec<(String, &ConfigSeries)> = all
+            .iter()
+            .map(|(key, series)| (labels(key), series))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut out = String::new();
+        for family in FAMILIES {
+            let Family {
+                name, kind, help, ..
+            } = family;
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            for (labels, series) in &entries {
+                if let Some(value) = (family.value)(series) {
+                    let _ = writeln!(out, "{name}{{{labels}}} {value}");
+                }
+            }
+        }
+        drop(all);
+        self.render_routes(&mut out);
+        out
+    }
+
+    fn render_routes(&self, out: &mut String) {
+        let Ok(routes) = self.routes.lock() else {
+            return;
+        };
+
+        let _ = writeln!(
+            out,
+            "# HELP http_requests_total Requests answered per route"
+        );
+        let _ = writeln!(out, "# TYPE http_requests_total counter");
+        for ((method, route), series) in routes.iter() {
+            let labels = route_labels(method, route);
+            for (status, count) in &series.statuses {
+                let _ = writeln!(
+                    out,
+                    "http_requests_total{{{labels},status=\"{status}\"}} {count}"
+                );
+            }
+        }
+
+        let name = "http_request_duration_seconds";
+        let _ = writeln!(out, "# HELP {name} Time taken to answer requests per route");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for ((method, route), series) in routes.iter() {
+            let labels = route_labels(method, route);
+            for (count, bound) in series.buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{bound}\"}} {count}");
+            }
+            let count = series.count;
+            let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {count}");
+            let _ = writeln!(out, "{name}_sum{{{labels}}} {}", series.seconds);
+            let _ = writeln!(out, "{name}_count{{{labels}}} {count}");
+        }
+    }
+}
+
+fn route_labels(method: &str, route: &str) -> String {
+    format!(
+        "method=\"{}\",route=\"{}\"",
+        escape_label(method),
+        escape_label(route)
+    )
+}
+
+fn labels(key: &ConfigKey) -> String {
+    format!(
+        "app=\"{}\",env=\"{}\",config=\"{}\"",
+        escape_label(&key.application),
+        escape_label(&key.environment),
+        escape_label(&key.config_name)
+    )
+}
+
+/// Escape a label value as the text format requires
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_series_beyond_cap_are_aggregated() {
+        let metrics = ConfigMetrics::with_max_configs(1);
+        let a = ConfigKey::new("app", "dev", "a");
+        let b = ConfigKey::new("app", "dev", "b");
+        let c = ConfigKey::new("app", "dev", "c");
+
+        metrics.record_read(&a);
+        metrics.record_read(&b);
+        metrics.record_read(&c);
+        metrics.record_version(&b, "v4");
+
+        let text = metrics.render();
+        assert!(text.contains(r#"config_reads_total{app="app",env="dev",config="a"} 1"#));
+        assert!(
+            text.contains(r#"config_reads_total{app="_other",env="_other",config="_other"} 2"#)
+        );
+        assert!(!text.contains(r#"config="b""#));
+        assert!(!text.contains("config_version{"));
+
+        assert_eq!(metrics.read_count(&a), Some(1));
+        assert_eq!(metrics.read_count(&b), None);
+    }
+
+    #[test]
+    fn test_request_latency_histogram() {
+        let metrics = ConfigMetrics::default();
+        let route = "/configs/:app/:env/:config";
+
+        metrics.record_request("GET", route, 200, Duration::from_millis(3));
+        metrics.record_request("GET", route, 200, Duration::from_millis(300));
+        metrics.record_request("GET", route, 404, Duration::from_secs(20));
+
+        let text = metrics.render();
+        let labels = r#"method="GET",route="/configs/:app/:env/:config""#;
+        assert!(text.contains(&format!(
+            r#"http_requests_total{{{labels},status="200"}} 2"#
+        )));
+        assert!(text.contains(&format!(
+            r#"http_requests_total{{{labels},status="404"}} 1"#
+        )));
+        for (bound, count) in [
+            ("0.005", 1),
+            ("0.25", 1),
+            ("0.5", 2),
+            ("10", 2),
+            ("+Inf", 3),
+        ] {
+            let line =
+                format!(r#"http_request_duration_seconds_bucket{{{labels},le="{bound}"}} {count}"#);
+            assert!(text.contains(&line), "missing {line}");
+        }
+        assert!(text.contains(&format!(
+            "http_request_duration_seconds_count{{{labels}}} 3"
+        )));
+    }
+
+    #[test]
+    fn test_label_values_escaped() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,114 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{Method, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Write,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::{error::ApiError, settings::AuthMode, state::AppState};
+
+/// Routes open without a key whatever the auth mode
+const PUBLIC_PATHS: &[&str] = &["/health"];
+
+/// Routes that use POST but only read, so stay open under `write-only` auth
+const READ_ONLY_POSTS: &[&str] = &["/configs/snapshot", "/configs/batch"];
+
+/// Hex digits of a key's hash that name it as a [`Principal`]
+const KEY_FINGERPRINT_LEN: usize = 12;
+
+/// Who a request authenticated as, attached to requests that presented a
+/// valid API key. Keys carry no names, so a key is identified by a
+/// fingerprint of its hash, never the key itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal(pub String);
+
+impl Principal {
+    fn for_api_key(key: &str) -> Self {
+        let mut fingerprint = String::from("api-key:");
+        for byte in Sha256::digest(key.as_bytes())
+            .iter()
+            .take(KEY_FINGERPRINT_LEN / 2)
+        {
+            let _ = write!(fingerprint, "{byte:02x}");
+        }
+        Self(fingerprint)
+    }
+}
+
+/// Fail a request with 504 if its handler runs longer than `limit`
+pub async fn route_timeout(
+    State(limit): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match tokio::time::timeout(limit, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => ApiError::GatewayTimeout(format!("Request did not complete within {limit:?}"))
+            .into_response(),
+    }
+}
+
+/// Count each request and its latency against the route it matched
+pub async fn track_requests(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let route = request.extensions().get::<MatchedPath>().map_or_else(
+        || request.uri().path().to_string(),
+        |path| path.as_str().to_string(),
+    );
+    let started = Instant::now();
+    let response = next.run(request).await;
+    state.metrics.record_request(
+        method.as_str(),
+        &route,
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+    response
+}
+
+/// Reject requests without a valid `Authorization: Bearer <key>` with 401,
+/// for the requests the configured [`AuthMode`] protects
+pub async fn require_api_key(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let is_read = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) || (request.method() == Method::POST && READ_ONLY_POSTS.contains(&path));
+    let protected = match state.settings.auth_mode {
+        AuthMode::Off => false,
+        AuthMode::All => !PUBLIC_PATHS.contains(&path),
+        AuthMode::WriteOnly => !is_read,
+    };
+    if !protected {
+        return next.run(request).await;
+    }
+
+    let key = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match key.map(str::trim) {
+        Some(key) if state.settings.api_keys.contains(key) => {
+            let principal = Principal::for_api_key(key);
+            request.extensions_mut().insert(principal);
+            next.run(request).await
+        }
+        Some(_) => ApiError::Unauthorized("Invalid API key".to_string()).into_response(),
+        None => ApiError::Unauthorized("Missing bearer API key".to_string()).into_response(),
+    }
+}
@@ -1,7 +1,16 @@
+pub mod diff;
 pub mod dto;
 pub mod error;
 pub mod handlers;
+pub mod merge_patch;
+pub mod metrics;
+pub mod middleware;
+pub mod openapi;
 pub mod server;
+pub mod settings;
 pub mod state;
+pub mod strict_schema;
+pub mod watch;
 
-pub use server::start_server;
+pub use server::{create_router, start_server};
+pub use settings::{AuthMode, ServerSettings};
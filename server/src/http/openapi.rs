@@ -0,0 +1,224 @@
+use serde_json::{Map, Value, json};
+use shared_types::ConfigKey;
+
+/// `OpenAPI` version the fragments declare
+const OPENAPI_VERSION: &str = "3.0.3";
+
+/// Keywords an `OpenAPI` 3.0 schema object accepts with the same meaning as in
+/// JSON Schema, copied through unchanged
+const PASSTHROUGH_KEYWORDS: &[&str] = &[
+    "title",
+    "description",
+    "multipleOf",
+    "maximum",
+    "exclusiveMaximum",
+    "minimum",
+    "exclusiveMinimum",
+    "maxLength",
+    "minLength",
+    "pattern",
+    "maxItems",
+    "minItems",
+    "uniqueItems",
+    "maxProperties",
+    "minProperties",
+    "required",
+    "enum",
+    "type",
+    "format",
+    "default",
+    "readOnly",
+    "writeOnly",
+    "deprecated",
+];
+
+/// A minimal `OpenAPI` document whose `components.schemas` holds `schema` under
+/// the config's name, alongside any definitions it references
+pub fn fragment(key: &ConfigKey, version: &str, schema: &Value) -> Value {
+    let mut schemas = Map::new();
+
+    // Local definitions become sibling components so `$ref`s can reach them
+    if let Value::Object(root) = schema {
+        for defs in ["definitions", "$defs"] {
+            if let Some(Value::Object(entries)) = root.get(defs) {
+                for (name, subschema) in entries {
+                    schemas.insert(name.clone(), translate(subschema, &key.config_name));
+                }
+            }
+        }
+    }
+    schemas.insert(key.config_name.clone(), translate(schema, &key.config_name));
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {"title": key.to_string(), "version": version},
+        "paths": {},
+        "components": {"schemas": schemas},
+    })
+}
+
+/// Rewrite a JSON Schema as an `OpenAPI` 3.0 schema object. Keywords with an
+/// `OpenAPI` spelling are converted; ones with no equivalent are dropped, which
+/// only ever loosens the schema.
+fn translate(schema: &Value, root_name: &str) -> Value {
+    let map = match schema {
+        Value::Bool(true) => return json!({}),
+        Value::Bool(false) => return json!({"not": {}}),
+        Value::Object(map) => map,
+        other => return other.clone(),
+    };
+
+    let mut out = Map::new();
+    for (keyword, value) in map {
+        match (keyword.as_str(), value) {
+            ("$ref", Value::String(reference)) => {
+                out.insert(keyword.clone(), json!(rewrite_ref(reference, root_name)));
+            }
+            ("const", value) => {
+                out.insert("enum".to_string(), json!([value]));
+            }
+            ("examples", Value::Array(examples)) => {
+                if let Some(first) = examples.first() {
+                    out.insert("example".to_string(), first.clone());
+                }
+            }
+            ("type", Value::Array(types)) => translate_type_list(types, &mut out),
+            // Draft 6+ numeric exclusive bounds are draft 4 style in OpenAPI 3.0
+            ("exclusiveMinimum" | "exclusiveMaximum", Value::Number(bound)) => {
+                let inclusive = if keyword == "exclusiveMinimum" {
+                    "minimum"
+                } else {
+                    "maximum"
+                };
+                out.insert(inclusive.to_string(), Value::Number(bound.clone()));
+                out.insert(keyword.clone(), Value::Bool(true));
+            }
+            ("properties", Value::Object(properties)) => {
+                let properties = properties
+                    .iter()
+                    .map(|(name, subschema)| (name.clone(), translate(subschema, root_name)))
+                    .collect();
+                out.insert(keyword.clone(), Value::Object(properties));
+            }
+            // OpenAPI allows a boolean here but nowhere else
+            ("additionalProperties", Value::Bool(_)) => {
+                out.insert(keyword.clone(), value.clone());
+            }
+            ("items" | "additionalProperties" | "not", Value::Object(_) | Value::Bool(_)) => {
+                out.insert(keyword.clone(), translate(value, root_name));
+            }
+            ("allOf" | "anyOf" | "oneOf", Value::Array(subschemas)) => {
+                let subschemas = subschemas
+                    .iter()
+                    .map(|subschema| translate(subschema, root_name))
+                    .collect();
+                out.insert(keyword.clone(), Value::Array(subschemas));
+            }
+            (keyword, value) if PASSTHROUGH_KEYWORDS.contains(&keyword) => {
+                out.insert(keyword.to_string(), value.clone());
+            }
+            _ => {}
+        }
+    }
+
+    Value::Object(out)
+}
+
+/// `"type": [...]` becomes a single type plus `nullable`, or an `anyOf` when
+/// several non-null types are allowed
+fn translate_type_list(types: &[Value], out: &mut Map<String, Value>) {
+    let non_null: Vec<&Value> = types.iter().filter(|t| *t != "null").collect();
+    if non_null.len() < types.len() {
+        out.insert("nullable".to_string(), Value::Bool(true));
+    }
+
+    match non_null.as_slice() {
+        [] => {}
+        [single] => {
+            out.insert("type".to_string(), (*single).clone());
+        }
+        several => {
+            let alternatives = several.iter().map(|t| json!({"type": t})).collect();
+            out.entry("anyOf").or_insert(Value::Array(alternatives));
+        }
+    }
+}
+
+/// Point local `$ref`s at the components they were hoisted to
+fn rewrite_ref(reference: &str, root_name: &str) -> String {
+    if reference == "#" {
+        return format!("#/components/schemas/{root_name}");
+    }
+    ["#/$defs/", "#/definitions/"]
+        .iter()
+        .find_map(|prefix| reference.strip_prefix(prefix))
+        .map_or_else(
+            || reference.to_string(),
+            |name| format!("#/components/schemas/{name}"),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_embedded_under_config_name() {
+        let key = ConfigKey::new("myapp", "prod", "database");
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": {"port": {"type": "integer", "minimum": 1}},
+            "required": ["port"]
+        });
+
+        let doc = fragment(&key, "v3", &schema);
+
+        assert_eq!(doc["openapi"], OPENAPI_VERSION);
+        assert_eq!(doc["info"]["version"], "v3");
+        assert_eq!(
+            doc["components"]["schemas"]["database"],
+            json!({
+                "type": "object",
+                "properties": {"port": {"type": "integer", "minimum": 1}},
+                "required": ["port"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_unsupported_keywords_translated() {
+        let key = ConfigKey::new("myapp", "prod", "flags");
+        let schema = json!({
+            "$defs": {"level": {"enum": ["low", "high"]}},
+            "properties": {
+                "name": {"type": ["string", "null"], "examples": ["a", "b"]},
+                "mode": {"const": "fast"},
+                "ratio": {"exclusiveMinimum": 0},
+                "level": {"$ref": "#/$defs/level"},
+                "tags": {"prefixItems": [{"type": "string"}]}
+            }
+        });
+
+        let doc = fragment(&key, "v1", &schema);
+        let schemas = &doc["components"]["schemas"];
+        let properties = &schemas["flags"]["properties"];
+
+        assert_eq!(
+            properties["name"],
+            json!({"type": "string", "nullable": true, "example": "a"})
+        );
+        assert_eq!(properties["mode"], json!({"enum": ["fast"]}));
+        assert_eq!(
+            properties["ratio"],
+            json!({"minimum": 0, "exclusiveMinimum": true})
+        );
+        assert_eq!(
+            properties["level"],
+            json!({"$ref": "#/components/schemas/level"})
+        );
+        assert_eq!(properties["tags"], json!({}));
+        assert_eq!(schemas["level"], json!({"enum": ["low", "high"]}));
+        assert!(schemas["flags"].get("$defs").is_none());
+    }
+}
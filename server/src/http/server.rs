@@ -1,27 +1,81 @@
 use anyhow::Result;
-use axum::{Router, routing::get};
-use std::{net::SocketAddr, sync::Arc};
+use axum::{
+    Router, middleware,
+    routing::{get, post, put},
+};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing::info;
 
-use super::{handlers, state::AppState};
+use super::{
+    handlers,
+    middleware::{require_api_key, route_timeout, track_requests},
+    settings::ServerSettings,
+    state::AppState,
+};
 use crate::storage::ConfigStorage;
 
-pub async fn start_server(storage: Arc<dyn ConfigStorage>, bind_address: SocketAddr) -> Result<()> {
-    let app_state = Arc::new(AppState { storage });
+/// Build the application router with all routes and middleware
+pub fn create_router(app_state: Arc<AppState>) -> Router {
+    // Routes that scan many configs or versions
+    let heavy_routes = Router::new()
+        .route(
+            "/configs/:app/:env/:config/timeline",
+            get(handlers::get_timeline),
+        )
+        .route("/configs", get(handlers::list_configs))
+        .route(
+            "/configs/:app/:env/from-template",
+            post(handlers::create_from_template),
+        )
+        // Application-level views
+        .route("/apps/:app/usage", get(handlers::get_app_usage))
+        // Administration
+        .route("/admin/inventory", get(handlers::get_inventory))
+        .route("/admin/gc", post(handlers::collect_garbage))
+        .layer(middleware::from_fn_with_state(
+            app_state.settings.heavy_request_timeout,
+            route_timeout,
+        ));
+
+    fast_routes(app_state.settings.request_timeout)
+        .merge(heavy_routes)
+        // Only matched routes, so the route label stays bounded
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            track_requests,
+        ))
+        .fallback(handlers::route_not_found)
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            require_api_key,
+        ))
+        // Add state
+        .with_state(app_state)
+        // Add middleware
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http())
+}
 
-    // Build the router
-    let app = Router::new()
+/// Single-config reads and writes
+fn fast_routes(request_timeout: Duration) -> Router<Arc<AppState>> {
+    Router::new()
         // Health check
         .route("/health", get(handlers::health_check))
+        .route("/metrics", get(handlers::get_metrics))
         // Config CRUD operations
         .route(
             "/configs/:app/:env/:config",
-            get(handlers::get_config).put(handlers::put_config),
+            get(handlers::get_config)
+                .put(handlers::put_config)
+                .patch(handlers::patch_config)
+                .delete(handlers::delete_config),
         )
+        .route("/configs/snapshot", post(handlers::read_snapshot))
+        .route("/configs/batch", post(handlers::batch_get))
         .route(
             "/configs/:app/:env",
-            axum::routing::delete(handlers::delete_environment),
+            post(handlers::create_config).delete(handlers::delete_environment),
         )
         // Version operations
         .route(
@@ -30,18 +84,76 @@ pub async fn start_server(storage: Arc<dyn ConfigStorage>, bind_address: SocketA
         )
         .route(
             "/configs/:app/:env/:config/versions/:version",
-            get(handlers::get_config_version),
+            get(handlers::get_config_version).delete(handlers::delete_version),
         )
-        // Add state
-        .with_state(app_state)
-        // Add middleware
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http());
+        .route(
+            "/configs/:app/:env/:config/versions/:version/mark-bad",
+            post(handlers::mark_version_bad),
+        )
+        .route(
+            "/configs/:app/:env/:config/activate/:version",
+            post(handlers::activate_version),
+        )
+        .route(
+            "/configs/:app/:env/:config/rollback/:version",
+            post(handlers::rollback_version),
+        )
+        .route("/configs/:app/:env/:config/alias", put(handlers::set_alias))
+        .route(
+            "/configs/:app/:env/:config/aliases/:alias",
+            get(handlers::get_version_alias).put(handlers::set_version_alias),
+        )
+        .route(
+            "/configs/:app/:env/:config/lineage",
+            get(handlers::get_lineage),
+        )
+        .route(
+            "/configs/:app/:env/:config/openapi",
+            get(handlers::get_openapi),
+        )
+        .route(
+            "/configs/:app/:env/:config/stats",
+            get(handlers::get_config_stats),
+        )
+        .route(
+            "/configs/:app/:env/:config/promote",
+            post(handlers::promote_config),
+        )
+        .route(
+            "/configs/:app/:env/:config/copy",
+            post(handlers::copy_config),
+        )
+        .route(
+            "/configs/:app/:env/:config/diff",
+            get(handlers::diff_versions),
+        )
+        .route(
+            "/configs/:app/:env/:config/validate",
+            post(handlers::validate_config),
+        )
+        .route(
+            "/configs/:app/:env/:config/watch",
+            get(handlers::watch_config),
+        )
+        .layer(middleware::from_fn_with_state(
+            request_timeout,
+            route_timeout,
+        ))
+}
 
-    info!("Server listening on {}", bind_address);
+pub async fn start_server(
+    storage: Arc<dyn ConfigStorage>,
+    settings: ServerSettings,
+    bind_address: SocketAddr,
+) -> Result<()> {
+    let app_state = Arc::new(AppState::new(storage).with_settings(settings));
+    let app = create_router(app_state);
 
     // Run the server
     let listener = tokio::net::TcpListener::bind(bind_address).await?;
+
+    // Log the bound address, which differs from the requested one for port 0
+    info!("Server listening on {}", listener.local_addr()?);
     axum::serve(listener, app).await?;
 
     Ok(())
@@ -0,0 +1,289 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::{
+    collections::{BTreeSet, HashSet},
+    str::FromStr,
+    time::Duration,
+};
+
+use super::metrics::DEFAULT_MAX_METRIC_CONFIGS;
+use crate::storage::KeyCase;
+
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_HEAVY_REQUEST_TIMEOUT: Duration = Duration::from_mins(2);
+const DEFAULT_MAX_SCHEMA_BYTES: usize = 256 * 1024;
+const DEFAULT_MAX_SCHEMA_DEPTH: usize = 32;
+
+/// Which requests must carry an API key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuthMode {
+    /// No request needs a key
+    #[default]
+    Off,
+    /// Every request except the health check needs a key
+    All,
+    /// Reads are open to anyone; anything that changes state needs a key
+    WriteOnly,
+}
+
+impl FromStr for AuthMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "all" => Ok(Self::All),
+            "write-only" => Ok(Self::WriteOnly),
+            _ => anyhow::bail!("Unknown auth mode: {s}. Must be 'off', 'all' or 'write-only'"),
+        }
+    }
+}
+
+/// HTTP-layer limits and policies, read once at startup
+#[derive(Debug, Clone)]
+pub struct ServerSettings {
+    /// Time limit for single-config reads and writes
+    pub request_timeout: Duration,
+    /// Time limit for routes that scan many configs, like timelines and inventory
+    pub heavy_request_timeout: Duration,
+    /// Maximum number of environments an application may have; `None` is unlimited
+    pub max_envs_per_app: Option<usize>,
+    /// Applications this server serves; `None` allows every application
+    pub allowed_apps: Option<HashSet<String>>,
+    /// Applications this server refuses, checked after `allowed_apps`
+    pub denied_apps: HashSet<String>,
+    /// Serve the newest readable earlier version when the current one can't be
+    /// read, instead of failing the request
+    pub fallback_on_corrupt: bool,
+    /// Pattern new environment names must match in full; `None` allows any name
+    pub env_name_pattern: Option<Regex>,
+    /// The only environment names that may be created; `None` allows any name
+    pub allowed_envs: Option<BTreeSet<String>>,
+    /// Largest schema, in serialized bytes, a write may carry
+    pub max_schema_bytes: usize,
+    /// Deepest nesting of objects and arrays a written schema may have
+    pub max_schema_depth: usize,
+    /// Configurations that get their own labeled metric series; the rest
+    /// share one
+    pub metrics_max_configs: usize,
+    /// Which requests need a bearer key from `api_keys`
+    pub auth_mode: AuthMode,
+    /// Keys accepted in `Authorization: Bearer <key>`
+    pub api_keys: HashSet<String>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            heavy_request_timeout: DEFAULT_HEAVY_REQUEST_TIMEOUT,
+            max_envs_per_app: None,
+            allowed_apps: None,
+            denied_apps: HashSet::new(),
+            fallback_on_corrupt: false,
+            env_name_pattern: None,
+            allowed_envs: None,
+            max_schema_bytes: DEFAULT_MAX_SCHEMA_BYTES,
+            max_schema_depth: DEFAULT_MAX_SCHEMA_DEPTH,
+            metrics_max_configs: DEFAULT_MAX_METRIC_CONFIGS,
+            auth_mode: AuthMode::Off,
+            api_keys: HashSet::new(),
+        }
+    }
+}
+
+impl ServerSettings {
+    pub fn from_env() -> Result<Self> {
+        let max_envs_per_app = std::env::var("MAX_ENVS_PER_APP")
+            .ok()
+            .map(|v| v.parse::<usize>())
+            .transpose()
+            .context("MAX_ENVS_PER_APP must be a non-negative integer")?;
+
+        let auth_mode =
+            std::env::var("AUTH_MODE").map_or(Ok(AuthMode::default()), |value| value.parse())?;
+        let mut api_keys = std::env::var("API_KEYS")
+            .map(|v| parse_app_list(&v))
+            .unwrap_or_default();
+        if let Ok(path) = std::env::var("API_KEYS_FILE") {
+            let file = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read API_KEYS_FILE {path}"))?;
+            api_keys.extend(parse_keys_file(&file));
+        }
+        if auth_mode != AuthMode::Off && api_keys.is_empty() {
+            anyhow::bail!("AUTH_MODE requires at least one key in API_KEYS or API_KEYS_FILE");
+        }
+
+        Ok(Self {
+            request_timeout: duration_ms_from_env("REQUEST_TIMEOUT_MS")?
+                .unwrap_or(DEFAULT_REQUEST_TIMEOUT),
+            heavy_request_timeout: duration_ms_from_env("HEAVY_REQUEST_TIMEOUT_MS")?
+                .unwrap_or(DEFAULT_HEAVY_REQUEST_TIMEOUT),
+            max_envs_per_app,
+            allowed_apps: std::env::var("ALLOWED_APPS")
+                .ok()
+                .map(|v| parse_app_list(&v)),
+            denied_apps: std::env::var("DENIED_APPS")
+                .map(|v| parse_app_list(&v))
+                .unwrap_or_default(),
+            fallback_on_corrupt: std::env::var("FALLBACK_ON_CORRUPT")
+                .ok()
+                .map(|v| v.parse::<bool>())
+                .transpose()
+                .context("FALLBACK_ON_CORRUPT must be true or false")?
+                .unwrap_or(false),
+            env_name_pattern: std::env::var("ENV_NAME_PATTERN")
+                .ok()
+                .map(|pattern| full_match_regex(&pattern))
+                .transpose()
+                .context("ENV_NAME_PATTERN must be a valid regular expression")?,
+            allowed_envs: std::env::var("ALLOWED_ENVS")
+                .ok()
+                .map(|v| parse_app_list(&v).into_iter().collect()),
+            max_schema_bytes: usize_from_env("MAX_SCHEMA_BYTES")?
+                .unwrap_or(DEFAULT_MAX_SCHEMA_BYTES),
+            max_schema_depth: usize_from_env("MAX_SCHEMA_DEPTH")?
+                .unwrap_or(DEFAULT_MAX_SCHEMA_DEPTH),
+            metrics_max_configs: usize_from_env("METRICS_MAX_CONFIGS")?
+                .unwrap_or(DEFAULT_MAX_METRIC_CONFIGS),
+            auth_mode,
+            api_keys,
+        })
+    }
+
+    /// Whether `app` may be served, comparing names as stored under `key_case`
+    pub fn is_app_allowed(&self, app: &str, key_case: KeyCase) -> bool {
+        let app = key_case.apply(app);
+        let listed = |apps: &HashSet<String>| apps.iter().any(|name| key_case.apply(name) == app);
+        self.allowed_apps.as_ref().is_none_or(listed) && !listed(&self.denied_apps)
+    }
+
+    /// Whether new environment names are restricted at all
+    pub fn restricts_env_names(&self) -> bool {
+        self.env_name_pattern.is_some() || self.allowed_envs.is_some()
+    }
+
+    /// Check a new environment's name, explaining what is allowed if it isn't
+    pub fn check_env_name(&self, env: &str) -> Result<(), String> {
+        if let Some(allowed) = self
+            .allowed_envs
+            .as_ref()
+            .filter(|allowed| !allowed.contains(env))
+        {
+            let allowed: Vec<_> = allowed.iter().map(String::as_str).collect();
+            return Err(format!(
+                "Environment {env:?} is not allowed; allowed environments: {}",
+                allowed.join(", ")
+            ));
+        }
+
+        if let Some(pattern) = self
+            .env_name_pattern
+            .as_ref()
+            .filter(|pattern| !pattern.is_match(env))
+        {
+            return Err(format!(
+                "Environment {env:?} does not match the allowed pattern {}",
+                pattern.as_str()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Compile `pattern` so it has to match a whole name, not just part of one
+pub fn full_match_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{pattern})$"))
+}
+
+fn duration_ms_from_env(var: &str) -> Result<Option<Duration>> {
+    std::env::var(var)
+        .ok()
+        .map(|v| v.parse::<u64>().map(Duration::from_millis))
+        .transpose()
+        .with_context(|| format!("{var} must be a whole number of milliseconds"))
+}
+
+fn usize_from_env(var: &str) -> Result<Option<usize>> {
+    std::env::var(var)
+        .ok()
+        .map(|v| v.parse::<usize>())
+        .transpose()
+        .with_context(|| format!("{var} must be a non-negative integer"))
+}
+
+/// Parse a comma-separated list of names, ignoring blanks
+fn parse_app_list(value: &str) -> HashSet<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|app| !app.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parse a keys file: one key per line, ignoring blank lines and `#` comments
+fn parse_keys_file(contents: &str) -> impl Iterator<Item = String> + '_ {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_file_skips_comments_and_blanks() {
+        let keys: Vec<String> =
+            parse_keys_file("# deploy bots\nkey-one\n\n  key-two  \n").collect();
+        assert_eq!(keys, ["key-one", "key-two"]);
+    }
+
+    #[test]
+    fn test_all_apps_allowed_by_default() {
+        let settings = ServerSettings::default();
+        assert!(settings.is_app_allowed("anything", KeyCase::Sensitive));
+    }
+
+    #[test]
+    fn test_allow_and_deny_lists() {
+        let settings = ServerSettings {
+            allowed_apps: Some(parse_app_list("billing, search,")),
+            denied_apps: parse_app_list("search"),
+            ..ServerSettings::default()
+        };
+
+        assert!(settings.is_app_allowed("billing", KeyCase::Sensitive));
+        assert!(!settings.is_app_allowed("search", KeyCase::Sensitive));
+        assert!(!settings.is_app_allowed("other", KeyCase::Sensitive));
+
+        // Under lowercased keys, any casing names the same app
+        assert!(!settings.is_app_allowed("Billing", KeyCase::Sensitive));
+        assert!(!settings.is_app_allowed("SEARCH", KeyCase::Lower));
+        assert!(settings.is_app_allowed("Billing", KeyCase::Lower));
+    }
+
+    #[test]
+    fn test_env_name_restrictions() -> Result<()> {
+        let settings = ServerSettings {
+            env_name_pattern: Some(full_match_regex("[a-z]+(-[0-9]+)?")?),
+            ..ServerSettings::default()
+        };
+        assert!(settings.check_env_name("pr-12").is_ok());
+        // The pattern has to match the whole name
+        assert!(settings.check_env_name("pr-12x").is_err());
+
+        let settings = ServerSettings {
+            allowed_envs: Some(["dev", "prod"].map(String::from).into()),
+            ..settings
+        };
+        assert!(settings.check_env_name("prod").is_ok());
+        let err = settings.check_env_name("prd").err().unwrap_or_default();
+        assert!(err.contains("dev, prod"));
+        Ok(())
+    }
+}
@@ -1,8 +1,55 @@
+use super::{metrics::ConfigMetrics, settings::ServerSettings, watch::ConfigWatchers};
 use crate::storage::ConfigStorage;
+use shared_types::ConfigKey;
 use std::sync::Arc;
+use tokio::sync::broadcast;
 
 /// Application state shared across handlers
 #[derive(Clone)]
 pub struct AppState {
     pub storage: Arc<dyn ConfigStorage>,
+    pub settings: ServerSettings,
+    pub metrics: Arc<ConfigMetrics>,
+    pub watchers: Arc<ConfigWatchers>,
+}
+
+impl AppState {
+    pub fn new(storage: Arc<dyn ConfigStorage>) -> Self {
+        Self {
+            storage,
+            settings: ServerSettings::default(),
+            metrics: Arc::default(),
+            watchers: Arc::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_settings(mut self, settings: ServerSettings) -> Self {
+        self.metrics = Arc::new(ConfigMetrics::with_max_configs(
+            settings.metrics_max_configs,
+        ));
+        self.settings = settings;
+        self
+    }
+
+    /// Follow the versions `key` switches to
+    pub fn subscribe(&self, key: &ConfigKey) -> broadcast::Receiver<String> {
+        self.watchers.subscribe(&self.stored_key(key))
+    }
+
+    /// Tell watchers of `key` it now serves `version`
+    pub fn notify_watchers(&self, key: &ConfigKey, version: &str) {
+        self.watchers.notify(&self.stored_key(key), version);
+    }
+
+    /// `key` cased as the storage layer stores it, so every spelling of one
+    /// config shares a watch channel
+    fn stored_key(&self, key: &ConfigKey) -> ConfigKey {
+        let case = self.storage.key_case();
+        ConfigKey::new(
+            case.apply(&key.application),
+            case.apply(&key.environment),
+            case.apply(&key.config_name),
+        )
+    }
 }
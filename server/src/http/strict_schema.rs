@@ -0,0 +1,201 @@
+use serde_json::Value;
+
+use shared_types::escape_pointer_token;
+
+/// Every keyword defined by the JSON Schema drafts the validator supports
+/// (draft 4 through 2020-12)
+const KNOWN_KEYWORDS: &[&str] = &[
+    // Core
+    "$schema",
+    "$id",
+    "id",
+    "$ref",
+    "$anchor",
+    "$dynamicRef",
+    "$dynamicAnchor",
+    "$recursiveRef",
+    "$recursiveAnchor",
+    "$vocabulary",
+    "$comment",
+    "$defs",
+    "definitions",
+    // Applicators
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "not",
+    "if",
+    "then",
+    "else",
+    "dependentSchemas",
+    "dependencies",
+    "prefixItems",
+    "items",
+    "additionalItems",
+    "contains",
+    "properties",
+    "patternProperties",
+    "additionalProperties",
+    "propertyNames",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+    // Validation
+    "type",
+    "enum",
+    "const",
+    "multipleOf",
+    "maximum",
+    "exclusiveMaximum",
+    "minimum",
+    "exclusiveMinimum",
+    "maxLength",
+    "minLength",
+    "pattern",
+    "maxItems",
+    "minItems",
+    "uniqueItems",
+    "maxContains",
+    "minContains",
+    "maxProperties",
+    "minProperties",
+    "required",
+    "dependentRequired",
+    "format",
+    "contentEncoding",
+    "contentMediaType",
+    "contentSchema",
+    // Annotations
+    "title",
+    "description",
+    "default",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+    "examples",
+];
+
+/// Keywords whose value is a single subschema
+const SCHEMA_KEYWORDS: &[&str] = &[
+    "not",
+    "if",
+    "then",
+    "else",
+    "items",
+    "additionalItems",
+    "contains",
+    "additionalProperties",
+    "propertyNames",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+    "contentSchema",
+];
+
+/// Keywords whose value is an array of subschemas
+const SCHEMA_ARRAY_KEYWORDS: &[&str] = &["allOf", "anyOf", "oneOf", "prefixItems", "items"];
+
+/// Keywords whose value maps names to subschemas
+const SCHEMA_MAP_KEYWORDS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "$defs",
+    "definitions",
+    "dependentSchemas",
+    "dependencies",
+];
+
+/// Find keywords no JSON Schema draft defines, such as a misspelled `tpye`
+///
+/// The validator ignores unknown keywords, so a typo silently turns a
+/// constraint off. Invalid values for known keywords are already rejected when
+/// the schema is compiled against its meta-schema. Returns one message per
+/// unknown keyword, located by JSON pointer into the schema.
+pub fn unknown_keywords(schema: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    check_schema("", schema, &mut errors);
+    errors
+}
+
+fn check_schema(path: &str, schema: &Value, errors: &mut Vec<String>) {
+    // Boolean schemas have no keywords
+    let Value::Object(map) = schema else {
+        return;
+    };
+
+    for (keyword, value) in map {
+        let child = format!("{path}/{}", escape_pointer_token(keyword));
+        if !KNOWN_KEYWORDS.contains(&keyword.as_str()) {
+            let location = if path.is_empty() { "root" } else { path };
+            errors.push(format!("{location}: unknown keyword \"{keyword}\""));
+            continue;
+        }
+
+        let keyword = keyword.as_str();
+        match value {
+            Value::Object(entries) if SCHEMA_MAP_KEYWORDS.contains(&keyword) => {
+                for (name, subschema) in entries {
+                    let entry = format!("{child}/{}", escape_pointer_token(name));
+                    check_schema(&entry, subschema, errors);
+                }
+            }
+            Value::Array(items) if SCHEMA_ARRAY_KEYWORDS.contains(&keyword) => {
+                for (i, subschema) in items.iter().enumerate() {
+                    check_schema(&format!("{child}/{i}"), subschema, errors);
+                }
+            }
+            _ if SCHEMA_KEYWORDS.contains(&keyword) => check_schema(&child, value, errors),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_known_keywords_pass() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": {
+                "port": {"type": "integer", "minimum": 1},
+                "hosts": {"type": "array", "items": {"type": "string"}}
+            },
+            "required": ["port"],
+            "additionalProperties": false
+        });
+
+        assert!(unknown_keywords(&schema).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_keywords_found_in_subschemas() {
+        let schema = json!({
+            "tpye": "object",
+            "properties": {
+                "port": {"type": "integer", "minimun": 1},
+                // Property names are not keywords
+                "tpye": {"type": "string"}
+            },
+            "anyOf": [{"requried": ["port"]}]
+        });
+
+        let mut errors = unknown_keywords(&schema);
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "/anyOf/0: unknown keyword \"requried\"",
+                "/properties/port: unknown keyword \"minimun\"",
+                "root: unknown keyword \"tpye\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enum_and_const_values_are_not_checked() {
+        let schema = json!({"enum": [{"tpye": 1}], "const": {"anything": true}});
+        assert!(unknown_keywords(&schema).is_empty());
+    }
+}
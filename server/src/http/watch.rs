@@ -0,0 +1,78 @@
+use shared_types::ConfigKey;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// Version announcements a slow subscriber may fall behind by before it
+/// skips ahead to the newest
+const CHANNEL_CAPACITY: usize = 16;
+
+/// One broadcast channel per watched configuration, carrying each version it
+/// switches to
+///
+/// A channel whose subscribers have all gone is dropped the next time anyone
+/// subscribes or its config changes, so the map never holds more than the
+/// configs watched since the last subscription.
+#[derive(Default)]
+pub struct ConfigWatchers {
+    channels: Mutex<HashMap<ConfigKey, broadcast::Sender<String>>>,
+}
+
+impl ConfigWatchers {
+    pub fn subscribe(&self, key: &ConfigKey) -> broadcast::Receiver<String> {
+        let Ok(mut channels) = self.channels.lock() else {
+            // Nothing is ever sent on a channel nobody else holds
+            return broadcast::channel(1).1;
+        };
+        // Watches that ended on configs that never changed again would
+        // otherwise stay here forever
+        channels.retain(|_, sender| sender.receiver_count() > 0);
+        channels
+            .entry(key.clone())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Tell subscribers of `key` it now serves `version`
+    pub fn notify(&self, key: &ConfigKey, version: &str) {
+        let Ok(mut channels) = self.channels.lock() else {
+            return;
+        };
+        if let Some(sender) = channels.get(key)
+            && sender.send(version.to_string()).is_err()
+        {
+            // Every subscriber has gone
+            channels.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_channel_dropped_with_last_subscriber() {
+        let watchers = ConfigWatchers::default();
+        let key = ConfigKey::new("app", "dev", "flags");
+
+        let mut receiver = watchers.subscribe(&key);
+        watchers.notify(&key, "v2");
+        assert_eq!(receiver.recv().await.ok().as_deref(), Some("v2"));
+
+        drop(receiver);
+        watchers.notify(&key, "v3");
+        assert!(watchers.channels.lock().is_ok_and(|c| c.is_empty()));
+    }
+
+    #[test]
+    fn test_abandoned_channels_pruned_on_subscribe() {
+        let watchers = ConfigWatchers::default();
+        for name in ["a", "b", "c"] {
+            drop(watchers.subscribe(&ConfigKey::new("app", "dev", name)));
+        }
+
+        let _receiver = watchers.subscribe(&ConfigKey::new("app", "dev", "d"));
+        assert!(watchers.channels.lock().is_ok_and(|c| c.len() == 1));
+    }
+}
@@ -1,8 +1,6 @@
-mod http;
-mod storage;
-
 use anyhow::Result;
-use std::{net::SocketAddr, sync::Arc};
+use server::{http, storage};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tracing::{Level, info};
 
 #[tokio::main]
@@ -23,13 +21,34 @@ async fn main() -> Result<()> {
     info!("Using storage backend: {:?}", storage_config);
 
     // Create local directory if using local storage
-    if let storage::StorageConfig::Local { ref path } = storage_config {
+    if let storage::StorageConfig::Local { ref path, .. } = storage_config {
         std::fs::create_dir_all(path)?;
     }
 
-    let storage = storage::ObjectStoreBackend::from_config(storage_config)?;
+    // Bound each object-store call so a slow backend can't hold requests forever
+    let op_timeout_ms = match std::env::var("STORAGE_OP_TIMEOUT_MS") {
+        Ok(ms) => ms.parse::<u64>()?,
+        Err(_) => 30_000,
+    };
+    let mut storage = storage::ObjectStoreBackend::from_config(storage_config)?
+        .with_op_timeout(Duration::from_millis(op_timeout_ms))
+        .with_key_case(storage::KeyCase::from_env()?);
+    if let Ok(ms) = std::env::var("SLOW_STORAGE_MS") {
+        storage = storage.with_slow_op_threshold(Duration::from_millis(ms.parse::<u64>()?));
+    }
+    if let Ok(threshold) = std::env::var("METADATA_COMPACT_THRESHOLD") {
+        storage = storage.with_metadata_compaction(threshold.parse()?);
+    }
+    if let Ok(secs) = std::env::var("GC_GRACE_SECS") {
+        storage = storage.with_gc_grace_period(Duration::from_secs(secs.parse()?));
+    }
     let storage: Arc<dyn storage::ConfigStorage> = Arc::new(storage);
 
+    // Seed an empty store from a directory of app/env/config.json files
+    if let Ok(seed_dir) = std::env::var("SEED_DIR") {
+        storage::seed::seed_from_dir(storage.as_ref(), std::path::Path::new(&seed_dir)).await?;
+    }
+
     // Bind to address - support both BIND_ADDRESS and HOST/PORT for compatibility
     let addr = if let Ok(bind_addr) = std::env::var("BIND_ADDRESS") {
         bind_addr.parse::<SocketAddr>()?
@@ -39,10 +58,12 @@ async fn main() -> Result<()> {
         format!("{host}:{port}").parse::<SocketAddr>()?
     };
 
+    let settings = http::ServerSettings::from_env()?;
+
     info!("Starting HTTP server on {}", addr);
 
     // Start the HTTP server
-    http::start_server(storage, addr).await?;
+    http::start_server(storage, settings, addr).await?;
 
     Ok(())
 }
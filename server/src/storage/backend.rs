@@ -1,25 +1,145 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use object_store::aws::AmazonS3Builder;
+use object_store::aws::{AmazonS3Builder, S3ConditionalPut};
 use object_store::local::LocalFileSystem;
+use object_store::memory::InMemory;
 use object_store::path::Path;
-use object_store::{ObjectStore, PutPayload};
-use shared_types::{ConfigData, ConfigKey, VersionInfo};
+use object_store::{ObjectStore, PutMode, PutPayload, UpdateVersion};
+use sha2::{Digest, Sha256};
+use shared_types::{ConfigData, ConfigKey, ConfigOrigin, VersionInfo};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::future::Future;
+use std::hash::{BuildHasher, RandomState};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
 
-use super::config::StorageConfig;
+use super::config::{KeyCase, StorageConfig};
 use super::error::StorageError;
-use super::metadata::Metadata;
-use super::traits::ConfigStorage;
+use super::metadata::{Metadata, VersionMetadata};
+use super::traits::{ChangeInfo, ConfigPage, ConfigStorage};
+
+/// Names the backend's layout uses for its own objects under a config
+const RESERVED_COMPONENTS: [&str; 3] = ["versions", "metadata.json", ARCHIVE_FILE];
+
+/// Where compaction moves a config's older version entries
+const ARCHIVE_FILE: &str = "versions_archive.json";
+
+/// Version numbers held by leftover objects that a put moves past before
+/// giving up on the config's history
+const MAX_ORPHAN_SKIPS: u32 = 8;
+
+/// How old a version object must be before garbage collection may remove it
+/// by default, outlasting any write still between its objects and its metadata
+const DEFAULT_GC_GRACE_PERIOD: Duration = Duration::from_hours(1);
+
+/// Locks serializing metadata updates within this process, shared by
+/// configs whose keys hash alike
+const WRITE_LOCK_COUNT: usize = 64;
 
 pub struct ObjectStoreBackend {
     store: Arc<dyn ObjectStore>,
+    op_timeout: Option<Duration>,
+    slow_op_threshold: Option<Duration>,
+    key_case: KeyCase,
+    compact_after: Option<usize>,
+    max_versions: Option<usize>,
+    gc_grace_period: Duration,
+    /// Whether the store lists objects in lexicographic order, letting a page
+    /// of a listing stop early
+    sorted_listing: bool,
+    /// Whether the store can replace an object only if it is unchanged since
+    /// it was read, so writers in other processes cannot overwrite each other
+    conditional_update: bool,
+    write_locks: Vec<tokio::sync::Mutex<()>>,
+    lock_hasher: RandomState,
 }
 
 impl ObjectStoreBackend {
+    pub fn new(store: Arc<dyn ObjectStore>) -> Self {
+        Self {
+            store,
+            op_timeout: None,
+            slow_op_threshold: None,
+            key_case: KeyCase::default(),
+            compact_after: None,
+            max_versions: None,
+            gc_grace_period: DEFAULT_GC_GRACE_PERIOD,
+            sorted_listing: false,
+            conditional_update: false,
+            write_locks: (0..WRITE_LOCK_COUNT)
+                .map(|_| tokio::sync::Mutex::default())
+                .collect(),
+            lock_hasher: RandomState::new(),
+        }
+    }
+
+    /// Choose how key components are cased when mapped to storage paths
+    #[must_use]
+    pub fn with_key_case(mut self, key_case: KeyCase) -> Self {
+        self.key_case = key_case;
+        self
+    }
+
+    /// Bound every individual object-store operation by `timeout`
+    #[must_use]
+    pub fn with_op_timeout(mut self, timeout: Duration) -> Self {
+        self.op_timeout = Some(timeout);
+        self
+    }
+
+    /// Once a config's metadata lists more than `threshold` versions, move all
+    /// but the newest half of them into a separate archive object, keeping the
+    /// metadata read on every `get` small
+    #[must_use]
+    pub fn with_metadata_compaction(mut self, threshold: usize) -> Self {
+        self.compact_after = Some(threshold);
+        self
+    }
+
+    /// Keep at most `max_versions` versions of each config, deleting the
+    /// oldest after each write. The current version is always kept.
+    #[must_use]
+    pub fn with_max_versions(mut self, max_versions: usize) -> Self {
+        self.max_versions = Some(max_versions);
+        self
+    }
+
+    /// Leave version objects younger than `grace_period` to garbage
+    /// collection, since a write in flight, here or in another process, may be
+    /// about to reference them
+    #[must_use]
+    pub fn with_gc_grace_period(mut self, grace_period: Duration) -> Self {
+        self.gc_grace_period = grace_period;
+        self
+    }
+
+    /// Declare that `store` can replace an object only if it is unchanged
+    /// since it was read, so metadata updates from other processes sharing
+    /// the store are detected rather than overwritten
+    #[must_use]
+    pub fn with_conditional_update(mut self, conditional_update: bool) -> Self {
+        self.conditional_update = conditional_update;
+        self
+    }
+
+    /// Log a warning for every object-store operation that takes `threshold` or longer
+    #[must_use]
+    pub fn with_slow_op_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_op_threshold = Some(threshold);
+        self
+    }
+
     pub fn from_config(config: StorageConfig) -> Result<Self> {
+        // Object stores list in key order and support conditional updates;
+        // local directories do neither
+        let sorted_listing = !matches!(config, StorageConfig::Local { .. });
+        let conditional_update = sorted_listing;
+        let max_versions = config.max_versions();
         let store: Arc<dyn ObjectStore> = match config {
-            StorageConfig::Local { path } => Arc::new(LocalFileSystem::new_with_prefix(path)?),
+            StorageConfig::Local { path, .. } => Arc::new(LocalFileSystem::new_with_prefix(path)?),
             StorageConfig::S3 {
                 bucket,
                 region,
@@ -27,10 +147,12 @@ impl ObjectStoreBackend {
                 access_key_id,
                 secret_access_key,
                 allow_http,
+                ..
             } => {
                 let mut builder = AmazonS3Builder::new()
                     .with_bucket_name(bucket)
-                    .with_allow_http(allow_http);
+                    .with_allow_http(allow_http)
+                    .with_conditional_put(S3ConditionalPut::ETagMatch);
 
                 if let Some(region) = region {
                     builder = builder.with_region(region);
@@ -50,29 +172,113 @@ impl ObjectStoreBackend {
 
                 Arc::new(builder.build()?)
             }
+            StorageConfig::InMemory { .. } => Arc::new(InMemory::new()),
+        };
+        Ok(Self {
+            max_versions,
+            sorted_listing,
+            conditional_update,
+            ..Self::new(store)
+        })
+    }
+
+    /// Await a single object-store operation, failing with
+    /// `StorageError::Timeout` if it exceeds the configured timeout and
+    /// warning if it is slower than the configured threshold
+    async fn timed<F: Future>(
+        &self,
+        operation: &str,
+        path: &Path,
+        fut: F,
+    ) -> Result<F::Output, StorageError> {
+        let started = Instant::now();
+        let result = match self.op_timeout {
+            Some(limit) => tokio::time::timeout(limit, fut).await.map_err(|_| {
+                StorageError::Timeout(format!(
+                    "{operation} {path} did not complete within {}ms",
+                    limit.as_millis()
+                ))
+            }),
+            None => Ok(fut.await),
         };
-        Ok(Self { store })
+
+        let elapsed = started.elapsed();
+        if self
+            .slow_op_threshold
+            .is_some_and(|threshold| elapsed >= threshold)
+        {
+            warn!(
+                operation,
+                path = %path,
+                elapsed_ms = elapsed.as_millis(),
+                "Slow storage operation"
+            );
+        }
+
+        result
+    }
+
+    /// Fetch an object's full contents as one timed operation
+    pub(super) async fn read_object(
+        &self,
+        path: &Path,
+    ) -> Result<object_store::Result<bytes::Bytes>> {
+        let read = async { self.store.get(path).await?.bytes().await };
+        Ok(self.timed("get", path, read).await?)
     }
 
-    fn config_path(key: &ConfigKey, file: &str) -> Path {
-        Path::from(format!(
+    /// Apply the configured key case to one path component
+    fn key_component<'a>(&self, component: &'a str) -> Cow<'a, str> {
+        self.key_case.apply(component)
+    }
+
+    /// A cased key component that is safe to place in a path: it must stay a
+    /// single segment and must not collide with the backend's own layout
+    fn path_component<'a>(&self, component: &'a str) -> Result<Cow<'a, str>, StorageError> {
+        let cased = self.key_component(component);
+        if cased.is_empty()
+            || cased == "."
+            || cased == ".."
+            || cased.contains(['/', '\\'])
+            || RESERVED_COMPONENTS.contains(&cased.as_ref())
+        {
+            return Err(StorageError::InvalidKey(format!(
+                "{component:?} is not allowed as a path component"
+            )));
+        }
+        Ok(cased)
+    }
+
+    fn config_path(&self, key: &ConfigKey, file: &str) -> Result<Path, StorageError> {
+        Ok(Path::from(format!(
             "{}/{}/{}/{}",
-            key.application, key.environment, key.config_name, file
-        ))
+            self.path_component(&key.application)?,
+            self.path_component(&key.environment)?,
+            self.path_component(&key.config_name)?,
+            file
+        )))
     }
 
-    fn version_path(key: &ConfigKey, version: &str, file: &str) -> Path {
-        Path::from(format!(
+    pub(super) fn version_path(
+        &self,
+        key: &ConfigKey,
+        version: &str,
+        file: &str,
+    ) -> Result<Path, StorageError> {
+        Ok(Path::from(format!(
             "{}/{}/{}/versions/{}/{}",
-            key.application, key.environment, key.config_name, version, file
-        ))
+            self.path_component(&key.application)?,
+            self.path_component(&key.environment)?,
+            self.path_component(&key.config_name)?,
+            self.path_component(version)?,
+            file
+        )))
     }
 
     async fn read_metadata(&self, key: &ConfigKey) -> Result<Option<Metadata>> {
-        let path = Self::config_path(key, "metadata.json");
-        match self.store.get(&path).await {
-            Ok(result) => {
-                let bytes = result.bytes().await?;
+        let path = self.config_path(key, "metadata.json")?;
+        match self.read_object(&path).await? {
+            Ok(bytes) => {
                 let metadata: Metadata = serde_json::from_slice(&bytes)?;
                 Ok(Some(metadata))
             }
@@ -81,25 +287,298 @@ impl ObjectStoreBackend {
         }
     }
 
-    async fn write_metadata(&self, key: &ConfigKey, metadata: &Metadata) -> Result<()> {
-        let path = Self::config_path(key, "metadata.json");
+    /// A config's metadata with the object version it was read at, so it can
+    /// be written back only if nobody has changed it meanwhile
+    async fn read_metadata_for_update(
+        &self,
+        key: &ConfigKey,
+    ) -> Result<Option<(Metadata, UpdateVersion)>> {
+        let path = self.config_path(key, "metadata.json")?;
+        let read = async {
+            let result = self.store.get(&path).await?;
+            let read_at = UpdateVersion {
+                e_tag: result.meta.e_tag.clone(),
+                version: result.meta.version.clone(),
+            };
+            Ok((result.bytes().await?, read_at))
+        };
+        match self.timed("get", &path, read).await? {
+            Ok((bytes, read_at)) => Ok(Some((serde_json::from_slice(&bytes)?, read_at))),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Hold while reading, changing and writing back a config's metadata, so
+    /// concurrent updates in this process take turns
+    async fn lock_for_update(&self, key: &ConfigKey) -> tokio::sync::MutexGuard<'_, ()> {
+        // Truncating the hash is fine, it only picks a lock
+        #[allow(clippy::cast_possible_truncation)]
+        let index = self.lock_hasher.hash_one(key) as usize % self.write_locks.len();
+        self.write_locks[index].lock().await
+    }
+
+    /// A config's metadata with any archived versions folded back in, for
+    /// operations that need the whole history
+    async fn read_full_metadata(&self, key: &ConfigKey) -> Result<Option<Metadata>> {
+        let Some(mut metadata) = self.read_metadata(key).await? else {
+            return Ok(None);
+        };
+        if metadata.archived_versions.is_some() {
+            let archive = self.read_archive(key).await?;
+            metadata.merge_archive(archive);
+        }
+        Ok(Some(metadata))
+    }
+
+    /// [`Self::read_full_metadata`] with the object version the head metadata
+    /// was read at, for updates that need the whole history
+    async fn read_full_metadata_for_update(
+        &self,
+        key: &ConfigKey,
+    ) -> Result<Option<(Metadata, UpdateVersion)>> {
+        let Some((mut metadata, read_at)) = self.read_metadata_for_update(key).await? else {
+            return Ok(None);
+        };
+        if metadata.archived_versions.is_some() {
+            let archive = self.read_archive(key).await?;
+            metadata.merge_archive(archive);
+        }
+        Ok(Some((metadata, read_at)))
+    }
+
+    async fn read_archive(&self, key: &ConfigKey) -> Result<Vec<VersionMetadata>> {
+        let path = self.config_path(key, ARCHIVE_FILE)?;
+        match self.read_object(&path).await? {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(object_store::Error::NotFound { .. }) => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Move older versions out of `metadata` into the archive object. The
+    /// archive is written first, so a failure leaves the inline entries in
+    /// place; entries found in both are deduplicated when merged.
+    async fn compact(
+        &self,
+        key: &ConfigKey,
+        mut metadata: Metadata,
+        threshold: usize,
+    ) -> Result<Metadata> {
+        let mut archive = if metadata.archived_versions.is_some() {
+            self.read_archive(key).await?
+        } else {
+            Vec::new()
+        };
+        archive.extend(metadata.split_for_archive(threshold / 2));
+
+        let path = self.config_path(key, ARCHIVE_FILE)?;
+        let json = serde_json::to_vec_pretty(&archive)?;
+        self.timed("put", &path, self.store.put(&path, PutPayload::from(json)))
+            .await??;
+
+        metadata.archived_versions = Some(archive.len());
+        Ok(metadata)
+    }
+
+    /// Write an object belonging to a version. Versions are append-only, so an
+    /// existing object at the path means the version numbering is corrupt.
+    async fn write_version_object(
+        &self,
+        key: &ConfigKey,
+        version: &str,
+        file: &str,
+        bytes: Vec<u8>,
+    ) -> Result<()> {
+        let path = self.version_path(key, version, file)?;
+        let put = self
+            .store
+            .put_opts(&path, PutPayload::from(bytes), PutMode::Create.into());
+        // A timeout may leave this object written without a metadata entry;
+        // such orphans are never referenced by reads
+        match self.timed("put", &path, put).await? {
+            Ok(_) => Ok(()),
+            Err(object_store::Error::AlreadyExists { .. }) => {
+                Err(StorageError::VersionCorruption(format!(
+                    "{file} for {key} @ {version} already exists; refusing to overwrite history"
+                ))
+                .into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Remove every version object of a config, then its metadata. Objects
+    /// already gone are ignored so an interrupted delete can be retried.
+    async fn delete_objects(&self, key: &ConfigKey, metadata: &Metadata) -> Result<()> {
+        for version_meta in &metadata.versions {
+            for file in ["data.json", "schema.json"] {
+                let path = self.version_path(key, &version_meta.version, file)?;
+                let _ = self.timed("delete", &path, self.store.delete(&path)).await;
+            }
+        }
+
+        let metadata_path = self.config_path(key, "metadata.json")?;
+        let _ = self
+            .timed("delete", &metadata_path, self.store.delete(&metadata_path))
+            .await;
+        let archive_path = self.config_path(key, ARCHIVE_FILE)?;
+        let _ = self
+            .timed("delete", &archive_path, self.store.delete(&archive_path))
+            .await;
+        Ok(())
+    }
+
+    /// Write an object as-is, replacing anything already at the path
+    pub(super) async fn write_object(&self, path: &Path, bytes: bytes::Bytes) -> Result<()> {
+        self.timed("put", path, self.store.put(path, PutPayload::from(bytes)))
+            .await??;
+        Ok(())
+    }
+
+    pub(super) async fn write_metadata(&self, key: &ConfigKey, metadata: &Metadata) -> Result<()> {
+        self.write_metadata_with(key, metadata, PutMode::Overwrite)
+            .await
+    }
+
+    /// Write `metadata` only if the stored copy is unchanged since it was read
+    /// at `read_at`, or still absent for `None`. Losing that race is reported
+    /// as a conflict with whatever version is now current.
+    async fn replace_metadata(
+        &self,
+        key: &ConfigKey,
+        metadata: &Metadata,
+        read_at: Option<UpdateVersion>,
+        expected_version: Option<&str>,
+    ) -> Result<()> {
+        let mode = match read_at {
+            None => PutMode::Create,
+            Some(read_at) if self.conditional_update => PutMode::Update(read_at),
+            Some(_) => PutMode::Overwrite,
+        };
+        let Err(e) = self.write_metadata_with(key, metadata, mode).await else {
+            return Ok(());
+        };
+        if !matches!(
+            e.downcast_ref(),
+            Some(
+                object_store::Error::AlreadyExists { .. }
+                    | object_store::Error::Precondition { .. }
+            )
+        ) {
+            return Err(e);
+        }
+        Err(self.lost_race(key, expected_version).await)
+    }
+
+    /// The conflict to report to a writer another writer got ahead of
+    async fn lost_race(&self, key: &ConfigKey, expected_version: Option<&str>) -> anyhow::Error {
+        let actual = match self.read_metadata(key).await {
+            Ok(Some(metadata)) => metadata.current_version,
+            Ok(None) => "none".to_string(),
+            Err(e) => return e,
+        };
+        StorageError::VersionConflict {
+            expected: expected_version.unwrap_or("none").to_string(),
+            actual,
+        }
+        .into()
+    }
+
+    async fn write_metadata_with(
+        &self,
+        key: &ConfigKey,
+        metadata: &Metadata,
+        mode: PutMode,
+    ) -> Result<()> {
+        let compacted = match self.compact_after {
+            Some(threshold) if metadata.versions.len() > threshold => {
+                Some(self.compact(key, metadata.clone(), threshold).await?)
+            }
+            _ => None,
+        };
+        let metadata = compacted.as_ref().unwrap_or(metadata);
+
+        let path = self.config_path(key, "metadata.json")?;
         let json = serde_json::to_vec_pretty(metadata)?;
-        self.store.put(&path, PutPayload::from(json)).await?;
+        let put = self
+            .store
+            .put_opts(&path, PutPayload::from(json), mode.into());
+        self.timed("put", &path, put).await??;
         Ok(())
     }
-}
 
-#[async_trait]
-impl ConfigStorage for ObjectStoreBackend {
-    async fn put(
+    async fn write_version(
         &self,
         key: &ConfigKey,
         data: &ConfigData,
         expected_version: Option<&str>,
+        activate: bool,
+        change: &ChangeInfo,
+    ) -> Result<String> {
+        let _guard = self.lock_for_update(key).await;
+        let (existing_metadata, read_at) = self.read_metadata_for_update(key).await?.unzip();
+        Self::check_writable(key, existing_metadata.as_ref(), expected_version)?;
+
+        let mut metadata = existing_metadata.unwrap_or_else(Metadata::new);
+
+        let data_json = serde_json::to_vec_pretty(&data.content)?;
+        let schema_json = serde_json::to_vec_pretty(&data.schema)?;
+        let data_size = data_json.len();
+        let content_hash = sha256_hex(&data_json);
+        let version = self
+            .claim_version(key, &mut metadata, expected_version, data_json, schema_json)
+            .await?;
+
+        if change.derived_from.is_some() {
+            metadata.derived_from.clone_from(&change.derived_from);
+        }
+        let previous_version = metadata.current_version.clone();
+        let entry = metadata.add_version(version.clone());
+        entry.content_type.clone_from(&data.content_type);
+        entry.data_size = data_size;
+        entry.has_schema = !data.schema.is_null();
+        entry.content_hash = content_hash;
+        entry.change_message.clone_from(&change.message);
+        entry.author.clone_from(&change.author);
+        if !activate {
+            metadata.current_version = previous_version;
+        }
+        let pruned = self.prune(key, &mut metadata).await?;
+        if let Err(e) = self
+            .replace_metadata(key, &metadata, read_at, expected_version)
+            .await
+        {
+            // Having lost the race, nothing will ever refer to this version.
+            // Other failures may still have written the metadata.
+            if matches!(e.downcast_ref(), Some(StorageError::VersionConflict { .. })) {
+                for file in ["data.json", "schema.json"] {
+                    self.discard_version_object(key, &version, file).await;
+                }
+            }
+            return Err(e);
+        }
+        self.discard_versions(key, &pruned).await;
+
+        Ok(version)
+    }
+
+    /// Refuse writes to aliases, and writes whose `expected_version` is not
+    /// current (or is missing for an existing config)
+    fn check_writable(
+        key: &ConfigKey,
+        existing_metadata: Option<&Metadata>,
+        expected_version: Option<&str>,
     ) -> Result<()> {
-        let existing_metadata = self.read_metadata(key).await?;
+        if let Some(target) = existing_metadata.and_then(|m| m.alias_of.as_ref()) {
+            return Err(StorageError::IsAlias {
+                alias: key.to_string(),
+                target: target.to_string(),
+            }
+            .into());
+        }
 
-        match (&existing_metadata, expected_version) {
+        match (existing_metadata, expected_version) {
             (None, None) => {}
             (Some(m), Some(expected)) if m.current_version == expected => {}
             (None, Some(expected)) => {
@@ -123,26 +602,319 @@ impl ConfigStorage for ObjectStoreBackend {
                 .into());
             }
         }
+        Ok(())
+    }
 
-        let mut metadata = existing_metadata.unwrap_or_else(Metadata::new);
-        let version = format!("v{}", metadata.next_version_number());
+    /// Write the objects of the next free version, returning its name.
+    ///
+    /// Every version object is in place before the metadata commits to the
+    /// version. If the schema can't be written, the data written just before
+    /// it is removed again rather than left behind as an orphan. Objects the
+    /// metadata doesn't know of, left by an earlier write that failed after
+    /// writing them, are never overwritten: their number counts as taken and
+    /// the next one is tried.
+    async fn claim_version(
+        &self,
+        key: &ConfigKey,
+        metadata: &mut Metadata,
+        expected_version: Option<&str>,
+        data_json: Vec<u8>,
+        schema_json: Vec<u8>,
+    ) -> Result<String> {
+        let read_next = metadata.next_version_number();
+        for _ in 0..=MAX_ORPHAN_SKIPS {
+            let number = metadata.next_version_number();
+            let version = format!("v{number}");
+            let error = match self
+                .write_version_object(key, &version, "data.json", data_json.clone())
+                .await
+            {
+                Ok(()) => match self
+                    .write_version_object(key, &version, "schema.json", schema_json.clone())
+                    .await
+                {
+                    Ok(()) => return Ok(version),
+                    Err(e) => {
+                        self.discard_version_object(key, &version, "data.json")
+                            .await;
+                        e
+                    }
+                },
+                Err(e) => e,
+            };
 
-        let data_path = Self::version_path(key, &version, "data.json");
-        let data_json = serde_json::to_vec_pretty(&data.content)?;
-        self.store
-            .put(&data_path, PutPayload::from(data_json))
-            .await?;
+            if !matches!(
+                error.downcast_ref(),
+                Some(StorageError::VersionCorruption(_))
+            ) {
+                return Err(error);
+            }
+            // A concurrent writer claiming the number moves the metadata on
+            let moved_on = self
+                .read_metadata(key)
+                .await
+                .is_ok_and(|now| now.unwrap_or_default().next_version_number() != read_next);
+            if moved_on {
+                return Err(self.lost_race(key, expected_version).await);
+            }
+            warn!("Skipping {version} of {key}, already held by unreferenced objects");
+            metadata.last_version_number = Some(number);
+        }
 
-        let schema_path = Self::version_path(key, &version, "schema.json");
-        let schema_json = serde_json::to_vec_pretty(&data.schema)?;
-        self.store
-            .put(&schema_path, PutPayload::from(schema_json))
-            .await?;
+        Err(StorageError::VersionCorruption(format!(
+            "{key} has more than {MAX_ORPHAN_SKIPS} unreferenced versions past its history"
+        ))
+        .into())
+    }
 
-        metadata.add_version(version);
-        self.write_metadata(key, &metadata).await?;
+    /// Drop the versions beyond the retention limit from `metadata`, archived
+    /// ones included, returning them for their objects to be deleted once the
+    /// metadata no longer refers to them
+    async fn prune(
+        &self,
+        key: &ConfigKey,
+        metadata: &mut Metadata,
+    ) -> Result<Vec<VersionMetadata>> {
+        let Some(max_versions) = self.max_versions else {
+            return Ok(Vec::new());
+        };
+        let total = metadata.versions.len() + metadata.archived_versions.unwrap_or(0);
+        if total <= max_versions {
+            return Ok(Vec::new());
+        }
+        if metadata.archived_versions.is_some() {
+            metadata.merge_archive(self.read_archive(key).await?);
+        }
+        Ok(metadata.prune(max_versions))
+    }
 
-        Ok(())
+    /// Best-effort removal of the objects of versions no metadata refers to
+    /// any more; leftovers are orphans that garbage collection can find
+    async fn discard_versions(&self, key: &ConfigKey, versions: &[VersionMetadata]) {
+        for version_meta in versions {
+            for file in ["data.json", "schema.json"] {
+                let Ok(path) = self.version_path(key, &version_meta.version, file) else {
+                    continue;
+                };
+                let error = match self.timed("delete", &path, self.store.delete(&path)).await {
+                    Ok(Ok(()) | Err(object_store::Error::NotFound { .. })) => continue,
+                    Ok(Err(e)) => e.to_string(),
+                    Err(e) => e.to_string(),
+                };
+                warn!("Failed to delete pruned version object {path}: {error}");
+            }
+        }
+    }
+
+    /// Best-effort removal of a version object no metadata refers to yet
+    async fn discard_version_object(&self, key: &ConfigKey, version: &str, file: &str) {
+        if let Ok(path) = self.version_path(key, version, file) {
+            let _ = self.timed("delete", &path, self.store.delete(&path)).await;
+        }
+    }
+
+    /// `key` followed by every key its aliases lead to, ending at a non-alias
+    async fn alias_chain(&self, key: &ConfigKey) -> Result<Vec<ConfigKey>> {
+        let mut chain = vec![key.clone()];
+        let mut current = key.clone();
+
+        while let Some(target) = self
+            .read_metadata(&current)
+            .await?
+            .and_then(|metadata| metadata.alias_of)
+        {
+            if chain.contains(&target) {
+                return Err(
+                    StorageError::AliasCycle(format!("{key} leads back to {target}")).into(),
+                );
+            }
+            chain.push(target.clone());
+            current = target;
+        }
+
+        Ok(chain)
+    }
+
+    async fn read_version(
+        &self,
+        key: &ConfigKey,
+        version: &str,
+        metadata: &Metadata,
+    ) -> Result<ConfigData> {
+        let data_path = self.version_path(key, version, "data.json")?;
+        let data_bytes = self
+            .read_object(&data_path)
+            .await?
+            .with_context(|| format!("Failed to read data for {key} @ {version}"))?;
+        // Versions written before hashes were recorded can't be checked
+        if let Some(expected) = metadata
+            .find_version(version)
+            .map(|v| v.content_hash.as_str())
+            .filter(|hash| !hash.is_empty())
+        {
+            let actual = sha256_hex(&data_bytes);
+            if actual != expected {
+                return Err(StorageError::IntegrityError(format!(
+                    "{key} @ {version} hashes to {actual}, expected {expected}"
+                ))
+                .into());
+            }
+        }
+        let content: serde_json::Value = serde_json::from_slice(&data_bytes)?;
+
+        let schema_path = self.version_path(key, version, "schema.json")?;
+        let schema_bytes = self
+            .read_object(&schema_path)
+            .await?
+            .with_context(|| format!("Failed to read schema for {key} @ {version}"))?;
+        let schema: serde_json::Value = serde_json::from_slice(&schema_bytes)?;
+
+        Ok(ConfigData {
+            content,
+            schema,
+            version: version.to_string(),
+            content_type: metadata
+                .find_version(version)
+                .and_then(|v| v.content_type.clone()),
+        })
+    }
+}
+
+#[async_trait]
+impl ConfigStorage for ObjectStoreBackend {
+    fn key_case(&self) -> KeyCase {
+        self.key_case
+    }
+
+    async fn put(
+        &self,
+        key: &ConfigKey,
+        data: &ConfigData,
+        expected_version: Option<&str>,
+    ) -> Result<String> {
+        self.put_with_change(key, data, expected_version, &ChangeInfo::default())
+            .await
+    }
+
+    async fn put_with_change(
+        &self,
+        key: &ConfigKey,
+        data: &ConfigData,
+        expected_version: Option<&str>,
+        change: &ChangeInfo,
+    ) -> Result<String> {
+        self.write_version(key, data, expected_version, true, change)
+            .await
+    }
+
+    async fn stage(
+        &self,
+        key: &ConfigKey,
+        data: &ConfigData,
+        expected_version: Option<&str>,
+    ) -> Result<String> {
+        self.stage_with_change(key, data, expected_version, &ChangeInfo::default())
+            .await
+    }
+
+    async fn stage_with_change(
+        &self,
+        key: &ConfigKey,
+        data: &ConfigData,
+        expected_version: Option<&str>,
+        change: &ChangeInfo,
+    ) -> Result<String> {
+        self.write_version(key, data, expected_version, false, change)
+            .await
+    }
+
+    async fn activate(&self, key: &ConfigKey, version: &str) -> Result<()> {
+        let _guard = self.lock_for_update(key).await;
+        let (mut metadata, read_at) = self
+            .read_full_metadata_for_update(key)
+            .await?
+            .ok_or_else(|| StorageError::NotFound(format!("Config not found: {key}")))?;
+        let read_version = metadata.current_version.clone();
+
+        if !metadata.activate(version) {
+            return Err(
+                StorageError::NotFound(format!("Version not found: {key} @ {version}")).into(),
+            );
+        }
+
+        self.replace_metadata(key, &metadata, Some(read_at), Some(&read_version))
+            .await
+    }
+
+    async fn mark_bad(&self, key: &ConfigKey, version: &str) -> Result<()> {
+        let _guard = self.lock_for_update(key).await;
+        let (mut metadata, read_at) = self
+            .read_full_metadata_for_update(key)
+            .await?
+            .ok_or_else(|| StorageError::NotFound(format!("Config not found: {key}")))?;
+        let read_version = metadata.current_version.clone();
+
+        if !metadata.mark_bad(version) {
+            return Err(
+                StorageError::NotFound(format!("Version not found: {key} @ {version}")).into(),
+            );
+        }
+
+        self.replace_metadata(key, &metadata, Some(read_at), Some(&read_version))
+            .await
+    }
+
+    async fn set_version_alias(&self, key: &ConfigKey, alias: &str, version: &str) -> Result<()> {
+        let _guard = self.lock_for_update(key).await;
+        let (mut metadata, read_at) = self
+            .read_full_metadata_for_update(key)
+            .await?
+            .ok_or_else(|| StorageError::NotFound(format!("Config not found: {key}")))?;
+        let read_version = metadata.current_version.clone();
+
+        if let Some(target) = &metadata.alias_of {
+            return Err(StorageError::IsAlias {
+                alias: key.to_string(),
+                target: target.to_string(),
+            }
+            .into());
+        }
+        if !metadata.set_version_alias(alias, version) {
+            return Err(
+                StorageError::NotFound(format!("Version not found: {key} @ {version}")).into(),
+            );
+        }
+
+        self.replace_metadata(key, &metadata, Some(read_at), Some(&read_version))
+            .await
+    }
+
+    async fn delete_version(&self, key: &ConfigKey, version: &str) -> Result<String> {
+        let _guard = self.lock_for_update(key).await;
+        let (mut metadata, read_at) = self
+            .read_full_metadata_for_update(key)
+            .await?
+            .ok_or_else(|| StorageError::NotFound(format!("Config not found: {key}")))?;
+        let read_version = metadata.current_version.clone();
+
+        if let Some(target) = &metadata.alias_of {
+            return Err(StorageError::IsAlias {
+                alias: key.to_string(),
+                target: target.to_string(),
+            }
+            .into());
+        }
+        if metadata.versions.len() == 1 && metadata.find_version(version).is_some() {
+            return Err(StorageError::OnlyVersion(key.to_string()).into());
+        }
+        let removed = metadata.remove_version(version).ok_or_else(|| {
+            StorageError::NotFound(format!("Version not found: {key} @ {version}"))
+        })?;
+
+        self.replace_metadata(key, &metadata, Some(read_at), Some(&read_version))
+            .await?;
+        self.discard_versions(key, &[removed]).await;
+        Ok(metadata.current_version)
     }
 
     async fn get(&self, key: &ConfigKey) -> Result<ConfigData> {
@@ -157,45 +929,38 @@ impl ConfigStorage for ObjectStoreBackend {
             );
         }
 
-        self.get_version(key, &metadata.current_version).await
+        self.read_version(key, &metadata.current_version, &metadata)
+            .await
     }
 
     async fn get_version(&self, key: &ConfigKey, version: &str) -> Result<ConfigData> {
-        let data_path = Self::version_path(key, version, "data.json");
-        let data_result = self
-            .store
-            .get(&data_path)
-            .await
-            .with_context(|| format!("Failed to read data for {key} @ {version}"))?;
-        let content: serde_json::Value = serde_json::from_slice(&data_result.bytes().await?)?;
-
-        let schema_path = Self::version_path(key, version, "schema.json");
-        let schema_result = self
-            .store
-            .get(&schema_path)
-            .await
-            .with_context(|| format!("Failed to read schema for {key} @ {version}"))?;
-        let schema: serde_json::Value = serde_json::from_slice(&schema_result.bytes().await?)?;
-
-        Ok(ConfigData {
-            content,
-            schema,
-            version: version.to_string(),
-        })
+        let mut metadata = self.read_metadata(key).await?.unwrap_or_default();
+        if metadata.find_version(version).is_none() && metadata.archived_versions.is_some() {
+            metadata = self.read_full_metadata(key).await?.unwrap_or_default();
+        }
+        self.read_version(key, version, &metadata).await
     }
 
     async fn delete_environment(&self, app: &str, env: &str) -> Result<usize> {
         use futures::StreamExt;
 
         // List all files in the app/env prefix
-        let prefix = Path::from(format!("{app}/{env}"));
+        let prefix = Path::from(format!(
+            "{}/{}",
+            self.path_component(app)?,
+            self.path_component(env)?
+        ));
         let mut stream = self.store.list(Some(&prefix));
 
         let mut deleted_count = 0;
         let mut configs_found = std::collections::HashSet::new();
 
         // Find all unique config names
-        while let Some(meta) = stream.next().await.transpose()? {
+        while let Some(meta) = self
+            .timed("list", &prefix, stream.next())
+            .await?
+            .transpose()?
+        {
             let parts: Vec<_> = meta.location.parts().collect();
             if parts.len() >= 3 {
                 configs_found.insert(parts[2].as_ref().to_string());
@@ -206,21 +971,9 @@ impl ConfigStorage for ObjectStoreBackend {
         for config_name in configs_found {
             let key = ConfigKey::new(app.to_string(), env.to_string(), config_name);
 
-            let metadata_opt = self.read_metadata(&key).await.ok().flatten();
+            let metadata_opt = self.read_full_metadata(&key).await.ok().flatten();
             if let Some(metadata) = metadata_opt {
-                // Delete all version files
-                for version_meta in &metadata.versions {
-                    let data_path = Self::version_path(&key, &version_meta.version, "data.json");
-                    let _ = self.store.delete(&data_path).await;
-                    let schema_path =
-                        Self::version_path(&key, &version_meta.version, "schema.json");
-                    let _ = self.store.delete(&schema_path).await;
-                }
-
-                // Delete metadata
-                let metadata_path = Self::config_path(&key, "metadata.json");
-                let _ = self.store.delete(&metadata_path).await;
-
+                self.delete_objects(&key, &metadata).await?;
                 deleted_count += 1;
             }
         }
@@ -228,9 +981,29 @@ impl ConfigStorage for ObjectStoreBackend {
         Ok(deleted_count)
     }
 
+    async fn delete(
+        &self,
+        key: &ConfigKey,
+        expected_version: Option<&str>,
+    ) -> Result<Option<usize>> {
+        let _guard = self.lock_for_update(key).await;
+        let Some(metadata) = self.read_full_metadata(key).await? else {
+            return Ok(None);
+        };
+        if let Some(expected) = expected_version.filter(|v| *v != metadata.current_version) {
+            return Err(StorageError::VersionConflict {
+                expected: expected.to_string(),
+                actual: metadata.current_version,
+            }
+            .into());
+        }
+        self.delete_objects(key, &metadata).await?;
+        Ok(Some(metadata.versions.len()))
+    }
+
     async fn exists(&self, key: &ConfigKey) -> Result<bool> {
-        let path = Self::config_path(key, "metadata.json");
-        match self.store.head(&path).await {
+        let path = self.config_path(key, "metadata.json")?;
+        match self.timed("head", &path, self.store.head(&path)).await? {
             Ok(_) => Ok(true),
             Err(object_store::Error::NotFound { .. }) => Ok(false),
             Err(e) => Err(e.into()),
@@ -239,7 +1012,7 @@ impl ConfigStorage for ObjectStoreBackend {
 
     async fn list_versions(&self, key: &ConfigKey) -> Result<Vec<VersionInfo>> {
         let metadata = self
-            .read_metadata(key)
+            .read_full_metadata(key)
             .await?
             .ok_or_else(|| StorageError::NotFound(format!("Config not found: {key}")))?;
 
@@ -249,7 +1022,211 @@ impl ConfigStorage for ObjectStoreBackend {
             .map(|v| VersionInfo {
                 version: v.version.clone(),
                 timestamp: v.timestamp,
+                bad: v.bad,
+                data_size: v.data_size,
+                has_schema: v.has_schema,
+                content_hash: v.content_hash.clone(),
+                change_message: v.change_message.clone(),
+                author: v.author.clone(),
             })
             .collect())
     }
+
+    async fn list(&self, prefix: Option<&str>) -> Result<Vec<ConfigKey>> {
+        use futures::StreamExt;
+
+        let prefix = prefix.map(|p| Path::from(self.key_component(p).as_ref()));
+        let mut stream = self.store.list(prefix.as_ref());
+        let list_path = prefix.clone().unwrap_or_default();
+
+        // Every config has exactly one app/env/config/metadata.json object
+        let mut keys = Vec::new();
+        while let Some(meta) = self
+            .timed("list", &list_path, stream.next())
+            .await?
+            .transpose()?
+        {
+            let parts: Vec<_> = meta.location.parts().collect();
+            if parts.len() == 4 && parts[3].as_ref() == "metadata.json" {
+                keys.push(ConfigKey::new(
+                    parts[0].as_ref(),
+                    parts[1].as_ref(),
+                    parts[2].as_ref(),
+                ));
+            }
+        }
+
+        keys.sort_by_key(ConfigKey::to_path);
+        Ok(keys)
+    }
+
+    async fn list_page(
+        &self,
+        prefix: Option<&str>,
+        limit: usize,
+        continuation_token: Option<&str>,
+    ) -> Result<ConfigPage> {
+        use futures::StreamExt;
+
+        let prefix = prefix.map(|p| Path::from(self.key_component(p).as_ref()));
+        let list_path = prefix.clone().unwrap_or_default();
+        let mut stream = match continuation_token {
+            Some(token) => {
+                let offset = Path::from(format!("{token}/metadata.json"));
+                self.store.list_with_offset(prefix.as_ref(), &offset)
+            }
+            None => self.store.list(prefix.as_ref()),
+        };
+
+        let mut keys = Vec::new();
+        while let Some(meta) = self
+            .timed("list", &list_path, stream.next())
+            .await?
+            .transpose()?
+        {
+            let parts: Vec<_> = meta.location.parts().collect();
+            if parts.len() == 4 && parts[3].as_ref() == "metadata.json" {
+                keys.push(ConfigKey::new(
+                    parts[0].as_ref(),
+                    parts[1].as_ref(),
+                    parts[2].as_ref(),
+                ));
+                // One past the page is enough to know another follows
+                if self.sorted_listing && keys.len() > limit {
+                    break;
+                }
+            }
+        }
+
+        Ok(ConfigPage::from_keys(keys, limit))
+    }
+
+    async fn metadata(&self, key: &ConfigKey) -> Result<Option<Metadata>> {
+        self.read_full_metadata(key).await
+    }
+
+    async fn collect_garbage(&self, apply: bool) -> Result<Vec<String>> {
+        use futures::StreamExt;
+
+        // Version objects live at app/env/config/versions/<version>/<file>
+        let root = Path::default();
+        let cutoff = chrono::Utc::now() - chrono::Duration::from_std(self.gc_grace_period)?;
+        let mut stream = self.store.list(None);
+        let mut version_objects = Vec::new();
+        while let Some(meta) = self
+            .timed("list", &root, stream.next())
+            .await?
+            .transpose()?
+        {
+            let parts: Vec<_> = meta.location.parts().collect();
+            if parts.len() == 6 && parts[3].as_ref() == "versions" && meta.last_modified <= cutoff {
+                let key = ConfigKey::new(parts[0].as_ref(), parts[1].as_ref(), parts[2].as_ref());
+                version_objects.push((key, parts[4].as_ref().to_string(), meta.location));
+            }
+        }
+
+        let mut referenced: HashMap<ConfigKey, HashSet<String>> = HashMap::new();
+        let mut orphans = Vec::new();
+        for (key, version, path) in version_objects {
+            if !referenced.contains_key(&key) {
+                // Without metadata, every version object of the config is orphaned
+                let versions = self
+                    .read_full_metadata(&key)
+                    .await?
+                    .map(|metadata| metadata.versions.into_iter().map(|v| v.version).collect())
+                    .unwrap_or_default();
+                referenced.insert(key.clone(), versions);
+            }
+            if referenced
+                .get(&key)
+                .is_some_and(|versions| versions.contains(&version))
+            {
+                continue;
+            }
+
+            if apply {
+                self.timed("delete", &path, self.store.delete(&path))
+                    .await??;
+            }
+            orphans.push(path.to_string());
+        }
+
+        orphans.sort();
+        Ok(orphans)
+    }
+
+    async fn copy(&self, from: &ConfigKey, to: &ConfigKey) -> Result<String> {
+        let data = self.get(from).await?;
+        let change = ChangeInfo {
+            derived_from: Some(ConfigOrigin {
+                key: from.clone(),
+                version: data.version.clone(),
+            }),
+            ..ChangeInfo::default()
+        };
+        self.write_version(to, &data, None, true, &change).await
+    }
+
+    async fn set_alias(&self, alias: &ConfigKey, target: &ConfigKey) -> Result<()> {
+        let _guard = self.lock_for_update(alias).await;
+        let (metadata, read_at) = self.read_metadata_for_update(alias).await?.unzip();
+        let mut metadata = metadata.unwrap_or_default();
+        if !metadata.versions.is_empty() {
+            return Err(StorageError::AlreadyExists(format!(
+                "{alias} has versions of its own and cannot become an alias"
+            ))
+            .into());
+        }
+
+        // Pointing at anything that already leads back here would loop forever
+        if self.alias_chain(target).await?.contains(alias) {
+            return Err(
+                StorageError::AliasCycle(format!("{alias} -> {target} -> ... -> {alias}")).into(),
+            );
+        }
+
+        metadata.alias_of = Some(target.clone());
+        self.replace_metadata(alias, &metadata, read_at, None).await
+    }
+
+    async fn resolve_alias(&self, key: &ConfigKey) -> Result<ConfigKey> {
+        let mut chain = self.alias_chain(key).await?;
+        Ok(chain.pop().unwrap_or_else(|| key.clone()))
+    }
+
+    async fn lineage(&self, key: &ConfigKey) -> Result<Vec<ConfigOrigin>> {
+        let mut metadata = self
+            .read_metadata(key)
+            .await?
+            .ok_or_else(|| StorageError::NotFound(format!("Config not found: {key}")))?;
+
+        let mut visited = std::collections::HashSet::from([key.clone()]);
+        let mut lineage = Vec::new();
+
+        while let Some(origin) = metadata.derived_from.take() {
+            // Copying back onto an ancestor can form a cycle
+            if !visited.insert(origin.key.clone()) {
+                break;
+            }
+            let next = self.read_metadata(&origin.key).await?;
+            lineage.push(origin);
+            match next {
+                Some(next) => metadata = next,
+                // The origin may have been deleted since; the chain ends there
+                None => break,
+            }
+        }
+
+        Ok(lineage)
+    }
+}
+
+/// Lowercase hex SHA-256 of `bytes`
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
 }
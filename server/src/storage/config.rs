@@ -1,10 +1,51 @@
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+/// How `ConfigKey` components are mapped onto storage paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyCase {
+    /// Keys are stored exactly as given, so `MyApp` and `myapp` are distinct
+    #[default]
+    Sensitive,
+    /// Keys are lowercased, so `MyApp/Prod/DB` and `myapp/prod/db` are the same config
+    Lower,
+}
+
+impl KeyCase {
+    pub fn from_env() -> anyhow::Result<Self> {
+        std::env::var("KEY_CASE").map_or(Ok(Self::default()), |value| value.parse())
+    }
+
+    /// A key component as it is stored under this case
+    pub fn apply(self, component: &str) -> Cow<'_, str> {
+        match self {
+            Self::Sensitive => Cow::Borrowed(component),
+            Self::Lower => Cow::Owned(component.to_lowercase()),
+        }
+    }
+}
+
+impl FromStr for KeyCase {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sensitive" => Ok(Self::Sensitive),
+            "lower" => Ok(Self::Lower),
+            _ => anyhow::bail!("Unknown key case: {s}. Must be 'sensitive' or 'lower'"),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StorageConfig {
     Local {
         path: PathBuf,
+        /// Versions kept per config; unlimited if unset
+        #[serde(default)]
+        max_versions: Option<usize>,
     },
     S3 {
         bucket: String,
@@ -13,12 +54,28 @@ pub enum StorageConfig {
         access_key_id: Option<String>,
         secret_access_key: Option<String>,
         allow_http: bool,
+        /// Versions kept per config; unlimited if unset
+        #[serde(default)]
+        max_versions: Option<usize>,
+    },
+    /// Held in process memory and lost on exit; for tests and trying things out
+    InMemory {
+        /// Versions kept per config; unlimited if unset
+        #[serde(default)]
+        max_versions: Option<usize>,
     },
 }
 
 impl StorageConfig {
     pub fn local(path: impl Into<PathBuf>) -> Self {
-        Self::Local { path: path.into() }
+        Self::Local {
+            path: path.into(),
+            max_versions: None,
+        }
+    }
+
+    pub fn memory() -> Self {
+        Self::InMemory { max_versions: None }
     }
 
     pub fn s3(
@@ -36,16 +93,42 @@ impl StorageConfig {
             access_key_id,
             secret_access_key,
             allow_http,
+            max_versions: None,
+        }
+    }
+
+    /// Keep at most `max_versions` versions of each config, deleting the
+    /// oldest beyond that
+    #[must_use]
+    pub fn with_max_versions(mut self, max_versions: usize) -> Self {
+        match &mut self {
+            Self::Local {
+                max_versions: max, ..
+            }
+            | Self::S3 {
+                max_versions: max, ..
+            }
+            | Self::InMemory { max_versions: max } => *max = Some(max_versions),
+        }
+        self
+    }
+
+    /// Versions kept per config, if limited
+    pub fn max_versions(&self) -> Option<usize> {
+        match self {
+            Self::Local { max_versions, .. }
+            | Self::S3 { max_versions, .. }
+            | Self::InMemory { max_versions } => *max_versions,
         }
     }
 
     pub fn from_env() -> anyhow::Result<Self> {
         let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
 
-        match backend.as_str() {
+        let config = match backend.as_str() {
             "local" => {
                 let path = std::env::var("STORAGE_PATH").unwrap_or_else(|_| "./data".to_string());
-                Ok(Self::local(path))
+                Self::local(path)
             }
             "s3" => {
                 let bucket = std::env::var("AWS_BUCKET")
@@ -59,16 +142,24 @@ impl StorageConfig {
                     .parse::<bool>()
                     .unwrap_or(false);
 
-                Ok(Self::s3(
+                Self::s3(
                     bucket,
                     region,
                     endpoint,
                     access_key_id,
                     secret_access_key,
                     allow_http,
-                ))
+                )
             }
-            _ => anyhow::bail!("Unknown storage backend: {backend}. Must be 'local' or 's3'"),
+            "memory" => Self::memory(),
+            _ => anyhow::bail!(
+                "Unknown storage backend: {backend}. Must be 'local', 's3' or 'memory'"
+            ),
+        };
+
+        match std::env::var("STORAGE_MAX_VERSIONS") {
+            Ok(max_versions) => Ok(config.with_max_versions(max_versions.parse()?)),
+            Err(_) => Ok(config),
         }
     }
 }
@@ -10,4 +10,28 @@ pub enum StorageError {
 
     #[error("Version conflict: expected {expected}, but found {actual}")]
     VersionConflict { expected: String, actual: String },
+
+    #[error("Version history corrupted: {0}")]
+    VersionCorruption(String),
+
+    #[error("Stored content failed its integrity check: {0}")]
+    IntegrityError(String),
+
+    #[error("Storage timeout: {0}")]
+    Timeout(String),
+
+    #[error("{alias} is an alias for {target}; write to {target} instead")]
+    IsAlias { alias: String, target: String },
+
+    #[error("Alias cycle: {0}")]
+    AliasCycle(String),
+
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    #[error("Cannot delete the only version of {0}; delete the configuration instead")]
+    OnlyVersion(String),
+
+    #[error("This storage backend does not support {0}")]
+    Unsupported(String),
 }
@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use shared_types::{ConfigKey, ConfigOrigin};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Metadata {
@@ -7,12 +9,52 @@ pub struct Metadata {
     pub current_version: String,
     #[serde(default)]
     pub versions: Vec<VersionMetadata>,
+    /// Set when the config was created as a copy of another config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derived_from: Option<ConfigOrigin>,
+    /// Set when this key is an alias: reads resolve to the target, writes are refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias_of: Option<ConfigKey>,
+    /// How many older versions compaction has moved out to the version
+    /// archive; `None` if `versions` is the whole history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_versions: Option<usize>,
+    /// Highest version number ever assigned, recorded once the version that
+    /// held it is deleted so the number is not handed out again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_version_number: Option<u32>,
+    /// Names like `stable` tagging particular versions, so clients can read
+    /// a version without knowing its number
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub aliases: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VersionMetadata {
     pub version: String,
     pub timestamp: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Marked as broken; it can still be read but not made current again
+    /// without forcing it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bad: bool,
+    /// Size in bytes of the stored content
+    #[serde(default)]
+    pub data_size: usize,
+    /// Whether a schema was stored alongside the content
+    #[serde(default)]
+    pub has_schema: bool,
+    /// Hex SHA-256 of the stored content; empty for versions written before
+    /// hashes were recorded
+    #[serde(default)]
+    pub content_hash: String,
+    /// Why the version was written, as given by whoever wrote it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_message: Option<String>,
+    /// Who wrote the version, if the server could tell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
 }
 
 impl Metadata {
@@ -20,13 +62,136 @@ impl Metadata {
         Self::default()
     }
 
-    pub fn add_version(&mut self, version: String) {
+    pub fn add_version(&mut self, version: String) -> &mut VersionMetadata {
         let version_meta = VersionMetadata {
             version: version.clone(),
             timestamp: Utc::now(),
+            content_type: None,
+            bad: false,
+            data_size: 0,
+            has_schema: false,
+            content_hash: String::new(),
+            change_message: None,
+            author: None,
         };
-        self.versions.push(version_meta);
         self.current_version = version;
+        self.versions.push(version_meta);
+        let last = self.versions.len() - 1;
+        &mut self.versions[last]
+    }
+
+    /// Point `current_version` at an existing version; false if there is no such version
+    pub fn activate(&mut self, version: &str) -> bool {
+        if self.find_version(version).is_none() {
+            return false;
+        }
+        self.current_version = version.to_string();
+        true
+    }
+
+    pub fn find_version(&self, version: &str) -> Option<&VersionMetadata> {
+        self.versions.iter().find(|v| v.version == version)
+    }
+
+    /// Flag an existing version as bad; false if there is no such version
+    pub fn mark_bad(&mut self, version: &str) -> bool {
+        match self.versions.iter_mut().find(|v| v.version == version) {
+            Some(v) => {
+                v.bad = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Point the version alias `alias` at an existing version; false if there
+    /// is no such version
+    pub fn set_version_alias(&mut self, alias: &str, version: &str) -> bool {
+        if self.find_version(version).is_none() {
+            return false;
+        }
+        self.aliases.insert(alias.to_string(), version.to_string());
+        true
+    }
+
+    /// The newest version written at or before `at`, if the config existed then
+    pub fn version_at(&self, at: DateTime<Utc>) -> Option<&VersionMetadata> {
+        self.versions
+            .iter()
+            .filter(|v| v.timestamp <= at)
+            .max_by_key(|v| v.timestamp)
+    }
+
+    /// Remove and return every version except the newest `keep` (at least
+    /// one) and the current one, oldest first, for archiving
+    pub fn split_for_archive(&mut self, keep: usize) -> Vec<VersionMetadata> {
+        let cut = self.versions.len().saturating_sub(keep.max(1));
+        let older: Vec<_> = self.versions.drain(..cut).collect();
+        let (current, archived): (Vec<_>, Vec<_>) = older
+            .into_iter()
+            .partition(|v| v.version == self.current_version);
+        self.versions.splice(0..0, current);
+        archived
+    }
+
+    /// Remove and return the oldest versions beyond the newest `keep` (at
+    /// least one), sparing the newest version, the current version and
+    /// aliased versions wherever they fall. Pruned version numbers are never
+    /// handed out again.
+    pub fn prune(&mut self, keep: usize) -> Vec<VersionMetadata> {
+        let mut excess = self.versions.len().saturating_sub(keep.max(1));
+        let highest = self.next_version_number() - 1;
+        let newest = self.versions.last().map(|v| v.version.clone());
+        let (pruned, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.versions)
+            .into_iter()
+            .partition(|v| {
+                let prune = excess > 0
+                    && v.version != self.current_version
+                    && Some(&v.version) != newest.as_ref()
+                    && !self.aliases.values().any(|aliased| *aliased == v.version);
+                if prune {
+                    excess -= 1;
+                }
+                prune
+            });
+        self.versions = kept;
+        if !pruned.is_empty() {
+            self.last_version_number = Some(highest);
+        }
+        pruned
+    }
+
+    /// Remove a version along with any aliases of it, moving `current_version`
+    /// to the newest remaining one (preferring versions not marked bad) if it
+    /// was current
+    pub fn remove_version(&mut self, version: &str) -> Option<VersionMetadata> {
+        let index = self.versions.iter().position(|v| v.version == version)?;
+        self.last_version_number = Some(self.next_version_number() - 1);
+        let removed = self.versions.remove(index);
+        self.aliases.retain(|_, aliased| aliased != version);
+
+        if self.current_version == version {
+            let replacement = self
+                .versions
+                .iter()
+                .rev()
+                .find(|v| !v.bad)
+                .or_else(|| self.versions.last());
+            self.current_version = replacement.map(|v| v.version.clone()).unwrap_or_default();
+        }
+        Some(removed)
+    }
+
+    /// Fold archived versions back in, making `versions` the whole history
+    pub fn merge_archive(&mut self, archive: Vec<VersionMetadata>) {
+        let mut all: Vec<_> = archive
+            .into_iter()
+            .filter(|archived| self.find_version(&archived.version).is_none())
+            .collect();
+        all.append(&mut self.versions);
+        all.sort_by_key(|v| v.timestamp);
+        self.versions = all;
+        self.archived_versions = None;
     }
 
     pub fn next_version_number(&self) -> u32 {
@@ -37,6 +202,7 @@ impl Metadata {
                     .strip_prefix('v')
                     .and_then(|n| n.parse::<u32>().ok())
             })
+            .chain(self.last_version_number)
             .max()
             .unwrap_or(0)
             + 1
@@ -103,14 +269,35 @@ mod tests {
         metadata.versions.push(VersionMetadata {
             version: "v1".to_string(),
             timestamp: Utc::now(),
+            content_type: None,
+            bad: false,
+            data_size: 0,
+            has_schema: false,
+            content_hash: String::new(),
+            change_message: None,
+            author: None,
         });
         metadata.versions.push(VersionMetadata {
             version: "v10".to_string(),
             timestamp: Utc::now(),
+            content_type: None,
+            bad: false,
+            data_size: 0,
+            has_schema: false,
+            content_hash: String::new(),
+            change_message: None,
+            author: None,
         });
         metadata.versions.push(VersionMetadata {
             version: "v5".to_string(),
             timestamp: Utc::now(),
+            content_type: None,
+            bad: false,
+            data_size: 0,
+            has_schema: false,
+            content_hash: String::new(),
+            change_message: None,
+            author: None,
         });
 
         assert_eq!(metadata.next_version_number(), 11);
@@ -122,19 +309,195 @@ mod tests {
         metadata.versions.push(VersionMetadata {
             version: "invalid".to_string(),
             timestamp: Utc::now(),
+            content_type: None,
+            bad: false,
+            data_size: 0,
+            has_schema: false,
+            content_hash: String::new(),
+            change_message: None,
+            author: None,
         });
         metadata.versions.push(VersionMetadata {
             version: "v2".to_string(),
             timestamp: Utc::now(),
+            content_type: None,
+            bad: false,
+            data_size: 0,
+            has_schema: false,
+            content_hash: String::new(),
+            change_message: None,
+            author: None,
         });
         metadata.versions.push(VersionMetadata {
             version: "vNaN".to_string(),
             timestamp: Utc::now(),
+            content_type: None,
+            bad: false,
+            data_size: 0,
+            has_schema: false,
+            content_hash: String::new(),
+            change_message: None,
+            author: None,
         });
 
         assert_eq!(metadata.next_version_number(), 3);
     }
 
+    #[test]
+    fn test_activate() {
+        let mut metadata = Metadata::new();
+        metadata.add_version("v1".to_string());
+        metadata.add_version("v2".to_string());
+
+        assert!(metadata.activate("v1"));
+        assert_eq!(metadata.current_version, "v1");
+        assert!(!metadata.activate("v3"));
+        assert_eq!(metadata.current_version, "v1");
+    }
+
+    #[test]
+    fn test_find_version() {
+        let mut metadata = Metadata::new();
+        metadata.add_version("v1".to_string()).content_type = Some("text/plain".to_string());
+        metadata.add_version("v2".to_string());
+
+        let v1 = metadata.find_version("v1");
+        assert_eq!(
+            v1.and_then(|v| v.content_type.as_deref()),
+            Some("text/plain")
+        );
+        assert!(metadata.find_version("v2").is_some());
+        assert!(metadata.find_version("v3").is_none());
+    }
+
+    #[test]
+    fn test_split_and_merge_archive() {
+        let mut metadata = Metadata::new();
+        for n in 1..=6 {
+            metadata.add_version(format!("v{n}"));
+        }
+        metadata.activate("v2");
+
+        let archived = metadata.split_for_archive(2);
+        let names = |versions: &[VersionMetadata]| -> Vec<String> {
+            versions.iter().map(|v| v.version.clone()).collect()
+        };
+        assert_eq!(names(&archived), ["v1", "v3", "v4"]);
+        // The current version stays inline even though it is old
+        assert_eq!(names(&metadata.versions), ["v2", "v5", "v6"]);
+        assert_eq!(metadata.next_version_number(), 7);
+
+        metadata.merge_archive(archived);
+        assert_eq!(
+            names(&metadata.versions),
+            ["v1", "v2", "v3", "v4", "v5", "v6"]
+        );
+    }
+
+    #[test]
+    fn test_prune_spares_current_version() {
+        let mut metadata = Metadata::new();
+        for n in 1..=5 {
+            metadata.add_version(format!("v{n}"));
+        }
+        metadata.activate("v1");
+
+        let pruned: Vec<String> = metadata.prune(2).into_iter().map(|v| v.version).collect();
+        let kept: Vec<&str> = metadata
+            .versions
+            .iter()
+            .map(|v| v.version.as_str())
+            .collect();
+        assert_eq!(pruned, ["v2", "v3", "v4"]);
+        assert_eq!(kept, ["v1", "v5"]);
+    }
+
+    #[test]
+    fn test_prune_spares_newest_version_and_its_number() {
+        let mut metadata = Metadata::new();
+        for n in 1..=3 {
+            metadata.add_version(format!("v{n}"));
+        }
+        // v3 staged rather than made current
+        metadata.activate("v2");
+
+        let pruned: Vec<String> = metadata.prune(1).into_iter().map(|v| v.version).collect();
+        let kept: Vec<&str> = metadata
+            .versions
+            .iter()
+            .map(|v| v.version.as_str())
+            .collect();
+        assert_eq!(pruned, ["v1"]);
+        assert_eq!(kept, ["v2", "v3"]);
+        assert_eq!(metadata.last_version_number, Some(3));
+    }
+
+    #[test]
+    fn test_version_aliases_follow_their_versions() {
+        let mut metadata = Metadata::new();
+        for n in 1..=5 {
+            metadata.add_version(format!("v{n}"));
+        }
+        assert!(metadata.set_version_alias("stable", "v2"));
+        assert!(metadata.set_version_alias("canary", "v3"));
+        assert!(!metadata.set_version_alias("stable", "v9"));
+
+        let pruned: Vec<String> = metadata.prune(2).into_iter().map(|v| v.version).collect();
+        assert_eq!(pruned, ["v1", "v4"]);
+
+        metadata.remove_version("v3");
+        assert_eq!(
+            metadata.aliases.get("stable").map(String::as_str),
+            Some("v2")
+        );
+        assert!(!metadata.aliases.contains_key("canary"));
+    }
+
+    #[test]
+    fn test_remove_current_version_promotes_newest_good() {
+        let mut metadata = Metadata::new();
+        for n in 1..=3 {
+            metadata.add_version(format!("v{n}"));
+        }
+        metadata.mark_bad("v2");
+
+        let removed = metadata.remove_version("v3").map(|v| v.version);
+        assert_eq!(removed.as_deref(), Some("v3"));
+        assert_eq!(metadata.current_version, "v1");
+        // The removed number is never reused
+        assert_eq!(metadata.next_version_number(), 4);
+        assert!(metadata.remove_version("v3").is_none());
+    }
+
+    #[test]
+    fn test_version_at() -> Result<(), Box<dyn std::error::Error>> {
+        let mut metadata = Metadata::new();
+        for (version, timestamp) in [
+            ("v1", "2024-01-01T00:00:00Z"),
+            ("v2", "2024-01-02T00:00:00Z"),
+        ] {
+            metadata.add_version(version.to_string()).timestamp = timestamp.parse()?;
+        }
+
+        let version_at = |at: &str| -> Result<_, chrono::ParseError> {
+            Ok(metadata.version_at(at.parse()?).map(|v| v.version.as_str()))
+        };
+        assert_eq!(version_at("2023-12-31T23:59:59Z")?, None);
+        assert_eq!(version_at("2024-01-01T00:00:00Z")?, Some("v1"));
+        assert_eq!(version_at("2024-01-01T14:32:00Z")?, Some("v1"));
+        assert_eq!(version_at("2024-06-01T00:00:00Z")?, Some("v2"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_metadata_without_content_type_deserializes()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let json = r#"{"version": "v1", "timestamp": "2024-01-01T00:00:00Z"}"#;
+        let version: VersionMetadata = serde_json::from_str(json)?;
+        assert_eq!(version.content_type, None);
+        Ok(())
+    }
+
     #[test]
     fn test_version_metadata_timestamp() {
         let before = Utc::now();
@@ -2,9 +2,11 @@ pub mod backend;
 pub mod config;
 pub mod error;
 pub mod metadata;
+pub mod migrate;
+pub mod seed;
 pub mod traits;
 
 pub use backend::ObjectStoreBackend;
-pub use config::StorageConfig;
+pub use config::{KeyCase, StorageConfig};
 pub use error::StorageError;
-pub use traits::ConfigStorage;
+pub use traits::{ChangeInfo, ConfigPage, ConfigStorage};
@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use shared_types::{ConfigData, ConfigKey};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use super::traits::ConfigStorage;
+
+const SCHEMA_SUFFIX: &str = ".schema.json";
+
+/// Seed an empty store from a directory laid out as `app/env/config.json`
+///
+/// Each config may have a sibling `config.schema.json`; without one the config
+/// is stored with a permissive object schema. Nothing is written if the store
+/// already contains any configuration. Returns the number of configs created.
+pub async fn seed_from_dir(storage: &dyn ConfigStorage, dir: &Path) -> Result<usize> {
+    if !storage.list(None).await?.is_empty() {
+        info!("Store already contains configurations, skipping seed");
+        return Ok(0);
+    }
+
+    let files = collect_seed_files(dir)?;
+    for (key, content_path) in &files {
+        let data = read_seed_file(content_path)?;
+        storage
+            .put(key, &data, None)
+            .await
+            .with_context(|| format!("Failed to seed {key}"))?;
+        info!("Seeded {} from {}", key, content_path.display());
+    }
+
+    info!(
+        "Seeded {} configurations from {}",
+        files.len(),
+        dir.display()
+    );
+    Ok(files.len())
+}
+
+fn collect_seed_files(dir: &Path) -> Result<Vec<(ConfigKey, PathBuf)>> {
+    let mut files = Vec::new();
+
+    for app in subdirectories(dir)? {
+        for env in subdirectories(&app)? {
+            for entry in std::fs::read_dir(&env)? {
+                let path = entry?.path();
+                let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if !path.is_file() || name.ends_with(SCHEMA_SUFFIX) {
+                    continue;
+                }
+                let Some(config_name) = name.strip_suffix(".json") else {
+                    continue;
+                };
+
+                let key = ConfigKey::new(dir_name(&app)?, dir_name(&env)?, config_name);
+                files.push((key, path));
+            }
+        }
+    }
+
+    files.sort_by_key(|(key, _)| key.to_path());
+    Ok(files)
+}
+
+fn read_seed_file(content_path: &Path) -> Result<ConfigData> {
+    let content: serde_json::Value = serde_json::from_slice(&std::fs::read(content_path)?)
+        .with_context(|| format!("Invalid JSON in {}", content_path.display()))?;
+
+    let schema_path = content_path.with_extension("schema.json");
+    let schema = if schema_path.is_file() {
+        serde_json::from_slice(&std::fs::read(&schema_path)?)
+            .with_context(|| format!("Invalid JSON in {}", schema_path.display()))?
+    } else {
+        serde_json::json!({"type": "object"})
+    };
+
+    let validator = jsonschema::Validator::new(&schema)
+        .map_err(|e| anyhow::anyhow!("Invalid schema for {}: {e}", content_path.display()))?;
+    if !validator.is_valid(&content) {
+        anyhow::bail!(
+            "Seed content {} does not match its schema",
+            content_path.display()
+        );
+    }
+
+    Ok(ConfigData {
+        content,
+        schema,
+        version: String::new(),
+        content_type: None,
+    })
+}
+
+fn subdirectories(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Cannot read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            dirs.push(path);
+        }
+    }
+    Ok(dirs)
+}
+
+fn dir_name(path: &Path) -> Result<&str> {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Invalid seed directory name: {}", path.display()))
+}
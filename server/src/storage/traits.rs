@@ -1,18 +1,228 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use shared_types::{ConfigData, ConfigKey, VersionInfo};
+use shared_types::{ConfigData, ConfigKey, ConfigOrigin, VersionInfo};
+use std::collections::BTreeSet;
 
+use super::config::KeyCase;
+use super::error::StorageError;
+use super::metadata::Metadata;
+
+/// One page of a listing, with the token that continues it if more remain
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigPage {
+    pub keys: Vec<ConfigKey>,
+    pub next_token: Option<String>,
+}
+
+impl ConfigPage {
+    /// The first `limit` of `keys` in page order, continuing after the last
+    /// of them if there were more
+    pub fn from_keys(mut keys: Vec<ConfigKey>, limit: usize) -> Self {
+        keys.sort_by_key(Self::position);
+        let next_token = (keys.len() > limit).then(|| {
+            keys.truncate(limit);
+            keys.last().map(ConfigKey::to_path)
+        });
+        Self {
+            keys,
+            next_token: next_token.flatten(),
+        }
+    }
+
+    /// Whether `key` comes after the one `token` names
+    pub fn is_after(key: &ConfigKey, token: &str) -> bool {
+        Self::position(key) > format!("{token}/")
+    }
+
+    /// Pages are ordered like the objects under each config's directory, so
+    /// `a/b/c-x` comes before `a/b/c`
+    fn position(key: &ConfigKey) -> String {
+        format!("{}/", key.to_path())
+    }
+}
+
+/// What a writer says about a version, recorded alongside it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeInfo {
+    /// Why the version was written
+    pub message: Option<String>,
+    /// Who wrote it
+    pub author: Option<String>,
+    /// The config version its content was copied from, if any
+    pub derived_from: Option<ConfigOrigin>,
+}
+
+/// A store of versioned configurations.
+///
+/// Bulk operations like [`delete_environment`](Self::delete_environment) have
+/// default implementations built on [`list`](Self::list) and
+/// [`delete`](Self::delete); backends can override them with something faster.
+/// Optional features such as staging, aliases and garbage collection fail
+/// with [`StorageError::Unsupported`] unless a backend implements them, so a
+/// backend only has to provide the core reads, writes and version history.
 #[async_trait]
 pub trait ConfigStorage: Send + Sync {
+    /// How key components are cased when stored, so names from requests can
+    /// be compared with stored ones
+    fn key_case(&self) -> KeyCase {
+        KeyCase::Sensitive
+    }
     async fn get(&self, key: &ConfigKey) -> Result<ConfigData>;
+    /// Write a new version and make it current. Returns the version written.
     async fn put(
         &self,
         key: &ConfigKey,
         data: &ConfigData,
         expected_version: Option<&str>,
-    ) -> Result<()>;
-    async fn delete_environment(&self, app: &str, env: &str) -> Result<usize>;
+    ) -> Result<String>;
+    /// [`put`](Self::put), recording `change` against the version written.
+    /// Backends that keep no such history just put.
+    async fn put_with_change(
+        &self,
+        key: &ConfigKey,
+        data: &ConfigData,
+        expected_version: Option<&str>,
+        _change: &ChangeInfo,
+    ) -> Result<String> {
+        self.put(key, data, expected_version).await
+    }
+    /// Write a new version without making it current. Returns the version written.
+    async fn stage(
+        &self,
+        _key: &ConfigKey,
+        _data: &ConfigData,
+        _expected_version: Option<&str>,
+    ) -> Result<String> {
+        unsupported("staging versions")
+    }
+    /// [`stage`](Self::stage), recording `change` against the version written.
+    /// Backends that keep no such history just stage.
+    async fn stage_with_change(
+        &self,
+        key: &ConfigKey,
+        data: &ConfigData,
+        expected_version: Option<&str>,
+        _change: &ChangeInfo,
+    ) -> Result<String> {
+        self.stage(key, data, expected_version).await
+    }
+    /// Make an existing version the current one
+    async fn activate(&self, _key: &ConfigKey, _version: &str) -> Result<()> {
+        unsupported("activating versions")
+    }
+    /// Flag a version as bad so it is not made current again by accident
+    async fn mark_bad(&self, _key: &ConfigKey, _version: &str) -> Result<()> {
+        unsupported("marking versions bad")
+    }
+    /// Tag an existing version with `alias`, moving the alias if it already
+    /// names another version
+    async fn set_version_alias(
+        &self,
+        _key: &ConfigKey,
+        _alias: &str,
+        _version: &str,
+    ) -> Result<()> {
+        unsupported("version aliases")
+    }
+    /// Remove a single version. If it was current, the newest remaining
+    /// version becomes current; the only version cannot be deleted. Returns
+    /// the version current afterwards.
+    async fn delete_version(&self, _key: &ConfigKey, _version: &str) -> Result<String> {
+        unsupported("deleting versions")
+    }
+    /// Remove every config in an environment. Returns how many were removed.
+    async fn delete_environment(&self, app: &str, env: &str) -> Result<usize> {
+        let mut deleted_count = 0;
+        for key in self.list(Some(&format!("{app}/{env}"))).await? {
+            if self.delete(&key, None).await?.is_some() {
+                deleted_count += 1;
+            }
+        }
+        Ok(deleted_count)
+    }
+    /// Every application with at least one config, sorted
+    async fn list_applications(&self) -> Result<Vec<String>> {
+        let apps: BTreeSet<String> = self
+            .list(None)
+            .await?
+            .into_iter()
+            .map(|key| key.application)
+            .collect();
+        Ok(apps.into_iter().collect())
+    }
+    /// Every environment of `app` with at least one config, sorted
+    async fn list_environments(&self, app: &str) -> Result<Vec<String>> {
+        let envs: BTreeSet<String> = self
+            .list(Some(app))
+            .await?
+            .into_iter()
+            .map(|key| key.environment)
+            .collect();
+        Ok(envs.into_iter().collect())
+    }
+    /// Remove a config with all of its versions, only if `expected_version` is
+    /// current when given. Returns how many versions were removed, or `None` if
+    /// the config did not exist.
+    async fn delete(
+        &self,
+        key: &ConfigKey,
+        expected_version: Option<&str>,
+    ) -> Result<Option<usize>>;
     async fn exists(&self, key: &ConfigKey) -> Result<bool>;
     async fn get_version(&self, key: &ConfigKey, version: &str) -> Result<ConfigData>;
     async fn list_versions(&self, key: &ConfigKey) -> Result<Vec<VersionInfo>>;
+    /// Every config, or those under `prefix`: an `app` or `app/env` path,
+    /// matched by whole segments
+    async fn list(&self, prefix: Option<&str>) -> Result<Vec<ConfigKey>>;
+    /// At most `limit` configs of [`list`](Self::list), starting after the one
+    /// a previous page's `next_token` named
+    async fn list_page(
+        &self,
+        prefix: Option<&str>,
+        limit: usize,
+        continuation_token: Option<&str>,
+    ) -> Result<ConfigPage> {
+        let keys = self
+            .list(prefix)
+            .await?
+            .into_iter()
+            .filter(|key| continuation_token.is_none_or(|token| ConfigPage::is_after(key, token)))
+            .collect();
+        Ok(ConfigPage::from_keys(keys, limit))
+    }
+    /// Create `to` as a new config holding the current version of `from`,
+    /// recording `from` as its origin. Returns the version created.
+    async fn copy(&self, _from: &ConfigKey, _to: &ConfigKey) -> Result<String> {
+        unsupported("copying configs")
+    }
+    /// The stored metadata for `key`, or `None` if the config does not exist
+    async fn metadata(&self, _key: &ConfigKey) -> Result<Option<Metadata>> {
+        unsupported("config metadata")
+    }
+    /// Find version objects that their config's metadata does not reference,
+    /// deleting them when `apply` is set. Returns the orphaned object paths.
+    /// Backends may pass over recent objects a write in flight could still
+    /// reference.
+    async fn collect_garbage(&self, _apply: bool) -> Result<Vec<String>> {
+        unsupported("garbage collection")
+    }
+    /// Point `alias` at `target`, so reads of `alias` can be served from `target`
+    async fn set_alias(&self, _alias: &ConfigKey, _target: &ConfigKey) -> Result<()> {
+        unsupported("config aliases")
+    }
+    /// Follow aliases from `key` to the config they point at; `key` itself if
+    /// it is not an alias. Without alias support, that is always `key`.
+    async fn resolve_alias(&self, key: &ConfigKey) -> Result<ConfigKey> {
+        Ok(key.clone())
+    }
+    /// The chain of configs `key` was derived from, nearest first. Without
+    /// copy support, nothing is derived from anything.
+    async fn lineage(&self, _key: &ConfigKey) -> Result<Vec<ConfigOrigin>> {
+        Ok(Vec::new())
+    }
+}
+
+/// The error for an operation a backend does not implement
+fn unsupported<T>(operation: &str) -> Result<T> {
+    Err(StorageError::Unsupported(operation.to_string()).into())
 }
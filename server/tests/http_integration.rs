@@ -2,47 +2,56 @@ use axum::{
     Router,
     body::Body,
     http::{Request, StatusCode},
-    routing::{delete, get, put},
+    routing::get,
 };
+use server::http::ServerSettings;
 use server::http::dto::*;
 use server::http::handlers;
 use server::http::state::AppState;
-use server::storage::{ObjectStoreBackend, StorageConfig};
+use server::storage::{ConfigStorage, KeyCase, ObjectStoreBackend, StorageConfig};
+use shared_types::{ConfigData, ConfigKey, ConfigOrigin, PatchOperation};
 use std::sync::Arc;
 use tempfile::TempDir;
 use tower::util::ServiceExt;
 
-fn create_test_app() -> anyhow::Result<(Router, TempDir)> {
-    let temp_dir = TempDir::new()?;
-    let config = StorageConfig::Local {
-        path: temp_dir.path().to_path_buf(),
-    };
-    let storage = ObjectStoreBackend::from_config(config)?;
-    let state = Arc::new(AppState {
-        storage: Arc::new(storage),
-    });
+fn create_test_app() -> anyhow::Result<Router> {
+    let (app, _storage) = create_test_app_with_storage()?;
+    Ok(app)
+}
 
-    let app = Router::new()
-        .route("/configs/:app/:env/:config", get(handlers::get_config))
-        .route("/configs/:app/:env/:config", put(handlers::put_config))
-        .route("/configs/:app/:env", delete(handlers::delete_environment))
-        .route(
-            "/configs/:app/:env/:config/versions",
-            get(handlers::list_versions),
-        )
-        .route(
-            "/configs/:app/:env/:config/versions/:version",
-            get(handlers::get_config_version),
-        )
-        .route("/health", get(handlers::health_check))
-        .with_state(state);
+/// Like `create_test_app`, but also hands back the storage so tests can
+/// arrange state that the HTTP API cannot produce directly
+fn create_test_app_with_storage() -> anyhow::Result<(Router, Arc<ObjectStoreBackend>)> {
+    create_test_app_with_settings(ServerSettings::default())
+}
+
+fn create_test_app_with_settings(
+    settings: ServerSettings,
+) -> anyhow::Result<(Router, Arc<ObjectStoreBackend>)> {
+    let storage = Arc::new(ObjectStoreBackend::from_config(StorageConfig::memory())?);
+    Ok((app_over(storage.clone(), settings), storage))
+}
 
-    Ok((app, temp_dir))
+/// Like `create_test_app_with_settings`, but stored on disk under the returned
+/// directory, for tests that tamper with the stored files
+fn create_disk_test_app(
+    settings: ServerSettings,
+) -> anyhow::Result<(Router, Arc<ObjectStoreBackend>, TempDir)> {
+    let temp_dir = TempDir::new()?;
+    let storage = Arc::new(ObjectStoreBackend::from_config(StorageConfig::local(
+        temp_dir.path(),
+    ))?);
+    Ok((app_over(storage.clone(), settings), storage, temp_dir))
+}
+
+fn app_over(storage: Arc<ObjectStoreBackend>, settings: ServerSettings) -> Router {
+    let state = Arc::new(AppState::new(storage).with_settings(settings));
+    server::http::create_router(state)
 }
 
 #[tokio::test]
 async fn test_health_check() -> anyhow::Result<()> {
-    let (app, _dir) = create_test_app()?;
+    let app = create_test_app()?;
 
     let response = app
         .oneshot(Request::builder().uri("/health").body(Body::empty())?)
@@ -61,13 +70,15 @@ async fn test_health_check() -> anyhow::Result<()> {
 
 #[tokio::test]
 async fn test_put_and_get_config() -> anyhow::Result<()> {
-    let (app, _dir) = create_test_app()?;
+    let app = create_test_app()?;
 
     // Create a config
     let put_request = PutConfigRequest {
         content: serde_json::json!({"database": "postgres", "port": 5432}),
         schema: Some(serde_json::json!({"type": "object"})),
         expected_version: None,
+        content_type: None,
+        change_message: None,
     };
 
     let response = app
@@ -102,6 +113,7 @@ async fn test_put_and_get_config() -> anyhow::Result<()> {
     assert_eq!(config.config_name, "database");
     assert_eq!(config.version, "v1");
     assert_eq!(config.content, put_request.content);
+    assert_eq!(config.content_type.as_deref(), Some("application/json"));
     assert_eq!(
         config.schema,
         put_request
@@ -113,13 +125,15 @@ async fn test_put_and_get_config() -> anyhow::Result<()> {
 
 #[tokio::test]
 async fn test_update_config_with_optimistic_locking() -> anyhow::Result<()> {
-    let (app, _dir) = create_test_app()?;
+    let app = create_test_app()?;
 
     // Create initial version
     let put_request = PutConfigRequest {
         content: serde_json::json!({"version": 1}),
         schema: Some(serde_json::json!({"type": "object"})),
         expected_version: None,
+        content_type: None,
+        change_message: None,
     };
 
     app.clone()
@@ -137,6 +151,8 @@ async fn test_update_config_with_optimistic_locking() -> anyhow::Result<()> {
         content: serde_json::json!({"version": 2}),
         schema: None, // Use previous schema
         expected_version: Some("v1".to_string()),
+        content_type: None,
+        change_message: None,
     };
 
     let response = app
@@ -157,6 +173,8 @@ async fn test_update_config_with_optimistic_locking() -> anyhow::Result<()> {
         content: serde_json::json!({"version": 3}),
         schema: None,
         expected_version: Some("v1".to_string()), // Wrong version
+        content_type: None,
+        change_message: None,
     };
 
     let response = app
@@ -169,19 +187,24 @@ async fn test_update_config_with_optimistic_locking() -> anyhow::Result<()> {
         )
         .await?;
 
-    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let error: ErrorResponse = serde_json::from_slice(&body)?;
+    assert_eq!(error.code.as_deref(), Some(VERSION_CONFLICT));
     Ok(())
 }
 
 #[tokio::test]
 async fn test_schema_required_for_first_version() -> anyhow::Result<()> {
-    let (app, _dir) = create_test_app()?;
+    let app = create_test_app()?;
 
     // Try to create without schema
     let put_request = PutConfigRequest {
         content: serde_json::json!({"test": true}),
         schema: None,
         expected_version: None,
+        content_type: None,
+        change_message: None,
     };
 
     let response = app
@@ -200,7 +223,7 @@ async fn test_schema_required_for_first_version() -> anyhow::Result<()> {
 
 #[tokio::test]
 async fn test_list_versions() -> anyhow::Result<()> {
-    let (app, _dir) = create_test_app()?;
+    let app = create_test_app()?;
 
     // Create multiple versions
     for i in 1..=3 {
@@ -216,6 +239,8 @@ async fn test_list_versions() -> anyhow::Result<()> {
             } else {
                 Some(format!("v{}", i - 1))
             },
+            content_type: None,
+            change_message: None,
         };
 
         app.clone()
@@ -252,7 +277,7 @@ async fn test_list_versions() -> anyhow::Result<()> {
 
 #[tokio::test]
 async fn test_get_specific_version() -> anyhow::Result<()> {
-    let (app, _dir) = create_test_app()?;
+    let app = create_test_app()?;
 
     // Create two versions
     let v1_content = serde_json::json!({"feature": "a"});
@@ -262,6 +287,8 @@ async fn test_get_specific_version() -> anyhow::Result<()> {
         content: v1_content.clone(),
         schema: Some(serde_json::json!({"type": "object"})),
         expected_version: None,
+        content_type: None,
+        change_message: None,
     };
 
     app.clone()
@@ -278,6 +305,8 @@ async fn test_get_specific_version() -> anyhow::Result<()> {
         content: v2_content.clone(),
         schema: None,
         expected_version: Some("v1".to_string()),
+        content_type: None,
+        change_message: None,
     };
 
     app.clone()
@@ -325,13 +354,15 @@ async fn test_get_specific_version() -> anyhow::Result<()> {
 
 #[tokio::test]
 async fn test_delete_environment() -> anyhow::Result<()> {
-    let (app, _dir) = create_test_app()?;
+    let app = create_test_app()?;
 
     // Create some configs in an environment
     let put_request = PutConfigRequest {
         content: serde_json::json!({"temporary": true}),
         schema: Some(serde_json::json!({"type": "object"})),
         expected_version: None,
+        content_type: None,
+        change_message: None,
     };
 
     // Create multiple configs
@@ -373,9 +404,72 @@ async fn test_delete_environment() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_delete_config_is_idempotent() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    for n in 1..=2 {
+        let data = ConfigData {
+            content: serde_json::json!({"n": n}),
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        storage.put(&key, &data, (n > 1).then_some("v1")).await?;
+    }
+
+    let delete = || {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("DELETE")
+                        .uri("/configs/myapp/dev/flags")
+                        .body(Body::empty())?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok(serde_json::from_slice::<DeleteConfigResponse>(&body)?)
+        }
+    };
+
+    let first = delete().await?;
+    assert!(first.existed);
+    assert_eq!(first.deleted_versions, 2);
+    assert!(!storage.exists(&key).await?);
+
+    // A retry finds nothing left and still succeeds
+    let retry = delete().await?;
+    assert!(!retry.existed);
+    assert_eq!(retry.deleted_versions, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delete_empty_environment_succeeds() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/configs/app/never-created")
+                .body(Body::empty())?,
+        )
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let success: SuccessResponse = serde_json::from_slice(&body)?;
+    assert!(success.message.starts_with("Deleted 0 "));
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_get_nonexistent_config() -> anyhow::Result<()> {
-    let (app, _dir) = create_test_app()?;
+    let app = create_test_app()?;
 
     let response = app
         .oneshot(
@@ -388,3 +482,3171 @@ async fn test_get_nonexistent_config() -> anyhow::Result<()> {
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
     Ok(())
 }
+
+/// Writer that collects formatted log output so tests can assert on it
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl LogBuffer {
+    fn contents(&self) -> anyhow::Result<String> {
+        let bytes = self
+            .0
+            .lock()
+            .map_err(|e| anyhow::anyhow!("log buffer poisoned: {e}"))?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+#[tokio::test]
+async fn test_validation_failure_is_logged() -> anyhow::Result<()> {
+    let logs = LogBuffer::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = create_test_app()?;
+
+    let put_request = PutConfigRequest {
+        content: serde_json::json!({"password": "hunter2"}),
+        schema: Some(serde_json::json!({
+            "type": "object",
+            "properties": {"password": {"type": "integer"}}
+        })),
+        expected_version: None,
+        content_type: None,
+        change_message: None,
+    };
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/configs/app/dev/secrets")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&put_request)?))?,
+        )
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let output = logs.contents()?;
+    assert!(output.contains("WARN"));
+    assert!(output.contains("Content validation failed"));
+    assert!(output.contains("config=app/dev/secrets"));
+    assert!(output.contains("schema_path=/properties/password/type"));
+    assert!(output.contains("instance_path=/password"));
+    assert!(!output.contains("hunter2"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lineage_walks_copy_chain() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+
+    let put_request = PutConfigRequest {
+        content: serde_json::json!({"origin": "a"}),
+        schema: Some(serde_json::json!({"type": "object"})),
+        expected_version: None,
+        content_type: None,
+        change_message: None,
+    };
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/configs/app/dev/a")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&put_request)?))?,
+        )
+        .await?;
+
+    // Copied to another config, then promoted onwards from there
+    let a = shared_types::ConfigKey::new("app", "dev", "a");
+    let b = shared_types::ConfigKey::new("app", "staging", "b");
+    let steps = [
+        (
+            "/configs/app/dev/a/copy",
+            serde_json::json!({
+                "to": {"application": "app", "environment": "staging", "config_name": "b"}
+            }),
+        ),
+        (
+            "/configs/app/staging/b/promote",
+            serde_json::json!({"to_environment": "prod"}),
+        ),
+    ];
+    for (uri, body) in steps {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+    }
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/configs/app/prod/b/lineage")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let lineage: LineageResponse = serde_json::from_slice(&body)?;
+    assert_eq!(lineage.lineage.len(), 2);
+    assert_eq!(lineage.lineage[0].key, b);
+    assert_eq!(lineage.lineage[0].version, "v1");
+    assert_eq!(lineage.lineage[1].key, a);
+
+    // A config that was created directly has no lineage
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/configs/app/dev/a/lineage")
+                .body(Body::empty())?,
+        )
+        .await?;
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let lineage: LineageResponse = serde_json::from_slice(&body)?;
+    assert!(lineage.lineage.is_empty());
+    Ok(())
+}
+
+/// In-memory object store whose reads stall for `delay`
+#[derive(Debug)]
+struct SlowStore {
+    inner: object_store::memory::InMemory,
+    delay: std::time::Duration,
+}
+
+impl std::fmt::Display for SlowStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SlowStore({:?})", self.delay)
+    }
+}
+
+#[async_trait::async_trait]
+impl object_store::ObjectStore for SlowStore {
+    async fn put_opts(
+        &self,
+        location: &object_store::path::Path,
+        payload: object_store::PutPayload,
+        opts: object_store::PutOptions,
+    ) -> object_store::Result<object_store::PutResult> {
+        self.inner.put_opts(location, payload, opts).await
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        location: &object_store::path::Path,
+        opts: object_store::PutMultipartOpts,
+    ) -> object_store::Result<Box<dyn object_store::MultipartUpload>> {
+        self.inner.put_multipart_opts(location, opts).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &object_store::path::Path,
+        options: object_store::GetOptions,
+    ) -> object_store::Result<object_store::GetResult> {
+        tokio::time::sleep(self.delay).await;
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn delete(&self, location: &object_store::path::Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+
+    fn list(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+    ) -> futures::stream::BoxStream<'_, object_store::Result<object_store::ObjectMeta>> {
+        self.inner.list(prefix)
+    }
+
+    async fn list_with_delimiter(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+    ) -> object_store::Result<object_store::ListResult> {
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+#[tokio::test]
+async fn test_slow_storage_operation_is_logged() -> anyhow::Result<()> {
+    let logs = LogBuffer::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let store = SlowStore {
+        inner: object_store::memory::InMemory::new(),
+        delay: std::time::Duration::from_millis(50),
+    };
+    let storage = ObjectStoreBackend::new(Arc::new(store))
+        .with_slow_op_threshold(std::time::Duration::from_millis(10));
+
+    let result = storage.get(&ConfigKey::new("myapp", "dev", "flags")).await;
+    assert!(result.is_err());
+
+    let output = logs.contents()?;
+    assert!(output.contains("WARN"));
+    assert!(output.contains("Slow storage operation"));
+    assert!(output.contains("operation=\"get\""));
+    assert!(output.contains("myapp/dev/flags/metadata.json"));
+    assert!(output.contains("elapsed_ms="));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_slow_storage_returns_gateway_timeout() -> anyhow::Result<()> {
+    let store = SlowStore {
+        inner: object_store::memory::InMemory::new(),
+        delay: std::time::Duration::from_secs(5),
+    };
+    let storage = ObjectStoreBackend::new(Arc::new(store))
+        .with_op_timeout(std::time::Duration::from_millis(50));
+    let state = Arc::new(AppState::new(Arc::new(storage)));
+    let app = Router::new()
+        .route("/configs/:app/:env/:config", get(handlers::get_config))
+        .with_state(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/configs/app/dev/slow")
+                .body(Body::empty())?,
+        )
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let error: ErrorResponse = serde_json::from_slice(&body)?;
+    assert_eq!(error.error, "Gateway Timeout");
+    assert!(
+        error
+            .details
+            .is_some_and(|details| details.contains("Storage timeout"))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_timeline() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+
+    let contents = [
+        serde_json::json!({"replicas": 1}),
+        serde_json::json!({"replicas": 2}),
+        serde_json::json!({"replicas": 2, "region": "eu"}),
+    ];
+    for (i, content) in contents.iter().enumerate() {
+        let put_request = PutConfigRequest {
+            content: content.clone(),
+            schema: (i == 0).then(|| serde_json::json!({"type": "object"})),
+            expected_version: (i > 0).then(|| format!("v{i}")),
+            content_type: None,
+            change_message: None,
+        };
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/configs/app/dev/timeline")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&put_request)?))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/configs/app/dev/timeline/timeline")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let timeline: TimelineResponse = serde_json::from_slice(&body)?;
+
+    assert!(!timeline.truncated);
+    assert_eq!(timeline.steps.len(), 3);
+    assert_eq!(timeline.steps[0].version, "v1");
+    assert!(timeline.steps[0].diff.is_none());
+    assert_eq!(
+        timeline.steps[1].diff,
+        Some(vec![shared_types::PatchOperation::Replace {
+            path: "/replicas".to_string(),
+            value: serde_json::json!(2)
+        }])
+    );
+    assert_eq!(
+        timeline.steps[2].diff,
+        Some(vec![shared_types::PatchOperation::Add {
+            path: "/region".to_string(),
+            value: serde_json::json!("eu")
+        }])
+    );
+    assert_eq!(timeline.steps[2].content, contents[2]);
+    Ok(())
+}
+
+async fn put_first_version(app: &Router, uri: &str) -> anyhow::Result<StatusCode> {
+    let request = PutConfigRequest {
+        content: serde_json::json!({"enabled": true}),
+        schema: Some(serde_json::json!({"type": "object"})),
+        expected_version: None,
+        content_type: None,
+        change_message: None,
+    };
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&request)?))?,
+        )
+        .await?;
+    Ok(response.status())
+}
+
+#[tokio::test]
+async fn test_env_limit_compares_environments_as_stored() -> anyhow::Result<()> {
+    let settings = ServerSettings {
+        max_envs_per_app: Some(1),
+        ..ServerSettings::default()
+    };
+    let storage = Arc::new(
+        ObjectStoreBackend::from_config(StorageConfig::memory())?.with_key_case(KeyCase::Lower),
+    );
+    let app = app_over(storage, settings);
+
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/prod/flags").await?,
+        StatusCode::OK
+    );
+    // Under lowercased keys Prod is the existing environment, not a second one
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/Prod/database").await?,
+        StatusCode::OK
+    );
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/flags").await?,
+        StatusCode::CONFLICT
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_envs_per_app_rejects_new_environment() -> anyhow::Result<()> {
+    let settings = ServerSettings {
+        max_envs_per_app: Some(2),
+        ..ServerSettings::default()
+    };
+    let (app, _storage) = create_test_app_with_settings(settings)?;
+
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/pr-1/flags").await?,
+        StatusCode::OK
+    );
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/pr-2/flags").await?,
+        StatusCode::OK
+    );
+
+    // A third environment is over the limit
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/pr-3/flags").await?,
+        StatusCode::CONFLICT
+    );
+
+    // Existing environments and other apps are unaffected
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/pr-1/database").await?,
+        StatusCode::OK
+    );
+    assert_eq!(
+        put_first_version(&app, "/configs/otherapp/pr-3/flags").await?,
+        StatusCode::OK
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/apps/myapp/usage")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let usage: AppUsageResponse = serde_json::from_slice(&body)?;
+    assert_eq!(usage.application, "myapp");
+    assert_eq!(usage.environment_count, 2);
+    assert_eq!(usage.config_count, 3);
+    assert_eq!(usage.max_environments, Some(2));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_promote_dry_run_reports_diff_and_errors() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+
+    let dev = ConfigKey::new("myapp", "dev", "api");
+    let prod = ConfigKey::new("myapp", "prod", "api");
+    storage
+        .put(
+            &dev,
+            &ConfigData {
+                content: serde_json::json!({"timeout": 30, "debug": true}),
+                schema: serde_json::json!({"type": "object"}),
+                version: String::new(),
+                content_type: None,
+            },
+            None,
+        )
+        .await?;
+    storage
+        .put(
+            &prod,
+            &ConfigData {
+                content: serde_json::json!({"timeout": 10}),
+                schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {"debug": {"const": false}}
+                }),
+                version: String::new(),
+                content_type: None,
+            },
+            None,
+        )
+        .await?;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/configs/myapp/dev/api/promote?dry_run=true")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"to_environment": "prod"}"#))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let preview: PromotePreviewResponse = serde_json::from_slice(&body)?;
+    assert_eq!(
+        preview.diff,
+        vec![
+            PatchOperation::Replace {
+                path: "/timeout".to_string(),
+                value: serde_json::json!(30),
+            },
+            PatchOperation::Add {
+                path: "/debug".to_string(),
+                value: serde_json::json!(true),
+            },
+        ]
+    );
+    assert!(!preview.valid);
+    assert_eq!(preview.errors.len(), 1);
+    assert!(preview.errors[0].starts_with("/debug"));
+
+    // Nothing was written to the target
+    let versions = storage.list_versions(&prod).await?;
+    assert_eq!(versions.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_post_creates_distinct_configs() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+
+    let request = CreateConfigRequest {
+        content: serde_json::json!({"variant": "a"}),
+        schema: serde_json::json!({"type": "object"}),
+        content_type: None,
+    };
+
+    let mut created = Vec::new();
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/configs/myapp/experiments")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request)?))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+        let response: CreateConfigResponse = serde_json::from_slice(&body)?;
+        assert_eq!(response.application, "myapp");
+        assert_eq!(response.environment, "experiments");
+        assert_eq!(response.version, "v1");
+        created.push(response.config_name);
+    }
+
+    assert_ne!(created[0], created[1]);
+    let keys = storage.list(Some("myapp")).await?;
+    assert_eq!(keys.len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_denied_app_is_forbidden() -> anyhow::Result<()> {
+    let settings = ServerSettings {
+        allowed_apps: Some(["billing".to_string(), "search".to_string()].into()),
+        denied_apps: ["search".to_string()].into(),
+        ..ServerSettings::default()
+    };
+    let (app, _storage) = create_test_app_with_settings(settings)?;
+
+    // Writes and reads to an allowed app work
+    assert_eq!(
+        put_first_version(&app, "/configs/billing/dev/rates").await?,
+        StatusCode::OK
+    );
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/configs/billing/dev/rates")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Denied and unlisted apps are refused for writes and reads alike
+    for denied in ["search", "other"] {
+        assert_eq!(
+            put_first_version(&app, &format!("/configs/{denied}/dev/rates")).await?,
+            StatusCode::FORBIDDEN
+        );
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/configs/{denied}/dev/rates"))
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_denied_app_is_forbidden_in_any_case() -> anyhow::Result<()> {
+    let settings = ServerSettings {
+        denied_apps: ["secret".to_string()].into(),
+        ..ServerSettings::default()
+    };
+    let storage = Arc::new(
+        ObjectStoreBackend::from_config(StorageConfig::memory())?.with_key_case(KeyCase::Lower),
+    );
+    let app = app_over(storage.clone(), settings);
+    let data = ConfigData {
+        content: serde_json::json!({"password": "hunter2"}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    storage
+        .put(&ConfigKey::new("secret", "prod", "db"), &data, None)
+        .await?;
+
+    // Under lowercased keys SECRET is the same app as secret
+    for uri in ["/configs/SECRET/prod/db", "/configs/Secret/prod/db"] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{uri}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_alias_into_denied_app_is_forbidden() -> anyhow::Result<()> {
+    let settings = ServerSettings {
+        denied_apps: ["secret".to_string()].into(),
+        ..ServerSettings::default()
+    };
+    let (app, storage) = create_test_app_with_settings(settings)?;
+    let hidden = ConfigKey::new("secret", "prod", "db");
+    let data = ConfigData {
+        content: serde_json::json!({"password": "hunter2"}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    storage.put(&hidden, &data, None).await?;
+    // Set up before the app was denied, so never checked over HTTP
+    storage
+        .set_alias(&ConfigKey::new("public", "prod", "db"), &hidden)
+        .await?;
+
+    for uri in [
+        "/configs/public/prod/db",
+        "/configs/public/prod/db/versions",
+        "/configs/public/prod/db/watch",
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{uri}");
+    }
+
+    let keys = serde_json::json!({"keys": [
+        {"application": "public", "environment": "prod", "config_name": "db"}
+    ]});
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/configs/snapshot")
+                .header("content-type", "application/json")
+                .body(Body::from(keys.to_string()))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/configs/batch")
+                .header("content-type", "application/json")
+                .body(Body::from(keys.to_string()))?,
+        )
+        .await?;
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let batch: BatchGetResponse = serde_json::from_slice(&body)?;
+    match &batch.configs["public/prod/db"] {
+        BatchGetResult::Error(e) => assert_eq!(e.error, "Forbidden"),
+        BatchGetResult::Config(_) => anyhow::bail!("aliased secret was served"),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_inventory_reports_version_counts() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+
+    let data = ConfigData {
+        content: serde_json::json!({"n": 0}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    let counts = [
+        (ConfigKey::new("billing", "dev", "rates"), 3),
+        (ConfigKey::new("billing", "prod", "rates"), 1),
+        (ConfigKey::new("search", "dev", "index"), 2),
+    ];
+    for (key, count) in &counts {
+        storage.put(key, &data, None).await?;
+        for v in 1..*count {
+            storage.put(key, &data, Some(&format!("v{v}"))).await?;
+        }
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/admin/inventory")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let inventory: Vec<InventoryEntry> = serde_json::from_slice(&body)?;
+    assert_eq!(inventory.len(), counts.len());
+    for (key, count) in &counts {
+        let entry = inventory
+            .iter()
+            .find(|entry| &entry.key == key)
+            .ok_or_else(|| anyhow::anyhow!("{key} missing from inventory"))?;
+        assert_eq!(entry.metadata.versions.len(), *count);
+        assert_eq!(entry.metadata.current_version, format!("v{count}"));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_heavy_route_timeout_is_separate() -> anyhow::Result<()> {
+    let store = SlowStore {
+        inner: object_store::memory::InMemory::new(),
+        delay: std::time::Duration::from_millis(300),
+    };
+    let settings = ServerSettings {
+        request_timeout: std::time::Duration::from_secs(5),
+        heavy_request_timeout: std::time::Duration::from_millis(50),
+        ..ServerSettings::default()
+    };
+    let state = Arc::new(
+        AppState::new(Arc::new(ObjectStoreBackend::new(Arc::new(store)))).with_settings(settings),
+    );
+    let app = server::http::create_router(state);
+
+    // A heavy route gives up once its own, shorter limit expires
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/configs/app/dev/slow/timeline")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let error: ErrorResponse = serde_json::from_slice(&body)?;
+    assert_eq!(error.error, "Gateway Timeout");
+
+    // The same storage delay fits within the fast-route limit
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/configs/app/dev/slow")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_strict_schema_rejects_unknown_keywords() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+
+    let put_request = PutConfigRequest {
+        content: serde_json::json!({"port": "not a number"}),
+        schema: Some(serde_json::json!({
+            "type": "object",
+            "properties": {"port": {"tpye": "integer"}}
+        })),
+        expected_version: None,
+        content_type: None,
+        change_message: None,
+    };
+    let body = serde_json::to_string(&put_request)?;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/configs/app/dev/strict?strict_schema=true")
+                .header("content-type", "application/json")
+                .body(Body::from(body.clone()))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let bytes = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let error: ErrorResponse = serde_json::from_slice(&bytes)?;
+    assert!(error.details.unwrap_or_default().contains("tpye"));
+
+    // Loose mode keeps accepting the schema, typo and all
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/configs/app/dev/strict")
+                .header("content-type", "application/json")
+                .body(Body::from(body))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stats_count_reads() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/flags").await?,
+        StatusCode::OK
+    );
+
+    for uri in [
+        "/configs/myapp/dev/flags",
+        "/configs/myapp/dev/flags",
+        "/configs/myapp/dev/flags/versions/v1",
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    // Reads of several configs at once count too
+    let keys = serde_json::json!({"keys": [
+        {"application": "myapp", "environment": "dev", "config_name": "flags"}
+    ]});
+    for uri in ["/configs/snapshot", "/configs/batch"] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(keys.to_string()))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/configs/myapp/dev/flags/stats")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let stats: ConfigStatsResponse = serde_json::from_slice(&body)?;
+    assert_eq!(stats.read_count, Some(5));
+
+    // The same counter as the metrics report
+    let response = app
+        .oneshot(Request::builder().uri("/metrics").body(Body::empty())?)
+        .await?;
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let text = String::from_utf8(body.to_vec())?;
+    assert!(text.contains(r#"config_reads_total{app="myapp",env="dev",config="flags"} 5"#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_config_flattened() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    storage
+        .put(
+            &ConfigKey::new("myapp", "dev", "service"),
+            &ConfigData {
+                content: serde_json::json!({
+                    "database": {"host": "localhost", "port": 5432},
+                    "items": [{"name": "a"}, {"name": "b"}]
+                }),
+                schema: serde_json::json!({"type": "object"}),
+                version: String::new(),
+                content_type: None,
+            },
+            None,
+        )
+        .await?;
+
+    let get_content = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            let config: GetConfigResponse = serde_json::from_slice(&body)?;
+            anyhow::Ok(config.content)
+        }
+    };
+
+    assert_eq!(
+        get_content("/configs/myapp/dev/service?flatten=true").await?,
+        serde_json::json!({
+            "database.host": "localhost",
+            "database.port": 5432,
+            "items.0.name": "a",
+            "items.1.name": "b"
+        })
+    );
+    assert_eq!(
+        get_content("/configs/myapp/dev/service?flatten=true&delim=/").await?,
+        serde_json::json!({
+            "database/host": "localhost",
+            "database/port": 5432,
+            "items/0/name": "a",
+            "items/1/name": "b"
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_config_selected_fields() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    storage
+        .put(
+            &ConfigKey::new("myapp", "dev", "service"),
+            &ConfigData {
+                content: serde_json::json!({"port": 8080}),
+                schema: serde_json::json!({"type": "object"}),
+                version: String::new(),
+                content_type: None,
+            },
+            None,
+        )
+        .await?;
+
+    let get = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+                .await?;
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok((status, serde_json::from_slice::<serde_json::Value>(&body)?))
+        }
+    };
+
+    let (status, body) = get("/configs/myapp/dev/service?fields=content").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, serde_json::json!({"content": {"port": 8080}}));
+    assert!(body.get("schema").is_none());
+
+    let (status, body) = get("/configs/myapp/dev/service?fields=content,version").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["version"], "v1");
+    assert!(body.get("schema").is_none());
+
+    let (status, _) = get("/configs/myapp/dev/service?fields=content,bogus").await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_gc_reports_then_removes_orphans() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let storage = Arc::new(
+        ObjectStoreBackend::from_config(StorageConfig::local(dir.path()))?
+            .with_gc_grace_period(std::time::Duration::ZERO),
+    );
+    let app = app_over(storage.clone(), ServerSettings::default());
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    storage
+        .put(
+            &key,
+            &ConfigData {
+                content: serde_json::json!({"on": true}),
+                schema: serde_json::json!({"type": "object"}),
+                version: String::new(),
+                content_type: None,
+            },
+            None,
+        )
+        .await?;
+
+    // Simulate an interrupted write that never reached the metadata
+    let orphan_dir = dir.path().join("myapp/dev/flags/versions/v7");
+    std::fs::create_dir_all(&orphan_dir)?;
+    std::fs::write(orphan_dir.join("data.json"), b"{}")?;
+
+    let run_gc = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(uri)
+                        .body(Body::empty())?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok(serde_json::from_slice::<GcResponse>(&body)?)
+        }
+    };
+
+    // Dry run by default: reported but left in place
+    let report = run_gc("/admin/gc").await?;
+    assert_eq!(
+        report.orphans,
+        vec!["myapp/dev/flags/versions/v7/data.json"]
+    );
+    assert_eq!(report.removed, 0);
+    assert!(orphan_dir.join("data.json").exists());
+
+    let report = run_gc("/admin/gc?apply=true").await?;
+    assert_eq!(report.removed, 1);
+    assert!(!orphan_dir.join("data.json").exists());
+
+    // Referenced versions are untouched
+    assert_eq!(storage.get(&key).await?.version, "v1");
+    assert!(run_gc("/admin/gc").await?.orphans.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_gc_leaves_recent_objects_alone() -> anyhow::Result<()> {
+    let (_app, storage, dir) = create_disk_test_app(ServerSettings::default())?;
+
+    // Written moments ago, as by a put that has yet to write its metadata
+    let in_flight = dir.path().join("myapp/dev/flags/versions/v1");
+    std::fs::create_dir_all(&in_flight)?;
+    std::fs::write(in_flight.join("data.json"), b"{}")?;
+
+    assert!(storage.collect_garbage(true).await?.is_empty());
+    assert!(in_flight.join("data.json").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_staged_version_is_served_only_after_activation() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/flags").await?,
+        StatusCode::OK
+    );
+
+    let get_current = || {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri("/configs/myapp/dev/flags")
+                        .body(Body::empty())?,
+                )
+                .await?;
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok(serde_json::from_slice::<GetConfigResponse>(&body)?)
+        }
+    };
+
+    // Stage v2 without making it current
+    let staged = PutConfigRequest {
+        content: serde_json::json!({"enabled": false}),
+        schema: None,
+        expected_version: Some("v1".to_string()),
+        content_type: None,
+        change_message: Some("Turn it off for the canary".to_string()),
+    };
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/configs/myapp/dev/flags?activate=false")
+                .header("content-type", "application/json")
+                .header("x-author", "ops")
+                .body(Body::from(serde_json::to_string(&staged)?))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let success: SuccessResponse = serde_json::from_slice(&body)?;
+    assert_eq!(success.version.as_deref(), Some("v2"));
+
+    let current = get_current().await?;
+    assert_eq!(current.version, "v1");
+    assert_eq!(current.content, serde_json::json!({"enabled": true}));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/configs/myapp/dev/flags/versions")
+                .body(Body::empty())?,
+        )
+        .await?;
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let versions: ListVersionsResponse = serde_json::from_slice(&body)?;
+    assert_eq!(versions.versions.len(), 2);
+    let staged = &versions.versions[1];
+    assert_eq!(
+        staged.change_message.as_deref(),
+        Some("Turn it off for the canary")
+    );
+    assert_eq!(staged.author.as_deref(), Some("ops"));
+
+    // Activate it
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/configs/myapp/dev/flags/activate/v2")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let current = get_current().await?;
+    assert_eq!(current.version, "v2");
+    assert_eq!(current.content, serde_json::json!({"enabled": false}));
+
+    // Unknown versions cannot be activated
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/configs/myapp/dev/flags/activate/v9")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_alias_reads_through_and_refuses_writes() -> anyhow::Result<()> {
+    let (app, _storage) = create_test_app_with_settings(ServerSettings::default())?;
+
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/prod/flags").await?,
+        StatusCode::OK
+    );
+
+    let set_alias = |uri: &'static str, target: ConfigKey| {
+        let app = app.clone();
+        async move {
+            let body = serde_json::json!({ "target": target });
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("PUT")
+                        .uri(uri)
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))?,
+                )
+                .await?;
+            anyhow::Ok(response.status())
+        }
+    };
+
+    assert_eq!(
+        set_alias(
+            "/configs/myapp/prod/current/alias",
+            ConfigKey::new("myapp", "prod", "flags")
+        )
+        .await?,
+        StatusCode::OK
+    );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/configs/myapp/prod/current")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let config: GetConfigResponse = serde_json::from_slice(&body)?;
+    assert_eq!(config.config_name, "flags");
+    assert_eq!(config.content, serde_json::json!({"enabled": true}));
+
+    // Writes must go to the target
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/prod/current").await?,
+        StatusCode::CONFLICT
+    );
+
+    // A config with versions of its own cannot become an alias
+    assert_eq!(
+        set_alias(
+            "/configs/myapp/prod/flags/alias",
+            ConfigKey::new("myapp", "prod", "other")
+        )
+        .await?,
+        StatusCode::CONFLICT
+    );
+
+    // Closing a loop is rejected
+    assert_eq!(
+        set_alias(
+            "/configs/myapp/prod/a/alias",
+            ConfigKey::new("myapp", "prod", "current")
+        )
+        .await?,
+        StatusCode::OK
+    );
+    assert_eq!(
+        set_alias(
+            "/configs/myapp/prod/b/alias",
+            ConfigKey::new("myapp", "prod", "a")
+        )
+        .await?,
+        StatusCode::OK
+    );
+    assert_eq!(
+        set_alias(
+            "/configs/myapp/prod/a/alias",
+            ConfigKey::new("myapp", "prod", "b")
+        )
+        .await?,
+        StatusCode::BAD_REQUEST
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_detailed_list_includes_current_versions() -> anyhow::Result<()> {
+    let (app, _storage) = create_test_app_with_settings(ServerSettings::default())?;
+
+    for uri in [
+        "/configs/myapp/dev/flags",
+        "/configs/myapp/prod/flags",
+        "/configs/other/dev/limits",
+    ] {
+        assert_eq!(put_first_version(&app, uri).await?, StatusCode::OK);
+    }
+
+    let list = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            let mut list: ListConfigsResponse = serde_json::from_slice(&body)?;
+            list.configs.sort_by_key(|c| c.key.to_path());
+            anyhow::Ok(list.configs)
+        }
+    };
+
+    let plain = list("/configs?prefix=myapp").await?;
+    assert_eq!(plain.len(), 2);
+    assert!(plain.iter().all(|c| c.current_version.is_none()));
+
+    let detailed = list("/configs?prefix=myapp&detailed=true").await?;
+    let versions: Vec<_> = detailed
+        .iter()
+        .map(|c| (c.key.to_path(), c.current_version.as_deref()))
+        .collect();
+    assert_eq!(
+        versions,
+        vec![
+            ("myapp/dev/flags".to_string(), Some("v1")),
+            ("myapp/prod/flags".to_string(), Some("v1")),
+        ]
+    );
+    assert!(detailed.iter().all(|c| c.updated_at.is_some()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_identical_put_reports_no_change() -> anyhow::Result<()> {
+    let (app, _storage) = create_test_app_with_settings(ServerSettings::default())?;
+
+    let put = |content: &'static str, expected: Option<&'static str>, uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let request = PutConfigRequest {
+                content: serde_json::from_str(content)?,
+                schema: Some(serde_json::json!({"type": "object"})),
+                expected_version: expected.map(str::to_string),
+                content_type: None,
+                change_message: None,
+            };
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("PUT")
+                        .uri(uri)
+                        .header("content-type", "application/json")
+                        .body(Body::from(serde_json::to_string(&request)?))?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok(serde_json::from_slice::<SuccessResponse>(&body)?)
+        }
+    };
+
+    let first = put(r#"{"a": 1, "b": 2}"#, None, "/configs/myapp/dev/flags").await?;
+    assert_eq!(first.code, None);
+
+    // Same content with keys in a different order
+    let second = put(
+        r#"{"b": 2, "a": 1}"#,
+        Some("v1"),
+        "/configs/myapp/dev/flags",
+    )
+    .await?;
+    assert_eq!(second.code.as_deref(), Some(NO_CHANGE));
+    assert_eq!(second.version.as_deref(), Some("v1"));
+
+    let versions = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/configs/myapp/dev/flags/versions")
+                .body(Body::empty())?,
+        )
+        .await?;
+    let body = axum::body::to_bytes(versions.into_body(), 1024 * 1024).await?;
+    let versions: ListVersionsResponse = serde_json::from_slice(&body)?;
+    assert_eq!(versions.versions.len(), 1);
+
+    // touch forces a new version anyway
+    let touched = put(
+        r#"{"a": 1, "b": 2}"#,
+        Some("v1"),
+        "/configs/myapp/dev/flags?touch=true",
+    )
+    .await?;
+    assert_eq!(touched.code, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_etag_answers_if_none_match() -> anyhow::Result<()> {
+    let (app, _storage) = create_test_app_with_settings(ServerSettings::default())?;
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/flags").await?,
+        StatusCode::OK
+    );
+
+    let list = |if_none_match: Option<String>| {
+        let app = app.clone();
+        async move {
+            let mut request = Request::builder().uri("/configs?detailed=true");
+            if let Some(etag) = if_none_match {
+                request = request.header("if-none-match", etag);
+            }
+            let response = app.oneshot(request.body(Body::empty())?).await?;
+            let etag = response
+                .headers()
+                .get("etag")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            anyhow::Ok((response.status(), etag))
+        }
+    };
+
+    let (status, etag) = list(None).await?;
+    assert_eq!(status, StatusCode::OK);
+    let etag = etag.ok_or_else(|| anyhow::anyhow!("listing has no ETag"))?;
+    assert!(etag.starts_with("W/\""));
+
+    let (status, _) = list(Some(etag.clone())).await?;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/limits").await?,
+        StatusCode::OK
+    );
+    let (status, new_etag) = list(Some(etag.clone())).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(new_etag, Some(etag));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_openapi_fragment_embeds_schema() -> anyhow::Result<()> {
+    let (app, _storage) = create_test_app_with_settings(ServerSettings::default())?;
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/flags").await?,
+        StatusCode::OK
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/configs/myapp/dev/flags/openapi")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let doc: serde_json::Value = serde_json::from_slice(&body)?;
+
+    assert!(doc["openapi"].as_str().is_some_and(|v| v.starts_with("3.")));
+    assert_eq!(
+        doc.pointer("/components/schemas/flags"),
+        Some(&serde_json::json!({"type": "object"}))
+    );
+    Ok(())
+}
+
+/// An app serving myapp/dev/flags with two versions, the current of which
+/// has unreadable data
+async fn create_app_with_corrupt_current(
+    settings: ServerSettings,
+) -> anyhow::Result<(Router, TempDir)> {
+    let (app, storage, dir) = create_disk_test_app(settings)?;
+
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    for (content, expected) in [
+        (serde_json::json!({"enabled": true}), None),
+        (serde_json::json!({"enabled": false}), Some("v1")),
+    ] {
+        let data = ConfigData {
+            content,
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        storage.put(&key, &data, expected).await?;
+    }
+
+    std::fs::write(
+        dir.path().join("myapp/dev/flags/versions/v2/data.json"),
+        b"{ not json",
+    )?;
+    Ok((app, dir))
+}
+
+#[tokio::test]
+async fn test_corrupt_current_version_falls_back_when_enabled() -> anyhow::Result<()> {
+    let settings = ServerSettings {
+        fallback_on_corrupt: true,
+        ..ServerSettings::default()
+    };
+    let (app, _dir) = create_app_with_corrupt_current(settings).await?;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/configs/myapp/dev/flags")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get(handlers::DEGRADED_HEADER)
+            .and_then(|v| v.to_str().ok()),
+        Some("true")
+    );
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let config: GetConfigResponse = serde_json::from_slice(&body)?;
+    assert_eq!(config.version, "v1");
+    assert_eq!(config.content, serde_json::json!({"enabled": true}));
+
+    // Off by default: the read fails
+    let (app, _dir) = create_app_with_corrupt_current(ServerSettings::default()).await?;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/configs/myapp/dev/flags")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_environment_from_template() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_settings(ServerSettings::default())?;
+
+    let templates = [
+        (
+            "database",
+            serde_json::json!({"host": "db.${env}.internal", "name": "${app}_${env}"}),
+        ),
+        (
+            "queues",
+            serde_json::json!({"names": ["jobs-${env}", "mail-${env}"], "workers": 2}),
+        ),
+    ];
+    for (name, content) in templates {
+        let data = ConfigData {
+            content,
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        storage
+            .put(&ConfigKey::new("myapp", "template", name), &data, None)
+            .await?;
+    }
+    // Already exists in the new environment, so it is left alone
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/staging/queues").await?,
+        StatusCode::OK
+    );
+
+    let instantiate = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(uri)
+                        .header("content-type", "application/json")
+                        .body(Body::from(r#"{"template_env": "template"}"#))?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok(serde_json::from_slice::<FromTemplateResponse>(&body)?)
+        }
+    };
+
+    let result = instantiate("/configs/myapp/staging/from-template").await?;
+    assert_eq!(result.created, vec!["database"]);
+    assert_eq!(result.skipped, vec!["queues"]);
+
+    let database = storage
+        .get(&ConfigKey::new("myapp", "staging", "database"))
+        .await?;
+    assert_eq!(database.version, "v1");
+    assert_eq!(
+        database.content,
+        serde_json::json!({"host": "db.staging.internal", "name": "myapp_staging"})
+    );
+
+    let result = instantiate("/configs/myapp/staging/from-template?overwrite=true").await?;
+    assert_eq!(result.created, vec!["database", "queues"]);
+    let queues = storage
+        .get(&ConfigKey::new("myapp", "staging", "queues"))
+        .await?;
+    assert_eq!(
+        queues.content,
+        serde_json::json!({"names": ["jobs-staging", "mail-staging"], "workers": 2})
+    );
+    assert_eq!(
+        storage
+            .lineage(&ConfigKey::new("myapp", "staging", "queues"))
+            .await?,
+        [ConfigOrigin {
+            key: ConfigKey::new("myapp", "template", "queues"),
+            version: "v1".to_string()
+        }]
+    );
+
+    // Written like any other config, so visible in the metrics
+    let response = app
+        .oneshot(Request::builder().uri("/metrics").body(Body::empty())?)
+        .await?;
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let text = String::from_utf8(body.to_vec())?;
+    for series in [
+        r#"config_writes_total{app="myapp",env="staging",config="database"} 2"#,
+        r#"config_version{app="myapp",env="staging",config="queues"} 2"#,
+    ] {
+        assert!(text.contains(series), "missing {series} in:\n{text}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_snapshot_returns_current_versions() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_settings(ServerSettings::default())?;
+
+    let flags = ConfigKey::new("myapp", "dev", "flags");
+    let limits = ConfigKey::new("myapp", "dev", "limits");
+    for (key, versions) in [(&flags, 2), (&limits, 1)] {
+        for n in 1..=versions {
+            let data = ConfigData {
+                content: serde_json::json!({ "n": n }),
+                schema: serde_json::json!({"type": "object"}),
+                version: String::new(),
+                content_type: None,
+            };
+            let expected = (n > 1).then(|| format!("v{}", n - 1));
+            storage.put(key, &data, expected.as_deref()).await?;
+        }
+    }
+
+    let body = serde_json::json!({ "keys": [&limits, &flags] });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/configs/snapshot")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let snapshot: SnapshotResponse = serde_json::from_slice(&body)?;
+
+    assert!(!snapshot.snapshot_id.is_empty());
+    let read: Vec<_> = snapshot
+        .configs
+        .iter()
+        .map(|c| {
+            (
+                c.config_name.as_str(),
+                c.version.as_str(),
+                c.content.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        read,
+        vec![
+            ("limits", "v1", serde_json::json!({"n": 1})),
+            ("flags", "v2", serde_json::json!({"n": 2})),
+        ]
+    );
+
+    // A missing key fails the whole snapshot
+    let body = serde_json::json!({ "keys": [&flags, ConfigKey::new("myapp", "dev", "nope")] });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/configs/snapshot")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_put_returns_representation_on_request() -> anyhow::Result<()> {
+    let (app, _storage) = create_test_app_with_settings(ServerSettings::default())?;
+
+    let put = |uri: &'static str, prefer: Option<&'static str>, expected: Option<&'static str>| {
+        let app = app.clone();
+        async move {
+            let request = PutConfigRequest {
+                content: serde_json::json!({"enabled": expected.is_some()}),
+                schema: Some(serde_json::json!({"type": "object"})),
+                expected_version: expected.map(str::to_string),
+                content_type: None,
+                change_message: None,
+            };
+            let mut builder = Request::builder()
+                .method("PUT")
+                .uri(uri)
+                .header("content-type", "application/json");
+            if let Some(prefer) = prefer {
+                builder = builder.header("prefer", prefer);
+            }
+            let response = app
+                .oneshot(builder.body(Body::from(serde_json::to_string(&request)?))?)
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok(serde_json::from_slice::<serde_json::Value>(&body)?)
+        }
+    };
+    let get_current = || {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri("/configs/myapp/dev/flags")
+                        .body(Body::empty())?,
+                )
+                .await?;
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok(serde_json::from_slice::<serde_json::Value>(&body)?)
+        }
+    };
+
+    // Default stays minimal
+    let minimal = put("/configs/myapp/dev/flags", None, None).await?;
+    assert!(minimal.get("content").is_none());
+    assert_eq!(minimal["version"], "v1");
+
+    let stored = put(
+        "/configs/myapp/dev/flags?return=representation",
+        None,
+        Some("v1"),
+    )
+    .await?;
+    assert_eq!(stored["version"], "v2");
+    assert_eq!(stored, get_current().await?);
+
+    let stored = put(
+        "/configs/myapp/dev/flags?touch=true",
+        Some("respond-async, return=representation"),
+        Some("v2"),
+    )
+    .await?;
+    assert_eq!(stored["version"], "v3");
+    assert_eq!(stored, get_current().await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_env_name_allowlist_checked_on_creation() -> anyhow::Result<()> {
+    let settings = ServerSettings {
+        allowed_envs: Some(["dev", "prod"].map(String::from).into()),
+        ..ServerSettings::default()
+    };
+    let (app, _storage) = create_test_app_with_settings(settings)?;
+
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/prod/flags").await?,
+        StatusCode::OK
+    );
+
+    let request = PutConfigRequest {
+        content: serde_json::json!({"enabled": true}),
+        schema: Some(serde_json::json!({"type": "object"})),
+        expected_version: None,
+        content_type: None,
+        change_message: None,
+    };
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/configs/myapp/prd/flags")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&request)?))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let error: ErrorResponse = serde_json::from_slice(&body)?;
+    assert!(
+        error
+            .details
+            .is_some_and(|details| details.contains("allowed environments: dev, prod"))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_schema_size_and_depth_limits() -> anyhow::Result<()> {
+    let settings = ServerSettings {
+        max_schema_bytes: 512,
+        max_schema_depth: 6,
+        ..ServerSettings::default()
+    };
+    let (app, _storage) = create_test_app_with_settings(settings)?;
+
+    let put_schema = |config: &'static str, schema: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let request = PutConfigRequest {
+                content: serde_json::json!({}),
+                schema: Some(schema),
+                expected_version: None,
+                content_type: None,
+                change_message: None,
+            };
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("PUT")
+                        .uri(format!("/configs/myapp/dev/{config}"))
+                        .header("content-type", "application/json")
+                        .body(Body::from(serde_json::to_string(&request)?))?,
+                )
+                .await?;
+            anyhow::Ok(response.status())
+        }
+    };
+
+    let normal = serde_json::json!({
+        "type": "object",
+        "properties": {"port": {"type": "integer", "minimum": 1}}
+    });
+    assert_eq!(put_schema("normal", normal).await?, StatusCode::OK);
+
+    let properties: serde_json::Map<_, _> = (0..100)
+        .map(|i| (format!("field{i}"), serde_json::json!({"type": "string"})))
+        .collect();
+    let oversized = serde_json::json!({"type": "object", "properties": properties});
+    assert_eq!(
+        put_schema("oversized", oversized).await?,
+        StatusCode::BAD_REQUEST
+    );
+
+    let mut deep = serde_json::json!({"type": "string"});
+    for _ in 0..4 {
+        deep = serde_json::json!({"type": "object", "properties": {"inner": deep}});
+    }
+    assert_eq!(put_schema("deep", deep).await?, StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_config_at_point_in_time() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "prod", "database");
+    for n in 1..=3 {
+        let data = ConfigData {
+            content: serde_json::json!({"pool": n}),
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        let expected = (n > 1).then(|| format!("v{}", n - 1));
+        storage.put(&key, &data, expected.as_deref()).await?;
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let versions = storage.list_versions(&key).await?;
+
+    let get_at = |at: chrono::DateTime<chrono::Utc>| {
+        let app = app.clone();
+        async move {
+            let uri = format!(
+                "/configs/myapp/prod/database?at={}",
+                at.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+            );
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+                .await?;
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok((status, serde_json::from_slice::<serde_json::Value>(&body)?))
+        }
+    };
+
+    // Between v2 and v3 being written, v2 was current
+    let between = versions[1].timestamp + (versions[2].timestamp - versions[1].timestamp) / 2;
+    let (status, body) = get_at(between).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["version"], "v2");
+    assert_eq!(body["content"], serde_json::json!({"pool": 2}));
+
+    let (_, body) = get_at(versions[0].timestamp).await?;
+    assert_eq!(body["version"], "v1");
+
+    let before = versions[0].timestamp - chrono::Duration::seconds(1);
+    let (status, _) = get_at(before).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rollback_writes_old_version_as_new_head() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "prod", "flags");
+    for n in 1..=3 {
+        let data = ConfigData {
+            content: serde_json::json!({"n": n}),
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        let expected = (n > 1).then(|| format!("v{}", n - 1));
+        storage.put(&key, &data, expected.as_deref()).await?;
+    }
+
+    let rollback = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .body(Body::empty())?,
+            )
+            .await
+            .map_err(anyhow::Error::from)
+        }
+    };
+
+    let response = rollback("/configs/myapp/prod/flags/rollback/v1").await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let body: SuccessResponse = serde_json::from_slice(&body)?;
+    assert_eq!(body.version.as_deref(), Some("v4"));
+
+    let current = storage.get(&key).await?;
+    assert_eq!(current.version, "v4");
+    assert_eq!(current.content["n"], 1);
+    assert_eq!(storage.list_versions(&key).await?.len(), 4);
+
+    let response = rollback("/configs/myapp/prod/flags/rollback/v9").await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(storage.get(&key).await?.version, "v4");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_diff_between_versions() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "prod", "db");
+    let contents = [
+        serde_json::json!({"host": "a", "pool": 5, "debug": true}),
+        serde_json::json!({"host": "b", "pool": 5, "tls": {"enabled": true}}),
+    ];
+    for (n, content) in contents.into_iter().enumerate() {
+        let data = ConfigData {
+            content,
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        let expected = (n > 0).then(|| format!("v{n}"));
+        storage.put(&key, &data, expected.as_deref()).await?;
+    }
+
+    let get = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+                .await?;
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok((status, body))
+        }
+    };
+
+    let (status, body) = get("/configs/myapp/prod/db/diff?from=v1&to=v2").await?;
+    assert_eq!(status, StatusCode::OK);
+    let diff: VersionDiffResponse = serde_json::from_slice(&body)?;
+    assert_eq!(diff.patch.len(), 3);
+    assert_eq!(diff.summary.added, ["tls"]);
+    assert_eq!(diff.summary.removed, ["debug"]);
+    assert_eq!(diff.summary.changed, ["host"]);
+
+    let (status, _) = get("/configs/myapp/prod/db/diff?from=v1").await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = get("/configs/myapp/prod/db/diff?from=v1&to=v7").await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch_get_reports_each_key() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "prod", "db");
+    let data = ConfigData {
+        content: serde_json::json!({"pool": 5}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    storage.put(&key, &data, None).await?;
+
+    let batch = |keys: Vec<ConfigKey>| {
+        let app = app.clone();
+        async move {
+            let body = serde_json::json!({ "keys": keys });
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/configs/batch")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))?,
+                )
+                .await?;
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok((status, body))
+        }
+    };
+
+    let missing = ConfigKey::new("myapp", "prod", "missing");
+    let (status, body) = batch(vec![key.clone(), missing.clone()]).await?;
+    assert_eq!(status, StatusCode::OK);
+    let response: BatchGetResponse = serde_json::from_slice(&body)?;
+    assert_eq!(response.configs.len(), 2);
+    match &response.configs[&key.to_path()] {
+        BatchGetResult::Config(config) => assert_eq!(config.content["pool"], 5),
+        BatchGetResult::Error(e) => anyhow::bail!("unexpected error: {e:?}"),
+    }
+    match &response.configs[&missing.to_path()] {
+        BatchGetResult::Error(e) => assert_eq!(e.error, "Not Found"),
+        BatchGetResult::Config(_) => anyhow::bail!("missing key was found"),
+    }
+
+    let (status, _) = batch(vec![key; 101]).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_merge_patch_updates_current_content() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "prod", "db");
+    let data = ConfigData {
+        content: serde_json::json!({"host": "a", "pool": {"min": 1, "max": 5}, "debug": true}),
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {"host": {"type": "string"}}
+        }),
+        version: String::new(),
+        content_type: None,
+    };
+    storage.put(&key, &data, None).await?;
+
+    let patch = |uri: &'static str, body: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("PATCH")
+                        .uri(uri)
+                        .header("content-type", "application/merge-patch+json")
+                        .body(Body::from(body.to_string()))?,
+                )
+                .await?;
+            anyhow::Ok(response.status())
+        }
+    };
+
+    let status = patch(
+        "/configs/myapp/prod/db",
+        serde_json::json!({"pool": {"max": 10}, "debug": null}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK);
+    let current = storage.get(&key).await?;
+    assert_eq!(current.version, "v2");
+    assert_eq!(
+        current.content,
+        serde_json::json!({"host": "a", "pool": {"min": 1, "max": 10}})
+    );
+    assert_eq!(current.schema, data.schema);
+
+    // The result must still satisfy the schema
+    let status = patch("/configs/myapp/prod/db", serde_json::json!({"host": 5})).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A stale expected version loses to the concurrent write
+    let status = patch(
+        "/configs/myapp/prod/db?expected_version=v1",
+        serde_json::json!({"host": "b"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(storage.get(&key).await?.version, "v2");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_merge_patch_shares_put_preconditions_and_history() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "prod", "db");
+    let data = ConfigData {
+        content: serde_json::json!({"host": "a"}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    storage.put(&key, &data, None).await?;
+
+    let patch = |uri: &'static str, if_match: Option<&'static str>, body: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let mut request = Request::builder()
+                .method("PATCH")
+                .uri(uri)
+                .header("content-type", "application/merge-patch+json")
+                .header("x-author", "ops");
+            if let Some(if_match) = if_match {
+                request = request.header("if-match", if_match);
+            }
+            let response = app
+                .oneshot(request.body(Body::from(body.to_string()))?)
+                .await?;
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok((status, body))
+        }
+    };
+
+    // A stale If-Match is a failed precondition, as on PUT
+    let (status, _) = patch(
+        "/configs/myapp/prod/db",
+        Some("\"v7\""),
+        serde_json::json!({"host": "b"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+    let (status, _) = patch(
+        "/configs/myapp/prod/db?change_message=Move%20to%20b",
+        Some("\"v1\""),
+        serde_json::json!({"host": "b"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK);
+    let versions = storage.list_versions(&key).await?;
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[1].change_message.as_deref(), Some("Move to b"));
+    assert_eq!(versions[1].author.as_deref(), Some("ops"));
+
+    // Patching in what is already there writes nothing
+    let (status, body) = patch(
+        "/configs/myapp/prod/db",
+        None,
+        serde_json::json!({"host": "b"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK);
+    let success: SuccessResponse = serde_json::from_slice(&body)?;
+    assert_eq!(success.code.as_deref(), Some(NO_CHANGE));
+    assert_eq!(success.version.as_deref(), Some("v2"));
+    assert_eq!(storage.list_versions(&key).await?.len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_etag_and_if_match_on_put() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "prod", "db");
+    let data = ConfigData {
+        content: serde_json::json!({"pool": 5}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    storage.put(&key, &data, None).await?;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/configs/myapp/prod/db")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["etag"], "\"v1\"");
+
+    let put = |if_match: &'static str, pool: u32| {
+        let app = app.clone();
+        async move {
+            let body = serde_json::json!({ "content": {"pool": pool} });
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("PUT")
+                        .uri("/configs/myapp/prod/db")
+                        .header("content-type", "application/json")
+                        .header("if-match", if_match)
+                        .body(Body::from(body.to_string()))?,
+                )
+                .await?;
+            anyhow::Ok(response.status())
+        }
+    };
+
+    assert_eq!(put("\"v1\"", 6).await?, StatusCode::OK);
+    assert_eq!(storage.get(&key).await?.version, "v2");
+
+    // A stale tag, or one naming no version at all, is a failed precondition
+    assert_eq!(put("\"v1\"", 7).await?, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(put("\"v9\"", 7).await?, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(storage.get(&key).await?.content["pool"], 6);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_config_answers_if_none_match() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "prod", "db");
+    let data = ConfigData {
+        content: serde_json::json!({"pool": 5}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    storage.put(&key, &data, None).await?;
+
+    let get = |if_none_match: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri("/configs/myapp/prod/db")
+                        .header("if-none-match", if_none_match)
+                        .body(Body::empty())?,
+                )
+                .await?;
+            let status = response.status();
+            let etag = response.headers().get("etag").cloned();
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok((status, etag, body))
+        }
+    };
+
+    let (status, etag, body) = get("\"v1\"").await?;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(etag.as_ref().and_then(|v| v.to_str().ok()), Some("\"v1\""));
+    assert!(body.is_empty());
+
+    storage.put(&key, &data, Some("v1")).await?;
+    let (status, etag, body) = get("\"v1\"").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(etag.as_ref().and_then(|v| v.to_str().ok()), Some("\"v2\""));
+    assert!(!body.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_configs_pages_with_token() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let data = ConfigData {
+        content: serde_json::json!({}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    for name in ["a", "b", "c"] {
+        storage
+            .put(&ConfigKey::new("myapp", "prod", name), &data, None)
+            .await?;
+    }
+
+    let list = |uri: String| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+                .await?;
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok((status, body))
+        }
+    };
+
+    let (status, body) = list("/configs?limit=2".to_string()).await?;
+    assert_eq!(status, StatusCode::OK);
+    let first: ListConfigsResponse = serde_json::from_slice(&body)?;
+    assert_eq!(first.configs.len(), 2);
+    let token = first
+        .next_page_token
+        .ok_or_else(|| anyhow::anyhow!("expected another page"))?;
+
+    let (_, body) = list(format!("/configs?limit=2&page_token={token}")).await?;
+    let second: ListConfigsResponse = serde_json::from_slice(&body)?;
+    assert_eq!(second.configs.len(), 1);
+    assert_eq!(second.configs[0].key.config_name, "c");
+    assert!(second.next_page_token.is_none());
+
+    let (status, _) = list("/configs?page_token=myapp/prod/a".to_string()).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_traversal_in_key_rejected() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+
+    for (method, uri) in [
+        ("GET", "/configs/..%2F..%2Fetc/prod/db"),
+        ("GET", "/configs/myapp/../db"),
+        ("PUT", "/configs/..%2F..%2Fetc/prod/db"),
+        ("PUT", "/configs/myapp/prod/.."),
+        ("DELETE", "/configs/myapp/prod/a%2Fb"),
+        ("GET", "/configs/%2E%2E/%2E%2E/etc"),
+        ("PUT", "/configs/myapp/%2E%2E/db"),
+        ("DELETE", "/configs/..%2F..%2Fetc/prod/db"),
+        ("DELETE", "/configs/%2E%2E/dev"),
+        // Every route is checked, not only some handlers
+        ("GET", "/configs/myapp/..%2Fsecret/db/versions"),
+        ("GET", "/configs/myapp/prod/..%2F..%2Fdb/lineage"),
+        ("POST", "/configs/myapp/prod/a%5Cb/activate/v1"),
+        ("GET", "/configs/myapp/prod/versions/watch"),
+    ] {
+        let body = serde_json::json!({"content": {}, "schema": {"type": "object"}});
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{method} {uri}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bad_version_not_activated_without_force() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "prod", "flags");
+    for n in 1..=3 {
+        let data = ConfigData {
+            content: serde_json::json!({"n": n}),
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        let expected = (n > 1).then(|| format!("v{}", n - 1));
+        storage.put(&key, &data, expected.as_deref()).await?;
+    }
+
+    let send = |method: &'static str, uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method(method)
+                        .uri(uri)
+                        .body(Body::empty())?,
+                )
+                .await?;
+            anyhow::Ok(response.status())
+        }
+    };
+
+    assert_eq!(
+        send("POST", "/configs/myapp/prod/flags/versions/v2/mark-bad").await?,
+        StatusCode::OK
+    );
+    assert!(storage.list_versions(&key).await?[1].bad);
+
+    assert_eq!(
+        send("POST", "/configs/myapp/prod/flags/activate/v2").await?,
+        StatusCode::CONFLICT
+    );
+    assert_eq!(storage.get(&key).await?.version, "v3");
+
+    // Still readable for inspection
+    assert_eq!(
+        send("GET", "/configs/myapp/prod/flags/versions/v2").await?,
+        StatusCode::OK
+    );
+
+    assert_eq!(
+        send("POST", "/configs/myapp/prod/flags/activate/v2?force=true").await?,
+        StatusCode::OK
+    );
+    assert_eq!(storage.get(&key).await?.version, "v2");
+
+    assert_eq!(
+        send("POST", "/configs/myapp/prod/flags/versions/v9/mark-bad").await?,
+        StatusCode::NOT_FOUND
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unknown_route_returns_json_error() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/no/such/route/here/at/all")
+                .body(Body::empty())?,
+        )
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let error: ErrorResponse = serde_json::from_slice(&body)?;
+    assert_eq!(error.error, "Not Found");
+    assert_eq!(error.code.as_deref(), Some(ROUTE_NOT_FOUND));
+    assert!(
+        error
+            .details
+            .is_some_and(|details| details.contains("/no/such/route/here/at/all"))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_conditional_delete() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    for n in 1..=2 {
+        let data = ConfigData {
+            content: serde_json::json!({"n": n}),
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        storage.put(&key, &data, (n > 1).then_some("v1")).await?;
+    }
+
+    let delete = |request: axum::http::request::Builder| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(request.method("DELETE").body(Body::empty())?)
+                .await?;
+            anyhow::Ok(response.status())
+        }
+    };
+
+    // Someone else already moved the config on to v2
+    let stale = Request::builder()
+        .uri("/configs/myapp/dev/flags")
+        .header("if-match", "\"v1\"");
+    assert_eq!(delete(stale).await?, StatusCode::CONFLICT);
+    let stale = Request::builder().uri("/configs/myapp/dev/flags?expected_version=v1");
+    assert_eq!(delete(stale).await?, StatusCode::CONFLICT);
+    assert!(storage.exists(&key).await?);
+
+    let current = Request::builder()
+        .uri("/configs/myapp/dev/flags")
+        .header("if-match", "\"v2\"");
+    assert_eq!(delete(current).await?, StatusCode::OK);
+    assert!(!storage.exists(&key).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_metrics_labeled_per_config() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+
+    for uri in ["/configs/myapp/dev/a", "/configs/myapp/dev/b"] {
+        assert_eq!(put_first_version(&app, uri).await?, StatusCode::OK);
+    }
+    for _ in 0..2 {
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/configs/myapp/dev/a")
+                    .body(Body::empty())?,
+            )
+            .await?;
+    }
+
+    let response = app
+        .oneshot(Request::builder().uri("/metrics").body(Body::empty())?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let text = String::from_utf8(body.to_vec())?;
+
+    for series in [
+        r#"config_reads_total{app="myapp",env="dev",config="a"} 2"#,
+        r#"config_writes_total{app="myapp",env="dev",config="a"} 1"#,
+        r#"config_writes_total{app="myapp",env="dev",config="b"} 1"#,
+        r#"config_version{app="myapp",env="dev",config="b"} 1"#,
+    ] {
+        assert!(text.contains(series), "missing {series} in:\n{text}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_metrics_count_requests_and_conflicts() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/a").await?,
+        StatusCode::OK
+    );
+
+    let stale = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/configs/myapp/dev/a")
+                .header("content-type", "application/json")
+                .header("if-match", "\"v7\"")
+                .body(Body::from(
+                    serde_json::json!({"content": {"enabled": false}}).to_string(),
+                ))?,
+        )
+        .await?;
+    assert_eq!(stale.status(), StatusCode::PRECONDITION_FAILED);
+
+    let response = app
+        .oneshot(Request::builder().uri("/metrics").body(Body::empty())?)
+        .await?;
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let text = String::from_utf8(body.to_vec())?;
+
+    let route = r#"method="PUT",route="/configs/:app/:env/:config""#;
+    for series in [
+        r#"config_version_conflicts_total{app="myapp",env="dev",config="a"} 1"#.to_string(),
+        format!(r#"http_requests_total{{{route},status="200"}} 1"#),
+        format!(r#"http_requests_total{{{route},status="412"}} 1"#),
+        format!("http_request_duration_seconds_count{{{route}}} 2"),
+    ] {
+        assert!(text.contains(&series), "missing {series} in:\n{text}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_watch_streams_version_changes() -> anyhow::Result<()> {
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    let app = create_test_app()?;
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/flags").await?,
+        StatusCode::OK
+    );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/configs/myapp/dev/flags/watch")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let mut frames = response.into_body().into_data_stream();
+    let mut next_frame = async || -> anyhow::Result<String> {
+        let frame = tokio::time::timeout(Duration::from_secs(5), frames.next())
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("stream ended"))??;
+        Ok(String::from_utf8(frame.to_vec())?)
+    };
+
+    // Late subscribers hear the current version straight away
+    let initial = next_frame().await?;
+    assert!(initial.contains("event: version"), "{initial}");
+    assert!(initial.contains(r#"data: {"version":"v1"}"#), "{initial}");
+
+    let update = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/configs/myapp/dev/flags")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "content": {"enabled": false},
+                        "expected_version": "v1"
+                    })
+                    .to_string(),
+                ))?,
+        )
+        .await?;
+    assert_eq!(update.status(), StatusCode::OK);
+
+    let changed = next_frame().await?;
+    assert!(changed.contains("id: v2"), "{changed}");
+    assert!(changed.contains(r#"data: {"version":"v2"}"#), "{changed}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_watch_sees_writes_under_another_casing() -> anyhow::Result<()> {
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    let storage = Arc::new(
+        ObjectStoreBackend::from_config(StorageConfig::memory())?.with_key_case(KeyCase::Lower),
+    );
+    let app = app_over(storage, ServerSettings::default());
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/prod/db").await?,
+        StatusCode::OK
+    );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/configs/MyApp/Prod/DB/watch")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut frames = response.into_body().into_data_stream();
+    let mut next_frame = async || -> anyhow::Result<String> {
+        let frame = tokio::time::timeout(Duration::from_secs(5), frames.next())
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("stream ended"))??;
+        Ok(String::from_utf8(frame.to_vec())?)
+    };
+    let initial = next_frame().await?;
+    assert!(initial.contains(r#"data: {"version":"v1"}"#), "{initial}");
+
+    let update = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/configs/myapp/prod/db")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "content": {"enabled": false},
+                        "expected_version": "v1"
+                    })
+                    .to_string(),
+                ))?,
+        )
+        .await?;
+    assert_eq!(update.status(), StatusCode::OK);
+
+    let changed = next_frame().await?;
+    assert!(changed.contains(r#"data: {"version":"v2"}"#), "{changed}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_validate_reports_errors_without_writing() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+    let schema = serde_json::json!({
+        "type": "object",
+        "properties": {"port": {"type": "integer"}}
+    });
+
+    let validate = |content: serde_json::Value| {
+        let body = serde_json::json!({"content": content, "schema": schema});
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/configs/myapp/dev/db/validate")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok(serde_json::from_slice::<ValidateConfigResponse>(&body)?)
+        }
+    };
+
+    let invalid = validate(serde_json::json!({"port": "5432"})).await?;
+    assert!(!invalid.valid);
+    assert_eq!(invalid.errors.len(), 1);
+    assert_eq!(invalid.errors[0].instance_path, "/port");
+
+    let valid = validate(serde_json::json!({"port": 5432})).await?;
+    assert!(valid.valid);
+    assert!(valid.errors.is_empty());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/configs/myapp/dev/db")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delete_version_promotes_previous() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/flags").await?,
+        StatusCode::OK
+    );
+    let data = storage.get(&key).await?;
+    storage.put(&key, &data, Some("v1")).await?;
+
+    let delete = |version: &str| {
+        let request = Request::builder()
+            .method("DELETE")
+            .uri(format!("/configs/myapp/dev/flags/versions/{version}"))
+            .body(Body::empty());
+        let app = app.clone();
+        async move { anyhow::Ok(app.oneshot(request?).await?.status()) }
+    };
+
+    assert_eq!(delete("v2").await?, StatusCode::OK);
+    assert_eq!(storage.get(&key).await?.version, "v1");
+    assert_eq!(delete("v2").await?, StatusCode::NOT_FOUND);
+    // The last version goes only with the config itself
+    assert_eq!(delete("v1").await?, StatusCode::BAD_REQUEST);
+
+    // Numbers of deleted versions are not reused
+    storage.put(&key, &data, Some("v1")).await?;
+    assert_eq!(storage.get(&key).await?.version, "v3");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_validate_history_rejects_schema_live_content_violates() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/flags").await?,
+        StatusCode::OK
+    );
+
+    let stricter = |query: &str| {
+        let body = serde_json::json!({
+            "content": {"enabled": true, "level": 2},
+            "schema": {"type": "object", "required": ["level"]},
+            "expected_version": "v1"
+        });
+        Request::builder()
+            .method("PUT")
+            .uri(format!("/configs/myapp/dev/flags{query}"))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+    };
+
+    let response = app
+        .clone()
+        .oneshot(stricter("?validate_history=true")?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let error: ErrorResponse = serde_json::from_slice(&body)?;
+    let details = error.details.unwrap_or_default();
+    assert!(details.contains("@ v1"), "{details}");
+    assert!(details.contains("level"), "{details}");
+
+    let response = app.oneshot(stricter("")?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_non_object_content_accepted_by_matching_schema() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+
+    let put = |uri: &str, content: serde_json::Value, schema: serde_json::Value| {
+        let body = serde_json::json!({"content": content, "schema": schema});
+        Request::builder()
+            .method("PUT")
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+    };
+
+    let hosts = serde_json::json!(["a.example.com", "b.example.com"]);
+    let response = app
+        .clone()
+        .oneshot(put(
+            "/configs/myapp/dev/hosts",
+            hosts.clone(),
+            serde_json::json!({"type": "array", "items": {"type": "string"}}),
+        )?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(put(
+            "/configs/myapp/dev/replicas",
+            serde_json::json!(3),
+            serde_json::json!({"type": "integer", "minimum": 1}),
+        )?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = Request::builder()
+        .uri("/configs/myapp/dev/hosts")
+        .body(Body::empty())?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let config: ConfigData = serde_json::from_slice(&body)?;
+    assert_eq!(config.content, hosts);
+
+    // A schema that asks for an object still turns other shapes away
+    let response = app
+        .oneshot(put(
+            "/configs/myapp/dev/flags",
+            serde_json::json!([true]),
+            serde_json::json!({"type": "object"}),
+        )?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_copy_config_to_new_environment() -> anyhow::Result<()> {
+    let app = create_test_app()?;
+    assert_eq!(
+        put_first_version(&app, "/configs/myapp/dev/flags").await?,
+        StatusCode::OK
+    );
+
+    let copy = |overwrite: bool| {
+        let body = serde_json::json!({
+            "to": {"application": "myapp", "environment": "staging", "config_name": "flags"},
+            "overwrite": overwrite
+        });
+        Request::builder()
+            .method("POST")
+            .uri("/configs/myapp/dev/flags/copy")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+    };
+
+    let response = app.clone().oneshot(copy(false)?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let success: SuccessResponse = serde_json::from_slice(&body)?;
+    assert_eq!(success.version.as_deref(), Some("v1"));
+    assert!(success.message.contains("myapp/staging/flags"));
+
+    let request = Request::builder()
+        .uri("/configs/myapp/staging/flags")
+        .body(Body::empty())?;
+    let response = app.clone().oneshot(request).await?;
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let copied: ConfigData = serde_json::from_slice(&body)?;
+    assert_eq!(copied.content, serde_json::json!({"enabled": true}));
+    assert_eq!(copied.schema, serde_json::json!({"type": "object"}));
+
+    let response = app.clone().oneshot(copy(false)?).await?;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app.clone().oneshot(copy(true)?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let success: SuccessResponse = serde_json::from_slice(&body)?;
+    assert_eq!(success.version.as_deref(), Some("v2"));
+
+    // The copy remembers where it came from
+    let request = Request::builder()
+        .uri("/configs/myapp/staging/flags/lineage")
+        .body(Body::empty())?;
+    let response = app.oneshot(request).await?;
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let lineage: LineageResponse = serde_json::from_slice(&body)?;
+    assert_eq!(lineage.lineage.len(), 1);
+    assert_eq!(
+        lineage.lineage[0].key,
+        ConfigKey::new("myapp", "dev", "flags")
+    );
+    assert_eq!(lineage.lineage[0].version, "v1");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_promote_writes_under_target_schema() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+
+    let dev = ConfigKey::new("myapp", "dev", "api");
+    let prod = ConfigKey::new("myapp", "prod", "api");
+    let data = |content: serde_json::Value, schema: serde_json::Value| ConfigData {
+        content,
+        schema,
+        version: String::new(),
+        content_type: None,
+    };
+    let strict = serde_json::json!({
+        "type": "object",
+        "properties": {"debug": {"const": false}}
+    });
+    storage
+        .put(
+            &dev,
+            &data(
+                serde_json::json!({"timeout": 30, "debug": true}),
+                serde_json::json!({"type": "object"}),
+            ),
+            None,
+        )
+        .await?;
+    storage
+        .put(
+            &prod,
+            &data(serde_json::json!({"timeout": 10}), strict.clone()),
+            None,
+        )
+        .await?;
+
+    let promote = || {
+        Request::builder()
+            .method("POST")
+            .uri("/configs/myapp/dev/api/promote")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"to_environment": "prod"}"#))
+    };
+
+    // The stricter prod schema cannot be bypassed
+    let response = app.clone().oneshot(promote()?).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(storage.get(&prod).await?.version, "v1");
+
+    storage
+        .put(
+            &dev,
+            &data(
+                serde_json::json!({"timeout": 30, "debug": false}),
+                serde_json::json!({"type": "object"}),
+            ),
+            Some("v1"),
+        )
+        .await?;
+    let response = app.oneshot(promote()?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let promoted: PromoteResponse = serde_json::from_slice(&body)?;
+    assert_eq!(promoted.source_version, "v2");
+    assert_eq!(promoted.target_version, "v2");
+
+    let current = storage.get(&prod).await?;
+    assert_eq!(
+        current.content,
+        serde_json::json!({"timeout": 30, "debug": false})
+    );
+    assert_eq!(current.schema, strict);
+    assert_eq!(
+        storage.lineage(&prod).await?,
+        [ConfigOrigin {
+            key: dev,
+            version: "v2".to_string()
+        }]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_version_alias_pins_version() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+
+    let key = ConfigKey::new("myapp", "dev", "flags");
+    for (n, expected) in [(1, None), (2, Some("v1")), (3, Some("v2"))] {
+        let data = ConfigData {
+            content: serde_json::json!({"build": n}),
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        storage.put(&key, &data, expected).await?;
+    }
+
+    let tag = |version: &str| {
+        Request::builder()
+            .method("PUT")
+            .uri("/configs/myapp/dev/flags/aliases/stable")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "version": version }).to_string(),
+            ))
+    };
+    let read = || {
+        Request::builder()
+            .uri("/configs/myapp/dev/flags/aliases/stable")
+            .body(Body::empty())
+    };
+
+    let response = app.clone().oneshot(read()?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = app.clone().oneshot(tag("v9")?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    for version in ["v1", "v2"] {
+        let response = app.clone().oneshot(tag(version)?).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.clone().oneshot(read()?).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+        let config: GetConfigResponse = serde_json::from_slice(&body)?;
+        assert_eq!(config.version, version);
+    }
+
+    // Deleting the tagged version takes the alias with it
+    storage.delete_version(&key, "v2").await?;
+    let response = app.oneshot(read()?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_versions_record_author() -> anyhow::Result<()> {
+    let settings = ServerSettings {
+        auth_mode: server::http::AuthMode::WriteOnly,
+        api_keys: ["secret".to_string()].into(),
+        ..ServerSettings::default()
+    };
+    let (authed, storage) = create_test_app_with_settings(settings)?;
+    let (open, open_storage) = create_test_app_with_storage()?;
+
+    let put = |authorization: Option<&str>| {
+        let request = PutConfigRequest {
+            content: serde_json::json!({"enabled": true}),
+            schema: Some(serde_json::json!({"type": "object"})),
+            expected_version: None,
+            content_type: None,
+            change_message: Some("Turn it on".to_string()),
+        };
+        let mut builder = Request::builder()
+            .method("PUT")
+            .uri("/configs/myapp/dev/flags")
+            .header("content-type", "application/json")
+            .header(handlers::AUTHOR_HEADER, "alice");
+        if let Some(authorization) = authorization {
+            builder = builder.header("authorization", authorization);
+        }
+        anyhow::Ok(builder.body(Body::from(serde_json::to_string(&request)?))?)
+    };
+
+    let key = ConfigKey::new("myapp", "dev", "flags");
+
+    // The key someone authenticated with outranks whoever they claim to be
+    let response = authed.oneshot(put(Some("Bearer secret"))?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let versions = storage.list_versions(&key).await?;
+    let author = versions[0].author.as_deref().unwrap_or_default();
+    assert!(author.starts_with("api-key:"), "{author}");
+    assert!(!author.contains("secret"));
+    assert_eq!(versions[0].change_message.as_deref(), Some("Turn it on"));
+
+    let response = open.oneshot(put(None)?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let versions = open_storage.list_versions(&key).await?;
+    assert_eq!(versions[0].author.as_deref(), Some("alice"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_config_pointer_returns_subtree() -> anyhow::Result<()> {
+    let (app, storage) = create_test_app_with_storage()?;
+    let key = ConfigKey::new("myapp", "dev", "service");
+    let data = ConfigData {
+        content: serde_json::json!({
+            "database": {"host": "db.internal", "ports": [5432, 5433]},
+            "a/b": true
+        }),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    storage.put(&key, &data, None).await?;
+
+    let get = |pointer: &str| {
+        let request = Request::builder()
+            .uri(format!("/configs/myapp/dev/service?pointer={pointer}"))
+            .body(Body::empty());
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request?).await?;
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+            anyhow::Ok((status, serde_json::from_slice(&body).ok()))
+        }
+    };
+
+    assert_eq!(
+        get("/database/host").await?,
+        (StatusCode::OK, Some(serde_json::json!("db.internal")))
+    );
+    assert_eq!(
+        get("/database/ports/1").await?,
+        (StatusCode::OK, Some(serde_json::json!(5433)))
+    );
+    // `~1` escapes a slash within a key
+    assert_eq!(
+        get("/a~1b").await?,
+        (StatusCode::OK, Some(serde_json::json!(true)))
+    );
+    assert_eq!(get("/database/user").await?.0, StatusCode::NOT_FOUND);
+    assert_eq!(get("database").await?.0, StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_write_only_auth_leaves_reads_open() -> anyhow::Result<()> {
+    let settings = ServerSettings {
+        auth_mode: server::http::AuthMode::WriteOnly,
+        api_keys: ["secret".to_string()].into(),
+        ..ServerSettings::default()
+    };
+    let (app, _storage) = create_test_app_with_settings(settings)?;
+
+    let put = |authorization: Option<&'static str>| {
+        let app = app.clone();
+        async move {
+            let request = PutConfigRequest {
+                content: serde_json::json!({"enabled": true}),
+                schema: Some(serde_json::json!({"type": "object"})),
+                expected_version: None,
+                content_type: None,
+                change_message: None,
+            };
+            let mut builder = Request::builder()
+                .method("PUT")
+                .uri("/configs/myapp/dev/flags")
+                .header("content-type", "application/json");
+            if let Some(authorization) = authorization {
+                builder = builder.header("authorization", authorization);
+            }
+            let response = app
+                .oneshot(builder.body(Body::from(serde_json::to_string(&request)?))?)
+                .await?;
+            anyhow::Ok(response.status())
+        }
+    };
+
+    assert_eq!(put(None).await?, StatusCode::UNAUTHORIZED);
+    assert_eq!(put(Some("Bearer wrong")).await?, StatusCode::UNAUTHORIZED);
+    assert_eq!(put(Some("Bearer secret")).await?, StatusCode::OK);
+
+    for uri in [
+        "/configs/myapp/dev/flags",
+        "/configs/myapp/dev/flags/versions",
+        "/configs",
+        "/health",
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty())?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK, "GET {uri}");
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/configs/myapp/dev/flags")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    Ok(())
+}
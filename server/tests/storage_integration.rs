@@ -1,6 +1,10 @@
 use anyhow::Result;
-use server::storage::{ConfigStorage, ObjectStoreBackend, StorageConfig};
-use shared_types::{ConfigData, ConfigKey};
+use server::storage::metadata::Metadata;
+use server::storage::migrate::migrate_between;
+use server::storage::seed::seed_from_dir;
+use server::storage::{ConfigStorage, KeyCase, ObjectStoreBackend, StorageConfig, StorageError};
+use shared_types::{ConfigData, ConfigKey, VersionInfo};
+use std::sync::Arc;
 use tempfile::TempDir;
 use testcontainers::{ContainerAsync, ImageExt, runners::AsyncRunner};
 use testcontainers_modules::minio::MinIO;
@@ -11,9 +15,7 @@ use testcontainers_modules::minio::MinIO;
 
 fn create_local_test_backend() -> Result<(ObjectStoreBackend, TempDir)> {
     let temp_dir = TempDir::new()?;
-    let config = StorageConfig::Local {
-        path: temp_dir.path().to_path_buf(),
-    };
+    let config = StorageConfig::local(temp_dir.path());
     let backend = ObjectStoreBackend::from_config(config)?;
     Ok((backend, temp_dir))
 }
@@ -27,6 +29,7 @@ async fn test_local_put_and_get_config() -> Result<()> {
         content: serde_json::json!({"host": "localhost", "port": 5432}),
         schema: serde_json::json!({"type": "object"}),
         version: String::new(),
+        content_type: None,
     };
 
     // First put should succeed with no expected version
@@ -49,6 +52,7 @@ async fn test_local_optimistic_concurrency_control() -> Result<()> {
         content: serde_json::json!({"version": 1}),
         schema: serde_json::json!({"type": "object"}),
         version: String::new(),
+        content_type: None,
     };
 
     // Create first version
@@ -60,6 +64,7 @@ async fn test_local_optimistic_concurrency_control() -> Result<()> {
         content: serde_json::json!({"version": 2}),
         schema: data1.schema.clone(),
         version: String::new(),
+        content_type: None,
     };
     backend.put(&key, &data2, Some(&retrieved.version)).await?;
 
@@ -82,6 +87,7 @@ async fn test_local_versioning() -> Result<()> {
             content: serde_json::json!({"version": i}),
             schema: serde_json::json!({"type": "object"}),
             version: String::new(),
+            content_type: None,
         };
 
         if i == 1 {
@@ -126,6 +132,7 @@ async fn test_local_delete_environment() -> Result<()> {
             content: serde_json::json!({"test": true}),
             schema: serde_json::json!({"type": "object"}),
             version: String::new(),
+            content_type: None,
         };
         backend.put(&key, &data, None).await?;
     }
@@ -165,6 +172,657 @@ async fn test_local_delete_environment() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_local_list_configs() -> Result<()> {
+    let (backend, _dir) = create_local_test_backend()?;
+
+    for (app, env, name) in [
+        ("app1", "dev", "b"),
+        ("app1", "dev", "a"),
+        ("app2", "prod", "c"),
+    ] {
+        let data = ConfigData {
+            content: serde_json::json!({}),
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        backend
+            .put(&ConfigKey::new(app, env, name), &data, None)
+            .await?;
+    }
+
+    let all = backend.list(None).await?;
+    assert_eq!(
+        all.iter().map(ConfigKey::to_path).collect::<Vec<_>>(),
+        vec!["app1/dev/a", "app1/dev/b", "app2/prod/c"]
+    );
+
+    let app1 = backend.list(Some("app1")).await?;
+    assert_eq!(app1.len(), 2);
+    assert!(backend.list(Some("app3")).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_existing_version_is_never_overwritten() -> Result<()> {
+    let (backend, dir) = create_local_test_backend()?;
+
+    let key = ConfigKey::new("app", "dev", "immutable");
+    let data = |n: i32| ConfigData {
+        content: serde_json::json!({"n": n}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+
+    backend.put(&key, &data(1), None).await?;
+    backend.put(&key, &data(2), Some("v1")).await?;
+
+    // Roll metadata back to v1 so the next put tries to write v2 again
+    let metadata_path = dir.path().join("app/dev/immutable/metadata.json");
+    let mut metadata: serde_json::Value = serde_json::from_slice(&std::fs::read(&metadata_path)?)?;
+    metadata["current_version"] = serde_json::json!("v1");
+    metadata["versions"]
+        .as_array_mut()
+        .ok_or(anyhow::anyhow!("versions is not an array"))?
+        .truncate(1);
+    std::fs::write(&metadata_path, serde_json::to_vec(&metadata)?)?;
+
+    // The unreferenced v2 keeps its number; the write moves past it
+    backend.put(&key, &data(3), Some("v1")).await?;
+    let current = backend.get(&key).await?;
+    assert_eq!(current.version, "v3");
+    assert_eq!(current.content["n"], 3);
+
+    // The original v2 content is untouched
+    let v2 = dir.path().join("app/dev/immutable/versions/v2/data.json");
+    let v2: serde_json::Value = serde_json::from_slice(&std::fs::read(v2)?)?;
+    assert_eq!(v2["n"], 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_key_case_lower_merges_casings() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let backend = ObjectStoreBackend::from_config(StorageConfig::local(temp_dir.path()))?
+        .with_key_case(KeyCase::Lower);
+
+    let data = ConfigData {
+        content: serde_json::json!({"pool": 10}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    backend
+        .put(&ConfigKey::new("MyApp", "Prod", "DB"), &data, None)
+        .await?;
+
+    let retrieved = backend.get(&ConfigKey::new("myapp", "prod", "db")).await?;
+    assert_eq!(retrieved.content["pool"], 10);
+
+    // Creating the other casing is now an update of the same config
+    let result = backend
+        .put(&ConfigKey::new("myapp", "prod", "db"), &data, None)
+        .await;
+    assert!(result.is_err());
+    assert_eq!(backend.list(Some("MYAPP")).await?.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_key_case_sensitive_by_default() -> Result<()> {
+    let (backend, _dir) = create_local_test_backend()?;
+
+    let data = ConfigData {
+        content: serde_json::json!({"pool": 10}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    backend
+        .put(&ConfigKey::new("MyApp", "Prod", "DB"), &data, None)
+        .await?;
+    backend
+        .put(&ConfigKey::new("myapp", "prod", "db"), &data, None)
+        .await?;
+
+    assert_eq!(backend.list(None).await?.len(), 2);
+    assert!(
+        backend
+            .get(&ConfigKey::new("MYAPP", "PROD", "DB"))
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_content_type_persisted_per_version() -> Result<()> {
+    let (backend, _dir) = create_local_test_backend()?;
+
+    let key = ConfigKey::new("test-app", "dev", "settings");
+    let yaml = ConfigData {
+        content: serde_json::json!({"raw": "debug: true"}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: Some("application/yaml".to_string()),
+    };
+    backend.put(&key, &yaml, None).await?;
+
+    let json = ConfigData {
+        content_type: None,
+        ..yaml.clone()
+    };
+    backend.put(&key, &json, Some("v1")).await?;
+
+    let current = backend.get(&key).await?;
+    assert_eq!(current.content_type, None);
+    assert_eq!(current.content_type(), "application/json");
+
+    let first = backend.get_version(&key, "v1").await?;
+    assert_eq!(first.content_type.as_deref(), Some("application/yaml"));
+    Ok(())
+}
+
+fn seed_fixture_dir() -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/seed")
+}
+
+#[tokio::test]
+async fn test_local_seed_from_dir() -> Result<()> {
+    let (backend, _dir) = create_local_test_backend()?;
+
+    let seeded = seed_from_dir(&backend, &seed_fixture_dir()).await?;
+    assert_eq!(seeded, 2);
+
+    let database = backend
+        .get(&ConfigKey::new("myapp", "dev", "database"))
+        .await?;
+    assert_eq!(database.version, "v1");
+    assert_eq!(database.content["port"], 5432);
+    assert_eq!(
+        database.schema["required"],
+        serde_json::json!(["host", "port"])
+    );
+
+    // Without a sibling schema file a permissive object schema is used
+    let api = backend.get(&ConfigKey::new("myapp", "prod", "api")).await?;
+    assert_eq!(api.content["url"], "https://api.example.com");
+    assert_eq!(api.schema, serde_json::json!({"type": "object"}));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_server_seeds_from_seed_dir_on_startup() -> Result<()> {
+    let (backend, dir) = create_local_test_backend()?;
+
+    // Run the real binary, so the SEED_DIR wiring is covered and not just
+    // seed_from_dir; from the temp dir, so no stray .env is picked up
+    let mut server = std::process::Command::new(env!("CARGO_BIN_EXE_server"))
+        .current_dir(dir.path())
+        .env("STORAGE_BACKEND", "local")
+        .env("STORAGE_PATH", dir.path())
+        .env("SEED_DIR", seed_fixture_dir())
+        .env("BIND_ADDRESS", "127.0.0.1:0")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(30);
+    let mut seeded = false;
+    while !seeded && std::time::Instant::now() < deadline && server.try_wait()?.is_none() {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        seeded = backend
+            .list(None)
+            .await
+            .is_ok_and(|configs| configs.len() == 2);
+    }
+    let _ = server.kill();
+    server.wait()?;
+
+    assert!(seeded, "server did not seed the store on startup");
+    let database = backend
+        .get(&ConfigKey::new("myapp", "dev", "database"))
+        .await?;
+    assert_eq!(database.content["port"], 5432);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_seed_skipped_when_store_not_empty() -> Result<()> {
+    let (backend, _dir) = create_local_test_backend()?;
+
+    let existing = ConfigData {
+        content: serde_json::json!({"keep": true}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    backend
+        .put(&ConfigKey::new("other", "dev", "config"), &existing, None)
+        .await?;
+
+    let seeded = seed_from_dir(&backend, &seed_fixture_dir()).await?;
+    assert_eq!(seeded, 0);
+    assert!(
+        !backend
+            .exists(&ConfigKey::new("myapp", "dev", "database"))
+            .await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_migrates_into_memory_store_with_full_fidelity() -> Result<()> {
+    let (src, _dir) = create_local_test_backend()?;
+
+    let flags = ConfigKey::new("myapp", "prod", "flags");
+    let limits = ConfigKey::new("myapp", "dev", "limits");
+    for (key, count) in [(&flags, 3), (&limits, 1)] {
+        for n in 1..=count {
+            let data = ConfigData {
+                content: serde_json::json!({ "n": n }),
+                schema: serde_json::json!({"type": "object"}),
+                version: String::new(),
+                content_type: (n == 2).then(|| "application/yaml".to_string()),
+            };
+            let expected = (n > 1).then(|| format!("v{}", n - 1));
+            src.put(key, &data, expected.as_deref()).await?;
+        }
+    }
+    src.set_alias(&ConfigKey::new("myapp", "prod", "current"), &flags)
+        .await?;
+
+    let dst = ObjectStoreBackend::from_config(StorageConfig::memory())?;
+    let report = migrate_between(&src, &dst).await?;
+    assert_eq!(report.configs, 3);
+    assert_eq!(report.versions, 4);
+
+    assert_eq!(dst.list(None).await?, src.list(None).await?);
+    for key in [&flags, &limits] {
+        let src_versions = src.list_versions(key).await?;
+        let dst_versions = dst.list_versions(key).await?;
+        assert_eq!(src_versions.len(), dst_versions.len());
+        for (a, b) in src_versions.iter().zip(&dst_versions) {
+            assert_eq!(a.version, b.version);
+            assert_eq!(a.timestamp, b.timestamp);
+
+            let a = src.get_version(key, &a.version).await?;
+            let b = dst.get_version(key, &b.version).await?;
+            assert_eq!(a.content, b.content);
+            assert_eq!(a.schema, b.schema);
+            assert_eq!(a.content_type, b.content_type);
+        }
+        assert_eq!(dst.get(key).await?.version, src.get(key).await?.version);
+    }
+    assert_eq!(
+        dst.resolve_alias(&ConfigKey::new("myapp", "prod", "current"))
+            .await?,
+        flags
+    );
+    Ok(())
+}
+
+/// A store implementing only the core `ConfigStorage` methods, relying on the
+/// trait's defaults for everything else
+#[derive(Default)]
+struct MinimalStore {
+    configs: std::sync::Mutex<std::collections::BTreeMap<String, (ConfigKey, ConfigData)>>,
+}
+
+impl MinimalStore {
+    fn configs(
+        &self,
+    ) -> Result<
+        std::sync::MutexGuard<'_, std::collections::BTreeMap<String, (ConfigKey, ConfigData)>>,
+    > {
+        self.configs
+            .lock()
+            .map_err(|_| anyhow::anyhow!("store lock poisoned"))
+    }
+}
+
+#[async_trait::async_trait]
+impl ConfigStorage for MinimalStore {
+    async fn get(&self, key: &ConfigKey) -> Result<ConfigData> {
+        self.configs()?
+            .get(&key.to_path())
+            .map(|(_, data)| data.clone())
+            .ok_or_else(|| StorageError::NotFound(key.to_string()).into())
+    }
+    async fn put(
+        &self,
+        key: &ConfigKey,
+        data: &ConfigData,
+        _expected_version: Option<&str>,
+    ) -> Result<String> {
+        self.configs()?
+            .insert(key.to_path(), (key.clone(), data.clone()));
+        Ok(data.version.clone())
+    }
+    async fn delete(&self, key: &ConfigKey, _: Option<&str>) -> Result<Option<usize>> {
+        Ok(self.configs()?.remove(&key.to_path()).map(|_| 1))
+    }
+    async fn exists(&self, key: &ConfigKey) -> Result<bool> {
+        Ok(self.configs()?.contains_key(&key.to_path()))
+    }
+    async fn list(&self, prefix: Option<&str>) -> Result<Vec<ConfigKey>> {
+        let prefix = prefix.map(|p| format!("{p}/")).unwrap_or_default();
+        Ok(self
+            .configs()?
+            .iter()
+            .filter(|(path, _)| path.starts_with(&prefix))
+            .map(|(_, (key, _))| key.clone())
+            .collect())
+    }
+    async fn get_version(&self, _: &ConfigKey, _: &str) -> Result<ConfigData> {
+        anyhow::bail!("not supported")
+    }
+    async fn list_versions(&self, _: &ConfigKey) -> Result<Vec<VersionInfo>> {
+        anyhow::bail!("not supported")
+    }
+}
+
+#[tokio::test]
+async fn test_default_bulk_operations_on_minimal_store() -> Result<()> {
+    let store = MinimalStore::default();
+    let data = ConfigData {
+        content: serde_json::json!({}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    for (app, env, config) in [
+        ("billing", "prod", "db"),
+        ("billing", "prod", "flags"),
+        ("billing", "dev", "db"),
+        ("billing", "prod-eu", "db"),
+        ("search", "prod", "index"),
+    ] {
+        store
+            .put(&ConfigKey::new(app, env, config), &data, None)
+            .await?;
+    }
+
+    assert_eq!(store.list_applications().await?, ["billing", "search"]);
+    assert_eq!(
+        store.list_environments("billing").await?,
+        ["dev", "prod", "prod-eu"]
+    );
+
+    // Only whole environments match, so prod-eu is untouched
+    assert_eq!(store.delete_environment("billing", "prod").await?, 2);
+    assert_eq!(store.delete_environment("billing", "prod").await?, 0);
+    assert_eq!(
+        store.list_environments("billing").await?,
+        ["dev", "prod-eu"]
+    );
+    assert!(
+        store
+            .exists(&ConfigKey::new("search", "prod", "index"))
+            .await?
+    );
+
+    // Features it doesn't implement are reported as such
+    let index = ConfigKey::new("search", "prod", "index");
+    let err = store
+        .activate(&index, "v1")
+        .await
+        .err()
+        .ok_or(anyhow::anyhow!("expected activate to be unsupported"))?;
+    assert!(matches!(
+        err.downcast_ref::<StorageError>(),
+        Some(StorageError::Unsupported(_))
+    ));
+    assert_eq!(store.resolve_alias(&index).await?, index);
+    assert!(store.lineage(&index).await?.is_empty());
+    Ok(())
+}
+
+/// An in-memory store whose schema writes can be made to fail
+#[derive(Debug, Default)]
+struct FailingSchemaStore {
+    inner: object_store::memory::InMemory,
+    fail_schema_writes: std::sync::atomic::AtomicBool,
+}
+
+impl std::fmt::Display for FailingSchemaStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FailingSchemaStore")
+    }
+}
+
+#[async_trait::async_trait]
+impl object_store::ObjectStore for FailingSchemaStore {
+    async fn put_opts(
+        &self,
+        location: &object_store::path::Path,
+        payload: object_store::PutPayload,
+        opts: object_store::PutOptions,
+    ) -> object_store::Result<object_store::PutResult> {
+        if location.filename() == Some("schema.json")
+            && self
+                .fail_schema_writes
+                .load(std::sync::atomic::Ordering::SeqCst)
+        {
+            return Err(object_store::Error::Generic {
+                store: "FailingSchemaStore",
+                source: "injected schema write failure".into(),
+            });
+        }
+        self.inner.put_opts(location, payload, opts).await
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        location: &object_store::path::Path,
+        opts: object_store::PutMultipartOpts,
+    ) -> object_store::Result<Box<dyn object_store::MultipartUpload>> {
+        self.inner.put_multipart_opts(location, opts).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &object_store::path::Path,
+        options: object_store::GetOptions,
+    ) -> object_store::Result<object_store::GetResult> {
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn delete(&self, location: &object_store::path::Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+
+    fn list(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+    ) -> futures::stream::BoxStream<'_, object_store::Result<object_store::ObjectMeta>> {
+        self.inner.list(prefix)
+    }
+
+    async fn list_with_delimiter(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+    ) -> object_store::Result<object_store::ListResult> {
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+#[tokio::test]
+async fn test_failed_put_leaves_no_orphaned_version_objects() -> Result<()> {
+    use futures::TryStreamExt;
+    use object_store::ObjectStore;
+
+    let store = Arc::new(FailingSchemaStore::default());
+    let backend = ObjectStoreBackend::new(store.clone());
+    let key = ConfigKey::new("myapp", "prod", "flags");
+    let data = |n: i32| ConfigData {
+        content: serde_json::json!({ "n": n }),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    backend.put(&key, &data(1), None).await?;
+
+    store
+        .fail_schema_writes
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    assert!(backend.put(&key, &data(2), Some("v1")).await.is_err());
+
+    let v2 = object_store::path::Path::from("myapp/prod/flags/versions/v2");
+    let leftovers: Vec<_> = store.inner.list(Some(&v2)).try_collect().await?;
+    assert!(leftovers.is_empty(), "orphaned objects: {leftovers:?}");
+    assert_eq!(backend.list_versions(&key).await?.len(), 1);
+    assert_eq!(backend.get(&key).await?.version, "v1");
+    assert!(backend.collect_garbage(false).await?.is_empty());
+    Ok(())
+}
+
+fn is_invalid_key<T>(result: &Result<T>) -> bool {
+    matches!(result, Err(e) if matches!(e.downcast_ref(), Some(StorageError::InvalidKey(_))))
+}
+
+#[tokio::test]
+async fn test_local_compacted_versions_stay_reachable() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let backend = ObjectStoreBackend::from_config(StorageConfig::local(temp_dir.path()))?
+        .with_metadata_compaction(4);
+    let key = ConfigKey::new("app", "prod", "flags");
+
+    for n in 1..=12 {
+        let data = ConfigData {
+            content: serde_json::json!({"n": n}),
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        let expected = (n > 1).then(|| format!("v{}", n - 1));
+        backend.put(&key, &data, expected.as_deref()).await?;
+    }
+
+    // Only the recent history is kept inline
+    let config_dir = temp_dir.path().join("app/prod/flags");
+    let inline: Metadata =
+        serde_json::from_slice(&std::fs::read(config_dir.join("metadata.json"))?)?;
+    assert!(inline.versions.len() <= 4);
+    assert!(config_dir.join("versions_archive.json").exists());
+
+    let versions = backend.list_versions(&key).await?;
+    assert_eq!(versions.len(), 12);
+    assert_eq!(versions[0].version, "v1");
+    assert_eq!(backend.get_version(&key, "v1").await?.content["n"], 1);
+    assert_eq!(backend.get(&key).await?.content["n"], 12);
+
+    // Archived versions can still be activated and are not garbage
+    backend.activate(&key, "v2").await?;
+    assert_eq!(backend.get(&key).await?.content["n"], 2);
+    assert!(backend.collect_garbage(false).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_list_pages_cover_every_config_once() -> Result<()> {
+    let (backend, _dir) = create_local_test_backend()?;
+    let data = ConfigData {
+        content: serde_json::json!({}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    for name in ["db", "db-replica", "cache", "flags", "queue"] {
+        backend
+            .put(&ConfigKey::new("app", "prod", name), &data, None)
+            .await?;
+    }
+    backend
+        .put(&ConfigKey::new("other", "prod", "db"), &data, None)
+        .await?;
+
+    let mut listed = Vec::new();
+    let mut token = None;
+    loop {
+        let page = backend.list_page(Some("app"), 2, token.as_deref()).await?;
+        assert!(page.keys.len() <= 2);
+        listed.extend(page.keys);
+        token = page.next_token;
+        if token.is_none() {
+            break;
+        }
+    }
+
+    let mut all = backend.list(Some("app")).await?;
+    all.sort_by_key(ConfigKey::to_path);
+    listed.sort_by_key(ConfigKey::to_path);
+    assert_eq!(listed, all);
+    assert_eq!(listed.len(), 5);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_rejects_keys_that_escape_their_path() -> Result<()> {
+    let (backend, dir) = create_local_test_backend()?;
+
+    let data = ConfigData {
+        content: serde_json::json!({}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+
+    let malicious = [
+        ConfigKey::new("..", "dev", "db"),
+        ConfigKey::new("app", "../../etc", "db"),
+        ConfigKey::new("/abs", "dev", "db"),
+        ConfigKey::new("app", "dev", "a\\..\\b"),
+        ConfigKey::new("app", "dev", "versions"),
+        ConfigKey::new("app", "metadata.json", "db"),
+        ConfigKey::new("app", "", "db"),
+    ];
+
+    for key in &malicious {
+        assert!(
+            is_invalid_key(&backend.put(key, &data, None).await),
+            "{key:?}"
+        );
+        assert!(is_invalid_key(&backend.get(key).await), "{key:?}");
+    }
+
+    // Nor may a version name climb out of its directory
+    let key = ConfigKey::new("app", "dev", "db");
+    backend.put(&key, &data, None).await?;
+    assert!(is_invalid_key(&backend.get_version(&key, "..").await));
+
+    assert!(!dir.path().join("metadata.json").exists());
+    assert!(!dir.path().join("..").join("etc").join("db").exists());
+    Ok(())
+}
+
 // ============================================================================
 // S3 Storage Tests
 // ============================================================================
@@ -238,6 +896,7 @@ async fn test_s3_put_and_get_config() -> Result<()> {
             "type": "object"
         }),
         version: String::new(),
+        content_type: None,
     };
 
     // Create new config
@@ -280,6 +939,7 @@ async fn test_s3_versioning() -> Result<()> {
                 "type": "object"
             }),
             version: String::new(),
+            content_type: None,
         };
 
         if i == 1 {
@@ -327,6 +987,7 @@ fn test_s3_config_construction() {
             access_key_id,
             secret_access_key,
             allow_http,
+            ..
         } => {
             assert_eq!(bucket, "test-bucket");
             assert_eq!(region, Some("us-east-1".to_string()));
@@ -344,9 +1005,347 @@ fn test_local_config_construction() {
     let config = StorageConfig::local("./data");
 
     match config {
-        StorageConfig::Local { path } => {
+        StorageConfig::Local { path, .. } => {
             assert_eq!(path.to_str().unwrap(), "./data");
         }
         _ => panic!("Expected Local config"),
     }
 }
+
+#[tokio::test]
+async fn test_local_versions_report_size_schema_and_hash() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let backend = ObjectStoreBackend::from_config(StorageConfig::local(temp_dir.path()))?;
+    let key = ConfigKey::new("app", "prod", "flags");
+
+    let data = ConfigData {
+        content: serde_json::json!({"enabled": true}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    backend.put(&key, &data, None).await?;
+
+    let versions = backend.list_versions(&key).await?;
+    let stored = std::fs::read(temp_dir.path().join("app/prod/flags/versions/v1/data.json"))?;
+    assert_eq!(versions[0].data_size, stored.len());
+    assert!(versions[0].has_schema);
+
+    // The hash covers exactly the stored bytes, so equal content hashes equally
+    assert_eq!(versions[0].content_hash.len(), 64);
+    backend.put(&key, &data, Some("v1")).await?;
+    let versions = backend.list_versions(&key).await?;
+    assert_eq!(versions[0].content_hash, versions[1].content_hash);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_max_versions_prunes_oldest() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let backend = ObjectStoreBackend::from_config(
+        StorageConfig::local(temp_dir.path()).with_max_versions(3),
+    )?;
+    let key = ConfigKey::new("app", "prod", "flags");
+
+    for n in 1..=10 {
+        let data = ConfigData {
+            content: serde_json::json!({"n": n}),
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        let expected = (n > 1).then(|| format!("v{}", n - 1));
+        backend.put(&key, &data, expected.as_deref()).await?;
+    }
+
+    let versions: Vec<String> = backend
+        .list_versions(&key)
+        .await?
+        .into_iter()
+        .map(|v| v.version)
+        .collect();
+    assert_eq!(versions, ["v8", "v9", "v10"]);
+    for n in 8..=10 {
+        assert_eq!(
+            backend.get_version(&key, &format!("v{n}")).await?.content["n"],
+            n
+        );
+    }
+    for n in 1..=7 {
+        assert!(backend.get_version(&key, &format!("v{n}")).await.is_err());
+        assert!(
+            !temp_dir
+                .path()
+                .join(format!("app/prod/flags/versions/v{n}"))
+                .join("data.json")
+                .exists()
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_tampered_content_fails_integrity_check() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let backend = ObjectStoreBackend::from_config(StorageConfig::local(temp_dir.path()))?;
+    let key = ConfigKey::new("app", "prod", "flags");
+    let data = ConfigData {
+        content: serde_json::json!({"enabled": true}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    backend.put(&key, &data, None).await?;
+
+    // Still valid JSON, so only the hash gives it away
+    let data_path = temp_dir.path().join("app/prod/flags/versions/v1/data.json");
+    std::fs::write(&data_path, br#"{"enabled": false}"#)?;
+
+    for result in [
+        backend.get(&key).await,
+        backend.get_version(&key, "v1").await,
+    ] {
+        let err = result
+            .err()
+            .ok_or_else(|| anyhow::anyhow!("read succeeded"))?;
+        assert!(matches!(
+            err.downcast_ref::<StorageError>(),
+            Some(StorageError::IntegrityError(_))
+        ));
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_puts_with_same_expected_version() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    for config in [
+        StorageConfig::local(temp_dir.path()),
+        StorageConfig::memory(),
+    ] {
+        let backend = Arc::new(ObjectStoreBackend::from_config(config)?);
+        let key = ConfigKey::new("app", "prod", "flags");
+        let data = |n: usize| ConfigData {
+            content: serde_json::json!({"writer": n}),
+            schema: serde_json::json!({"type": "object"}),
+            version: String::new(),
+            content_type: None,
+        };
+        backend.put(&key, &data(0), None).await?;
+
+        let writers: Vec<_> = (1..=8)
+            .map(|n| {
+                let backend = backend.clone();
+                let key = key.clone();
+                let data = data(n);
+                tokio::spawn(async move { backend.put(&key, &data, Some("v1")).await })
+            })
+            .collect();
+        let mut succeeded = 0;
+        for writer in writers {
+            match writer.await? {
+                Ok(version) => {
+                    assert_eq!(version, "v2");
+                    succeeded += 1;
+                }
+                Err(e) => assert!(
+                    matches!(
+                        e.downcast_ref::<StorageError>(),
+                        Some(StorageError::VersionConflict { .. })
+                    ),
+                    "unexpected error: {e:#}"
+                ),
+            }
+        }
+
+        assert_eq!(succeeded, 1);
+        assert_eq!(backend.list_versions(&key).await?.len(), 2);
+        assert_eq!(backend.get(&key).await?.version, "v2");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_orphaned_version_objects_do_not_wedge_puts() -> Result<()> {
+    let (backend, dir) = create_local_test_backend()?;
+    let key = ConfigKey::new("app", "dev", "flags");
+    let data = |n: i32| ConfigData {
+        content: serde_json::json!({ "n": n }),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    backend.put(&key, &data(1), None).await?;
+
+    // As left by a put that timed out after writing its data
+    let orphan = dir.path().join("app/dev/flags/versions/v2");
+    std::fs::create_dir_all(&orphan)?;
+    std::fs::write(orphan.join("data.json"), b"{\"n\": 0}")?;
+
+    backend.put(&key, &data(2), Some("v1")).await?;
+    backend.put(&key, &data(3), Some("v3")).await?;
+
+    let versions: Vec<String> = backend
+        .list_versions(&key)
+        .await?
+        .into_iter()
+        .map(|v| v.version)
+        .collect();
+    assert_eq!(versions, ["v1", "v3", "v4"]);
+    assert_eq!(backend.get(&key).await?.content["n"], 3);
+    Ok(())
+}
+
+/// An in-memory store that is slow to read, widening the window between a
+/// writer reading metadata and writing it back
+#[derive(Debug, Default)]
+struct SlowReadStore {
+    inner: object_store::memory::InMemory,
+}
+
+impl std::fmt::Display for SlowReadStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SlowReadStore")
+    }
+}
+
+#[async_trait::async_trait]
+impl object_store::ObjectStore for SlowReadStore {
+    async fn put_opts(
+        &self,
+        location: &object_store::path::Path,
+        payload: object_store::PutPayload,
+        opts: object_store::PutOptions,
+    ) -> object_store::Result<object_store::PutResult> {
+        self.inner.put_opts(location, payload, opts).await
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        location: &object_store::path::Path,
+        opts: object_store::PutMultipartOpts,
+    ) -> object_store::Result<Box<dyn object_store::MultipartUpload>> {
+        self.inner.put_multipart_opts(location, opts).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &object_store::path::Path,
+        options: object_store::GetOptions,
+    ) -> object_store::Result<object_store::GetResult> {
+        let result = self.inner.get_opts(location, options).await;
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        result
+    }
+
+    async fn delete(&self, location: &object_store::path::Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+
+    fn list(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+    ) -> futures::stream::BoxStream<'_, object_store::Result<object_store::ObjectMeta>> {
+        self.inner.list(prefix)
+    }
+
+    async fn list_with_delimiter(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+    ) -> object_store::Result<object_store::ListResult> {
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_metadata_updates_from_other_processes_are_not_lost() -> Result<()> {
+    // Two backends sharing a store, as two server processes would; their
+    // in-process locks don't see each other
+    let store: Arc<dyn object_store::ObjectStore> = Arc::new(SlowReadStore::default());
+    let backends: Vec<_> = (0..2)
+        .map(|_| Arc::new(ObjectStoreBackend::new(store.clone()).with_conditional_update(true)))
+        .collect();
+    let key = ConfigKey::new("app", "prod", "flags");
+    let data = ConfigData {
+        content: serde_json::json!({"on": true}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    backends[0].put(&key, &data, None).await?;
+
+    let writers: Vec<_> = (0..8)
+        .map(|n| {
+            let backend = backends[n % 2].clone();
+            let key = key.clone();
+            tokio::spawn(async move {
+                let alias = format!("alias-{n}");
+                let result = backend.set_version_alias(&key, &alias, "v1").await;
+                (alias, result)
+            })
+        })
+        .collect();
+    let mut set = Vec::new();
+    for writer in writers {
+        let (alias, result) = writer.await?;
+        match result {
+            Ok(()) => set.push(alias),
+            Err(e) => assert!(
+                matches!(
+                    e.downcast_ref::<StorageError>(),
+                    Some(StorageError::VersionConflict { .. })
+                ),
+                "unexpected error: {e:#}"
+            ),
+        }
+    }
+
+    // Every update reported as applied is still there
+    let metadata = backends[1]
+        .metadata(&key)
+        .await?
+        .ok_or(anyhow::anyhow!("metadata missing"))?;
+    assert!(!set.is_empty());
+    for alias in set {
+        assert!(metadata.aliases.contains_key(&alias), "{alias} was lost");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_versions_keeps_staged_version() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let backend = ObjectStoreBackend::from_config(StorageConfig::local(temp_dir.path()))?
+        .with_max_versions(1);
+    let key = ConfigKey::new("app", "prod", "flags");
+    let data = |n: i32| ConfigData {
+        content: serde_json::json!({"n": n}),
+        schema: serde_json::json!({"type": "object"}),
+        version: String::new(),
+        content_type: None,
+    };
+    backend.put(&key, &data(1), None).await?;
+
+    let staged = backend.stage(&key, &data(2), Some("v1")).await?;
+    assert_eq!(staged, "v2");
+    assert_eq!(backend.get_version(&key, "v2").await?.content["n"], 2);
+    backend.activate(&key, "v2").await?;
+    assert_eq!(backend.get(&key).await?.content["n"], 2);
+    Ok(())
+}
@@ -13,9 +13,7 @@ use testcontainers_modules::minio::MinIO;
 
 fn create_local_test_backend() -> Result<(ObjectStoreBackend, TempDir)> {
     let temp_dir = TempDir::new()?;
-    let config = StorageConfig::Local {
-        path: temp_dir.path().to_path_buf(),
-    };
+    let config = StorageConfig::local(temp_dir.path());
     let backend = ObjectStoreBackend::from_config(config)?;
     Ok((backend, temp_dir))
 }
@@ -29,6 +27,7 @@ async fn test_local_put_and_get_config() -> Result<()> {
         content: serde_json::json!({"host": "localhost", "port": 5432}),
         schema: serde_json::json!({"type": "object"}),
         version: String::new(),
+        content_type: None,
     };
 
     // First put should succeed with no expected version
@@ -51,6 +50,7 @@ async fn test_local_optimistic_concurrency_control() -> Result<()> {
         content: serde_json::json!({"version": 1}),
         schema: serde_json::json!({"type": "object"}),
         version: String::new(),
+        content_type: None,
     };
 
     // Create first version
@@ -62,6 +62,7 @@ async fn test_local_optimistic_concurrency_control() -> Result<()> {
         content: serde_json::json!({"version": 2}),
         schema: data1.schema.clone(),
         version: String::new(),
+        content_type: None,
     };
     backend.put(&key, &data2, Some(&retrieved.version)).await?;
 
@@ -84,6 +85,7 @@ async fn test_local_versioning() -> Result<()> {
             content: serde_json::json!({"version": i}),
             schema: serde_json::json!({"type": "object"}),
             version: String::new(),
+            content_type: None,
         };
 
         if i == 1 {
@@ -128,6 +130,7 @@ async fn test_local_delete_environment() -> Result<()> {
             content: serde_json::json!({"test": true}),
             schema: serde_json::json!({"type": "object"}),
             version: String::new(),
+            content_type: None,
         };
         backend.put(&key, &data, None).await?;
     }
@@ -237,6 +240,7 @@ async fn test_s3_put_and_get_config() -> Result<()> {
             "type": "object"
         }),
         version: String::new(),
+        content_type: None,
     };
 
     // Create new config
@@ -279,6 +283,7 @@ async fn test_s3_versioning() -> Result<()> {
                 "type": "object"
             }),
             version: String::new(),
+            content_type: None,
         };
 
         if i == 1 {
@@ -326,6 +331,7 @@ fn test_s3_config_construction() {
             access_key_id,
             secret_access_key,
             allow_http,
+            ..
         } => {
             assert_eq!(bucket, "test-bucket");
             assert_eq!(region, Some("us-east-1".to_string()));
@@ -343,7 +349,7 @@ fn test_local_config_construction() {
     let config = StorageConfig::local("./data");
 
     match config {
-        StorageConfig::Local { path } => {
+        StorageConfig::Local { path, .. } => {
             assert_eq!(path.to_str().unwrap(), "./data");
         }
         _ => panic!("Expected Local config"),
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The differences between two JSON documents, each located by a JSON Pointer
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfigDiff {
+    /// Values present only in the second document
+    pub added: Vec<DiffValue>,
+    /// Values present only in the first document
+    pub removed: Vec<DiffValue>,
+    /// Values present in both documents that differ
+    pub changed: Vec<DiffChange>,
+}
+
+/// A value found on only one side of a diff
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffValue {
+    pub path: String,
+    pub value: Value,
+}
+
+/// A value that differs between the two sides of a diff
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffChange {
+    pub path: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+impl ConfigDiff {
+    /// Whether the two documents were equal
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare `from` with `to`
+///
+/// Objects are compared key by key, recursively. Any other differing value,
+/// including an array or a value whose type changed, is reported as changed
+/// as a whole.
+pub fn diff(from: &Value, to: &Value) -> ConfigDiff {
+    let mut result = ConfigDiff::default();
+    diff_values("", from, to, &mut result);
+    result
+}
+
+fn diff_values(path: &str, from: &Value, to: &Value, result: &mut ConfigDiff) {
+    match (from, to) {
+        (Value::Object(from_map), Value::Object(to_map)) => {
+            for (key, from_value) in from_map {
+                let child = format!("{path}/{}", escape_pointer_token(key));
+                match to_map.get(key) {
+                    Some(to_value) => diff_values(&child, from_value, to_value, result),
+                    None => result.removed.push(DiffValue {
+                        path: child,
+                        value: from_value.clone(),
+                    }),
+                }
+            }
+            for (key, to_value) in to_map {
+                if !from_map.contains_key(key) {
+                    result.added.push(DiffValue {
+                        path: format!("{path}/{}", escape_pointer_token(key)),
+                        value: to_value.clone(),
+                    });
+                }
+            }
+        }
+        _ if from != to => result.changed.push(DiffChange {
+            path: path.to_string(),
+            from: from.clone(),
+            to: to.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Escape a key for use as a JSON Pointer (RFC 6901) reference token
+pub fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_equal_documents() {
+        let value = json!({"a": 1, "b": {"c": [1, 2]}});
+        assert!(diff(&value, &value).is_empty());
+    }
+
+    #[test]
+    fn test_nested_changes() {
+        let from = json!({"db": {"host": "a", "pool": {"min": 1, "max": 5}}, "old": true});
+        let to = json!({"db": {"host": "b", "pool": {"min": 1}, "a/b": 2}});
+
+        assert_eq!(
+            diff(&from, &to),
+            ConfigDiff {
+                added: vec![DiffValue {
+                    path: "/db/a~1b".to_string(),
+                    value: json!(2)
+                }],
+                removed: vec![
+                    DiffValue {
+                        path: "/db/pool/max".to_string(),
+                        value: json!(5)
+                    },
+                    DiffValue {
+                        path: "/old".to_string(),
+                        value: json!(true)
+                    },
+                ],
+                changed: vec![DiffChange {
+                    path: "/db/host".to_string(),
+                    from: json!("a"),
+                    to: json!("b")
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn test_type_changes() {
+        let result = diff(
+            &json!({"port": "80", "tls": {"on": true}}),
+            &json!({"port": 80, "tls": true}),
+        );
+
+        assert!(result.added.is_empty() && result.removed.is_empty());
+        assert_eq!(
+            result.changed,
+            vec![
+                DiffChange {
+                    path: "/port".to_string(),
+                    from: json!("80"),
+                    to: json!(80)
+                },
+                DiffChange {
+                    path: "/tls".to_string(),
+                    from: json!({"on": true}),
+                    to: json!(true)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arrays_compared_whole() {
+        let result = diff(
+            &json!({"hosts": ["a", "b"], "ports": [1]}),
+            &json!({"hosts": ["a"], "ports": [1]}),
+        );
+
+        assert_eq!(
+            result.changed,
+            vec![DiffChange {
+                path: "/hosts".to_string(),
+                from: json!(["a", "b"]),
+                to: json!(["a"])
+            }]
+        );
+        assert!(result.added.is_empty() && result.removed.is_empty());
+    }
+
+    #[test]
+    fn test_root_change() {
+        let result = diff(&json!([1]), &json!({"a": 1}));
+        assert_eq!(result.changed.len(), 1);
+        assert_eq!(result.changed[0].path, "");
+    }
+}
@@ -0,0 +1,78 @@
+use serde_json::{Map, Value};
+
+/// Delimiter used by [`flatten_json`] when none is given
+pub const DEFAULT_FLATTEN_DELIMITER: &str = ".";
+
+/// Flatten nested objects and arrays into a single-level object
+///
+/// Keys are the path segments joined with `delimiter`, with array elements
+/// addressed by index: `{"items": [{"name": "a"}]}` becomes
+/// `{"items.0.name": "a"}`. Empty objects and arrays are kept as values so
+/// they survive flattening. A non-container root is stored under the empty key.
+pub fn flatten_json(value: &Value, delimiter: &str) -> Map<String, Value> {
+    let mut flat = Map::new();
+    flatten_into(&mut flat, String::new(), value, delimiter);
+    flat
+}
+
+fn flatten_into(flat: &mut Map<String, Value>, prefix: String, value: &Value, delimiter: &str) {
+    let join = |segment: &str| {
+        if prefix.is_empty() {
+            segment.to_string()
+        } else {
+            format!("{prefix}{delimiter}{segment}")
+        }
+    };
+
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                flatten_into(flat, join(key), child, delimiter);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, child) in items.iter().enumerate() {
+                flatten_into(flat, join(&i.to_string()), child, delimiter);
+            }
+        }
+        leaf => {
+            flat.insert(prefix, leaf.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_flatten_nested_object() {
+        let value = json!({"database": {"host": "localhost", "pool": {"max": 10}}, "debug": true});
+
+        assert_eq!(
+            Value::Object(flatten_json(&value, DEFAULT_FLATTEN_DELIMITER)),
+            json!({"database.host": "localhost", "database.pool.max": 10, "debug": true})
+        );
+    }
+
+    #[test]
+    fn test_flatten_arrays_by_index() {
+        let value = json!({"items": [{"name": "a"}, {"name": "b"}], "tags": ["x"]});
+
+        assert_eq!(
+            Value::Object(flatten_json(&value, DEFAULT_FLATTEN_DELIMITER)),
+            json!({"items.0.name": "a", "items.1.name": "b", "tags.0": "x"})
+        );
+    }
+
+    #[test]
+    fn test_flatten_custom_delimiter_and_empty_containers() {
+        let value = json!({"a": {"b": 1, "empty": {}}, "none": []});
+
+        assert_eq!(
+            Value::Object(flatten_json(&value, "__")),
+            json!({"a__b": 1, "a__empty": {}, "none": []})
+        );
+    }
+}
@@ -1,5 +1,14 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
+
+mod diff;
+mod flatten;
+mod merge;
+
+pub use diff::{ConfigDiff, DiffChange, DiffValue, diff, escape_pointer_token};
+pub use flatten::{DEFAULT_FLATTEN_DELIMITER, flatten_json};
+pub use merge::merge_json;
 
 /// Structured key for identifying configurations
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -29,6 +38,28 @@ impl ConfigKey {
             self.application, self.environment, self.config_name
         )
     }
+
+    /// Check that every component is a single, ordinary path segment: not
+    /// empty, not `.` or `..`, and free of slashes
+    pub fn validate(&self) -> Result<(), ParseConfigKeyError> {
+        for component in [&self.application, &self.environment, &self.config_name] {
+            if component.is_empty()
+                || component == "."
+                || component == ".."
+                || component.contains(['/', '\\'])
+            {
+                return Err(ParseConfigKeyError(format!(
+                    "{component:?} is not allowed as a key component"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse an `app/env/config` path as produced by [`to_path`](Self::to_path)
+    pub fn from_path(path: &str) -> Result<Self, ParseConfigKeyError> {
+        path.parse()
+    }
 }
 
 impl fmt::Display for ConfigKey {
@@ -37,12 +68,69 @@ impl fmt::Display for ConfigKey {
     }
 }
 
+impl FromStr for ConfigKey {
+    type Err = ParseConfigKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split('/').collect::<Vec<_>>().as_slice() {
+            [app, env, config] => {
+                let key = Self::new(*app, *env, *config);
+                key.validate()?;
+                Ok(key)
+            }
+            segments => Err(ParseConfigKeyError(format!(
+                "'{s}' has {} segments, expected app/env/config",
+                segments.len()
+            ))),
+        }
+    }
+}
+
+/// A string that is not an `app/env/config` path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseConfigKeyError(String);
+
+impl fmt::Display for ParseConfigKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid config key: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseConfigKeyError {}
+
+/// Content type assumed when a configuration doesn't declare one
+pub const DEFAULT_CONTENT_TYPE: &str = "application/json";
+
 /// Configuration data with required schema and version
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigData {
     pub content: serde_json::Value,
     pub schema: serde_json::Value, // Schema is now required
     pub version: String,
+    /// MIME type of the content; `None` means [`DEFAULT_CONTENT_TYPE`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+impl ConfigData {
+    /// The declared content type, falling back to [`DEFAULT_CONTENT_TYPE`]
+    pub fn content_type(&self) -> &str {
+        self.content_type.as_deref().unwrap_or(DEFAULT_CONTENT_TYPE)
+    }
+
+    /// Layer `other` on top of this config, e.g. an environment override on a base
+    ///
+    /// Content is combined with [`merge_json`]; the schema, version and content
+    /// type are taken from `other`, the topmost layer.
+    #[must_use]
+    pub fn merge(&self, other: &ConfigData) -> ConfigData {
+        ConfigData {
+            content: merge_json(&self.content, &other.content),
+            schema: other.schema.clone(),
+            version: other.version.clone(),
+            content_type: other.content_type.clone(),
+        }
+    }
 }
 
 /// Version information for a configuration
@@ -50,6 +138,71 @@ pub struct ConfigData {
 pub struct VersionInfo {
     pub version: String,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Marked as bad, so it is not offered as a rollback target
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bad: bool,
+    /// Size in bytes of the stored content
+    #[serde(default)]
+    pub data_size: usize,
+    /// Whether a schema was stored alongside the content
+    #[serde(default)]
+    pub has_schema: bool,
+    /// Hex SHA-256 of the stored content, empty if the server has none
+    #[serde(default)]
+    pub content_hash: String,
+    /// Why the version was written, if the writer said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_message: Option<String>,
+    /// Who wrote the version, if the server knows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+/// A configuration in a listing; the version fields are only filled in for
+/// detailed listings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConfigSummary {
+    #[serde(flatten)]
+    pub key: ConfigKey,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A specific version of a configuration that another configuration was derived from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConfigOrigin {
+    #[serde(flatten)]
+    pub key: ConfigKey,
+    pub version: String,
+}
+
+/// A single RFC 6902 JSON Patch operation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add {
+        path: String,
+        value: serde_json::Value,
+    },
+    Remove {
+        path: String,
+    },
+    Replace {
+        path: String,
+        value: serde_json::Value,
+    },
+}
+
+/// One version in a configuration's timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineStep {
+    pub version: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub content: serde_json::Value,
+    /// Changes from the previous version; `None` for the first version
+    pub diff: Option<Vec<PatchOperation>>,
 }
 
 #[cfg(test)]
@@ -57,6 +210,47 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_config_key_parse_round_trip() -> Result<(), ParseConfigKeyError> {
+        let key = ConfigKey::new("myapp", "prod", "database");
+        assert_eq!(key.to_path().parse::<ConfigKey>()?, key);
+        assert_eq!(ConfigKey::from_path("myapp/prod/database")?, key);
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_key_parse_rejects_bad_paths() {
+        for path in [
+            "",
+            "myapp/prod",
+            "myapp/prod/db/extra",
+            "myapp//db",
+            "/prod/db",
+        ] {
+            assert!(path.parse::<ConfigKey>().is_err(), "{path} parsed");
+        }
+        let err = ConfigKey::from_path("a/b").err();
+        assert_eq!(
+            err.map(|e| e.to_string()),
+            Some("Invalid config key: 'a/b' has 2 segments, expected app/env/config".to_string())
+        );
+    }
+
+    #[test]
+    fn test_config_key_validate_rejects_traversal() {
+        for (app, env, config) in [
+            ("../../etc", "prod", "db"),
+            ("myapp", "..", "db"),
+            ("myapp", "prod", "."),
+            ("myapp", "prod", "a/b"),
+            ("myapp", "prod", "..\\secrets"),
+            ("", "prod", "db"),
+        ] {
+            assert!(ConfigKey::new(app, env, config).validate().is_err());
+        }
+        assert!(ConfigKey::new("myapp", "prod", "db.v2").validate().is_ok());
+    }
+
     #[test]
     fn test_config_key_new() {
         let key = ConfigKey::new("app", "dev", "config");
@@ -92,6 +286,7 @@ mod tests {
             content: json!({"host": "localhost", "port": 5432}),
             schema: json!({"type": "object"}),
             version: "v1".to_string(),
+            content_type: None,
         };
 
         let json = serde_json::to_string(&data)?;
@@ -100,6 +295,116 @@ mod tests {
         assert_eq!(data.content, deserialized.content);
         assert_eq!(data.schema, deserialized.schema);
         assert_eq!(data.version, deserialized.version);
+        assert_eq!(deserialized.content_type, None);
+        assert!(!json.contains("content_type"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_data_with_content_type() -> Result<(), Box<dyn std::error::Error>> {
+        let data = ConfigData {
+            content: json!({"raw": "key: value"}),
+            schema: json!({"type": "object"}),
+            version: "v2".to_string(),
+            content_type: Some("application/yaml".to_string()),
+        };
+
+        let json = serde_json::to_string(&data)?;
+        let deserialized: ConfigData = serde_json::from_str(&json)?;
+
+        assert_eq!(
+            deserialized.content_type.as_deref(),
+            Some("application/yaml")
+        );
+        assert_eq!(deserialized.content_type(), "application/yaml");
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_data_without_content_type_defaults() -> Result<(), Box<dyn std::error::Error>> {
+        let json = r#"{"content": {"a": 1}, "schema": {"type": "object"}, "version": "v1"}"#;
+        let data: ConfigData = serde_json::from_str(json)?;
+
+        assert_eq!(data.content_type, None);
+        assert_eq!(data.content_type(), DEFAULT_CONTENT_TYPE);
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_data_merge() {
+        let base = ConfigData {
+            content: json!({"db": {"host": "localhost", "pool": 5}, "debug": true}),
+            schema: json!({"type": "object"}),
+            version: "v4".to_string(),
+            content_type: None,
+        };
+        let overlay = ConfigData {
+            content: json!({"db": {"host": "db.prod"}, "debug": null}),
+            schema: json!({"type": "object", "required": ["db"]}),
+            version: "v2".to_string(),
+            content_type: None,
+        };
+
+        let merged = base.merge(&overlay);
+        assert_eq!(
+            merged.content,
+            json!({"db": {"host": "db.prod", "pool": 5}})
+        );
+        assert_eq!(merged.schema, overlay.schema);
+        assert_eq!(merged.version, "v2");
+    }
+
+    #[test]
+    fn test_config_origin_serialization() -> Result<(), Box<dyn std::error::Error>> {
+        let origin = ConfigOrigin {
+            key: ConfigKey::new("app", "dev", "config"),
+            version: "v3".to_string(),
+        };
+
+        let json = serde_json::to_value(&origin)?;
+        assert_eq!(
+            json,
+            json!({
+                "application": "app",
+                "environment": "dev",
+                "config_name": "config",
+                "version": "v3"
+            })
+        );
+
+        let deserialized: ConfigOrigin = serde_json::from_value(json)?;
+        assert_eq!(deserialized, origin);
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_operation_serialization() -> Result<(), Box<dyn std::error::Error>> {
+        let ops = vec![
+            PatchOperation::Add {
+                path: "/a".to_string(),
+                value: json!(1),
+            },
+            PatchOperation::Remove {
+                path: "/b".to_string(),
+            },
+            PatchOperation::Replace {
+                path: "/c/d".to_string(),
+                value: json!("x"),
+            },
+        ];
+
+        let json = serde_json::to_value(&ops)?;
+        assert_eq!(
+            json,
+            json!([
+                {"op": "add", "path": "/a", "value": 1},
+                {"op": "remove", "path": "/b"},
+                {"op": "replace", "path": "/c/d", "value": "x"}
+            ])
+        );
+
+        let deserialized: Vec<PatchOperation> = serde_json::from_value(json)?;
+        assert_eq!(deserialized, ops);
         Ok(())
     }
 
@@ -109,6 +414,12 @@ mod tests {
         let version = VersionInfo {
             version: "v2".to_string(),
             timestamp: now,
+            bad: false,
+            data_size: 42,
+            has_schema: true,
+            content_hash: "ab".repeat(32),
+            change_message: Some("Raise the limit".to_string()),
+            author: Some("alice".to_string()),
         };
 
         let json = serde_json::to_string(&version)?;
@@ -116,6 +427,11 @@ mod tests {
 
         assert_eq!(version.version, deserialized.version);
         assert_eq!(version.timestamp, deserialized.timestamp);
+        assert_eq!(deserialized.data_size, 42);
+        assert!(deserialized.has_schema);
+        assert_eq!(deserialized.content_hash, version.content_hash);
+        assert_eq!(deserialized.change_message, version.change_message);
+        assert_eq!(deserialized.author, version.author);
         Ok(())
     }
 }
@@ -0,0 +1,98 @@
+use serde_json::Value;
+
+/// Deep-merge `overlay` onto `base`, with the overlay winning on conflicts
+///
+/// Objects are merged key by key, recursively. A `null` in the overlay deletes
+/// the key from the result. Any other overlay value, arrays included, replaces
+/// the base value wholesale; arrays are never concatenated.
+pub fn merge_json(base: &Value, overlay: &Value) -> Value {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            let mut merged = base_map.clone();
+            for (key, overlay_value) in overlay_map {
+                if overlay_value.is_null() {
+                    merged.remove(key);
+                    continue;
+                }
+                let value = match base_map.get(key) {
+                    Some(base_value) => merge_json(base_value, overlay_value),
+                    None => strip_nulls(overlay_value),
+                };
+                merged.insert(key.clone(), value);
+            }
+            Value::Object(merged)
+        }
+        _ => strip_nulls(overlay),
+    }
+}
+
+/// Drop null-valued object members, which mean "delete" inside an overlay
+fn strip_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), strip_nulls(v)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_nested_objects_merge() {
+        let base = json!({"db": {"host": "localhost", "port": 5432}, "debug": false});
+        let overlay = json!({"db": {"host": "db.prod"}, "debug": true});
+
+        assert_eq!(
+            merge_json(&base, &overlay),
+            json!({"db": {"host": "db.prod", "port": 5432}, "debug": true})
+        );
+    }
+
+    #[test]
+    fn test_arrays_are_replaced() {
+        let base = json!({"hosts": ["a", "b"]});
+        let overlay = json!({"hosts": ["c"]});
+
+        assert_eq!(merge_json(&base, &overlay), json!({"hosts": ["c"]}));
+    }
+
+    #[test]
+    fn test_type_conflicts_take_overlay() {
+        let base = json!({"limit": {"max": 10}, "mode": "fast"});
+        let overlay = json!({"limit": 5, "mode": {"name": "slow"}});
+
+        assert_eq!(
+            merge_json(&base, &overlay),
+            json!({"limit": 5, "mode": {"name": "slow"}})
+        );
+    }
+
+    #[test]
+    fn test_null_deletes_keys() {
+        let base = json!({"keep": 1, "drop": 2, "nested": {"drop": 3, "keep": 4}});
+        let overlay = json!({"drop": null, "nested": {"drop": null}, "absent": null});
+
+        assert_eq!(
+            merge_json(&base, &overlay),
+            json!({"keep": 1, "nested": {"keep": 4}})
+        );
+    }
+
+    #[test]
+    fn test_new_subtrees_have_nulls_removed() {
+        let base = json!({});
+        let overlay = json!({"feature": {"enabled": true, "legacy": null}});
+
+        assert_eq!(
+            merge_json(&base, &overlay),
+            json!({"feature": {"enabled": true}})
+        );
+    }
+}